@@ -1,16 +1,15 @@
 #[macro_use]
 extern crate stockton_input_codegen;
 
-use std::collections::BTreeMap;
 use stockton_input::Action;
-use stockton_input::{Axis, Button, InputManager, InputMutation};
+use stockton_input::{Axis, Button, InputManager, InputMutation, Key};
 
 #[derive(InputManager, Default, Debug, Clone)]
 struct MovementInputs {
-    #[axis]
+    #[axis(label = "Move Forward/Back", min = -1.0, max = 1.0)]
     vertical: Axis,
 
-    #[axis]
+    #[axis(label = "Move Left/Right", min = -1.0, max = 1.0)]
     horizontal: Axis,
 
     #[button]
@@ -18,48 +17,59 @@ struct MovementInputs {
 }
 
 const TEST_ACTIONS: [Action; 10] = [
-    Action::KeyPress(1),
-    Action::KeyRelease(1),
-    Action::KeyPress(2),
-    Action::KeyPress(3),
-    Action::KeyRelease(2),
-    Action::KeyRelease(3),
-    Action::KeyPress(4),
-    Action::KeyPress(5),
-    Action::KeyRelease(4),
-    Action::KeyRelease(5),
+    Action::KeyPress(Key::W as u32),
+    Action::KeyRelease(Key::W as u32),
+    Action::KeyPress(Key::A as u32),
+    Action::KeyPress(Key::S as u32),
+    Action::KeyRelease(Key::A as u32),
+    Action::KeyRelease(Key::S as u32),
+    Action::KeyPress(Key::D as u32),
+    Action::KeyPress(Key::Space as u32),
+    Action::KeyRelease(Key::D as u32),
+    Action::KeyRelease(Key::Space as u32),
 ];
 
-// For testing,   1 = w     2 = a
-//                3 = s     4 = d
-//                5 = jump
 fn main() {
-    let mut action_schema = BTreeMap::new();
-    action_schema.insert(
-        1,
-        (MovementInputsFields::Vertical, InputMutation::PositiveAxis),
-    );
-    action_schema.insert(
-        3,
-        (MovementInputsFields::Vertical, InputMutation::NegativeAxis),
-    );
-    action_schema.insert(
-        4,
-        (
+    let mut manager = MovementInputsManager::builder()
+        .bind(
+            Key::W,
+            MovementInputsFields::Vertical,
+            InputMutation::PositiveAxis,
+        )
+        .bind(
+            Key::S,
+            MovementInputsFields::Vertical,
+            InputMutation::NegativeAxis,
+        )
+        .bind(
+            Key::D,
             MovementInputsFields::Horizontal,
             InputMutation::PositiveAxis,
-        ),
-    );
-    action_schema.insert(
-        2,
-        (
+        )
+        .bind(
+            Key::A,
             MovementInputsFields::Horizontal,
             InputMutation::NegativeAxis,
-        ),
-    );
-    action_schema.insert(5, (MovementInputsFields::Jump, InputMutation::MapToButton));
+        )
+        .bind(
+            Key::Space,
+            MovementInputsFields::Jump,
+            InputMutation::MapToButton,
+        )
+        .build();
 
-    let mut manager = MovementInputsManager::new(action_schema);
+    for field in [
+        MovementInputsFields::Vertical,
+        MovementInputsFields::Horizontal,
+        MovementInputsFields::Jump,
+    ] {
+        println!(
+            "{} ({}) is bound to {:?}",
+            field.label(),
+            field.name(),
+            manager.keycodes_for(field)
+        );
+    }
 
     for action in TEST_ACTIONS.iter() {
         pretty_print_state(&manager.inputs);
@@ -69,10 +79,10 @@ fn main() {
 }
 
 fn pretty_print_state(inputs: &MovementInputs) {
-    if *inputs.vertical != 0 {
+    if *inputs.vertical != 0.0 {
         print!("vertical = {}  ", *inputs.vertical);
     }
-    if *inputs.horizontal != 0 {
+    if *inputs.horizontal != 0.0 {
         print!("horizontal = {}  ", *inputs.horizontal);
     }
     if inputs.jump.is_down() {
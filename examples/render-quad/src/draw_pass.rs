@@ -8,9 +8,8 @@ use hal::{
     image::Layout,
     pass::Attachment,
     pso::{
-        BlendDesc, BlendOp, BlendState, ColorBlendDesc, ColorMask, DepthStencilDesc, Face, Factor,
-        FrontFace, InputAssemblerDesc, LogicOp, PolygonMode, Primitive, Rasterizer, State,
-        VertexInputRate,
+        DepthStencilDesc, Face, FrontFace, InputAssemblerDesc, PolygonMode, Primitive, Rasterizer,
+        State, VertexInputRate,
     },
 };
 use legion::{Entity, IntoQuery};
@@ -21,14 +20,16 @@ use std::{
 use stockton_skeleton::{
     buffers::draw::DrawBuffers,
     builders::{
-        AttachmentSpec, CompletePipeline, PipelineSpecBuilder, RenderpassSpec, ShaderDesc,
-        ShaderKind, VertexBufferSpec, VertexPrimitiveAssemblerSpec,
+        AttachmentSpec, BlendMode, CompletePipeline, PipelineSpecBuilder, RenderpassSpec,
+        ShaderDesc, ShaderKind, VertexLayout, VertexPrimitiveAssemblerSpec,
+    },
+    draw_passes::util::{
+        begin_render_pass, end_render_pass, SurfaceResources, TargetSpecificResources,
     },
-    draw_passes::util::TargetSpecificResources,
     mem::{DataPool, StagingPool},
     queue_negotiator::QueueFamilyNegotiator,
     types::*,
-    DrawPass, IntoDrawPass, PassPosition, RenderingContext, Session,
+    DrawPass, FrameContext, IntoDrawPass, PassPosition, RenderingContext, Session,
 };
 
 use crate::ExampleState;
@@ -38,6 +39,12 @@ use crate::ExampleState;
 #[repr(C)]
 struct Vertex(pub Vector2, pub Vector3);
 
+impl VertexLayout for Vertex {
+    fn attributes() -> Vec<Format> {
+        vec![Format::Rg32Sfloat, Format::Rgb32Sfloat]
+    }
+}
+
 /// An example draw pass
 pub struct ExampleDrawPass<'a> {
     /// Index and vertex buffer pair
@@ -62,28 +69,35 @@ impl<'a, P: PassPosition> DrawPass<P> for ExampleDrawPass<'a> {
         session: &Session,
         img_view: &ImageViewT,
         cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
     ) -> anyhow::Result<()> {
+        // Get state
+        let (state,) = <(&ExampleState,)>::query().get(&session.world, self.state_ent)?;
+
+        // Write our example into the buffers for this frame
+        // There's one set of buffers per frame in flight, so we don't overwrite anything the GPU might still be reading
+        let buffers = self.draw_buffers.for_frame(&frame);
+        buffers.index_buffer[0] = (0, 1, 2);
+        buffers.vertex_buffer[0] = Vertex(Vector2::new(0.5, 0.5), state.color());
+        buffers.vertex_buffer[1] = Vertex(Vector2::new(0.0, -0.5), state.color());
+        buffers.vertex_buffer[2] = Vertex(Vector2::new(-0.5, 0.5), state.color());
+
         // Commit any changes to our vertex buffers
         // We queue this first so that it's executed before any draw commands
-        self.draw_buffers
-            .vertex_buffer
-            .record_commit_cmds(cmd_buffer)?;
-        self.draw_buffers
-            .index_buffer
-            .record_commit_cmds(cmd_buffer)?;
+        buffers.vertex_buffer.record_commit_cmds(cmd_buffer)?;
+        buffers.index_buffer.record_commit_cmds(cmd_buffer)?;
 
         // Get framebuffer
         let fb = self.surface_resources.framebuffers.get_next();
 
-        // Get state
-        let (state,) = <(&ExampleState,)>::query().get(&session.world, self.state_ent)?;
-
         // Begin render pass & bind everything needed
         unsafe {
-            cmd_buffer.begin_render_pass(
+            begin_render_pass(
+                cmd_buffer,
                 &self.surface_resources.pipeline.renderpass,
                 fb,
-                self.surface_resources.pipeline.render_area,
+                // Only draw to the region we've been given
+                frame.viewport,
                 vec![RenderAttachmentInfo {
                     image_view: img_view,
                     clear_value: ClearValue {
@@ -96,12 +110,15 @@ impl<'a, P: PassPosition> DrawPass<P> for ExampleDrawPass<'a> {
                 SubpassContents::Inline,
             );
             cmd_buffer.bind_graphics_pipeline(&self.surface_resources.pipeline.pipeline);
+            self.surface_resources
+                .pipeline
+                .set_viewport(cmd_buffer, frame.viewport);
 
             // Bind buffers
             cmd_buffer.bind_vertex_buffers(
                 0,
                 once((
-                    self.draw_buffers.vertex_buffer.get_buffer(),
+                    buffers.vertex_buffer.get_buffer(),
                     SubRange {
                         offset: 0,
                         size: None,
@@ -109,28 +126,23 @@ impl<'a, P: PassPosition> DrawPass<P> for ExampleDrawPass<'a> {
                 )),
             );
             cmd_buffer.bind_index_buffer(
-                self.draw_buffers.index_buffer.get_buffer(),
+                buffers.index_buffer.get_buffer(),
                 SubRange {
                     offset: 0,
                     size: None,
                 },
-                hal::IndexType::U16,
+                buffers.index_type(),
             );
         }
 
-        // Draw an example
-        self.draw_buffers.index_buffer[0] = (0, 1, 2);
-        self.draw_buffers.vertex_buffer[0] = Vertex(Vector2::new(0.5, 0.5), state.color());
-        self.draw_buffers.vertex_buffer[1] = Vertex(Vector2::new(0.0, -0.5), state.color());
-        self.draw_buffers.vertex_buffer[2] = Vertex(Vector2::new(-0.5, 0.5), state.color());
-
+        // Draw our example
         unsafe {
             cmd_buffer.draw_indexed(0..3, 0, 0..1);
         }
 
         // Remember to clean up afterwards!
         unsafe {
-            cmd_buffer.end_render_pass();
+            end_render_pass(cmd_buffer);
         }
 
         Ok(())
@@ -150,21 +162,9 @@ impl<'a, P: PassPosition> DrawPass<P> for ExampleDrawPass<'a> {
         _session: &Session,
         context: &mut RenderingContext,
     ) -> Result<Self> {
-        // We need to make sure there's never an invalid value for self.surface_resources,
-        // and that we deactivate everything in case of an error (since we'll be dropped in that case).
-        let new_resources = match SurfaceDependentResources::new::<P>(context) {
-            Ok(x) => x,
-            Err(e) => {
-                <Self as DrawPass<P>>::deactivate(self, context)?;
-
-                return Err(e);
-            }
-        };
-
-        let old_resources = self.surface_resources;
-        self.surface_resources = new_resources;
-
-        match old_resources.deactivate(context) {
+        // Only our surface resources need recreated, everything else is kept as is.
+        // We need to deactivate everything in case of an error though, since we'll be dropped in that case.
+        match self.surface_resources.recreate::<P>(context, &()) {
             Ok(_) => Ok(self),
             Err(e) => {
                 <Self as DrawPass<P>>::deactivate(self, context)?;
@@ -181,7 +181,7 @@ impl<'a, P: PassPosition> IntoDrawPass<ExampleDrawPass<'a>, P> for ExampleDrawPa
         _session: &mut Session,
         context: &mut RenderingContext,
     ) -> Result<ExampleDrawPass<'a>> {
-        let surface_resources = SurfaceDependentResources::new::<P>(context)?;
+        let surface_resources = SurfaceDependentResources::create::<P>(context, &())?;
         let draw_buffers =
             match DrawBuffers::from_context(context).context("Error creating draw buffers") {
                 Ok(x) => x,
@@ -213,8 +213,11 @@ struct SurfaceDependentResources {
     pub framebuffers: TargetSpecificResources<FramebufferT>,
 }
 
-impl SurfaceDependentResources {
-    pub fn new<P: PassPosition>(context: &mut RenderingContext) -> Result<Self> {
+impl SurfaceResources for SurfaceDependentResources {
+    /// We don't need anything else to create our resources
+    type Shared = ();
+
+    fn create<P: PassPosition>(context: &mut RenderingContext, _shared: &()) -> Result<Self> {
         let (pipeline, framebuffers) = {
             // Our graphics pipeline
             // Vulkan has a lot of config, so this is basically always going to be a big builder block
@@ -233,28 +236,10 @@ impl SurfaceDependentResources {
                     depth_bounds: false,
                     stencil: None,
                 })
-                .blender(BlendDesc {
-                    logic_op: Some(LogicOp::Copy),
-                    targets: vec![ColorBlendDesc {
-                        mask: ColorMask::ALL,
-                        blend: Some(BlendState {
-                            color: BlendOp::Add {
-                                src: Factor::SrcAlpha,
-                                dst: Factor::OneMinusSrcAlpha,
-                            },
-                            alpha: BlendOp::Add {
-                                src: Factor::SrcAlpha,
-                                dst: Factor::OneMinusSrcAlpha,
-                            },
-                        }),
-                    }],
-                })
+                .blender(BlendMode::AlphaBlend)
                 .primitive_assembler(VertexPrimitiveAssemblerSpec::with_buffers(
                     InputAssemblerDesc::new(Primitive::TriangleList),
-                    vec![VertexBufferSpec {
-                        attributes: vec![Format::Rg32Sfloat, Format::Rgb32Sfloat],
-                        rate: VertexInputRate::Vertex,
-                    }],
+                    vec![Vertex::buffer_spec(VertexInputRate::Vertex)],
                 ))
                 .shader_vertex(ShaderDesc {
                     source: include_str!("./data/shader.vert").to_string(),
@@ -266,6 +251,9 @@ impl SurfaceDependentResources {
                     entry: "main".to_string(),
                     kind: ShaderKind::Fragment,
                 })
+                // We set these to the viewport we're given each frame
+                .dynamic_viewport(true)
+                .dynamic_scissor(true)
                 .renderpass(RenderpassSpec {
                     colors: vec![AttachmentSpec {
                         attachment: Attachment {
@@ -294,7 +282,12 @@ impl SurfaceDependentResources {
             let mut device = context.lock_device()?;
 
             let pipeline = pipeline_spec
-                .build(&mut device, context.properties().extent, empty())
+                .build(
+                    &mut device,
+                    context.properties().extent,
+                    &context.physical_device_properties().limits,
+                    empty(),
+                )
                 .context("Error building pipeline")?;
 
             // Our framebuffers just have the swapchain framebuffer attachment
@@ -308,7 +301,7 @@ impl SurfaceDependentResources {
                         context.properties().extent,
                     )?)
                 },
-                context.properties().image_count as usize,
+                context.frames_in_flight(),
             )?;
 
             (pipeline, framebuffers)
@@ -320,7 +313,7 @@ impl SurfaceDependentResources {
         })
     }
 
-    pub fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
         unsafe {
             let mut device = context.lock_device()?;
             for fb in self.framebuffers.dissolve() {
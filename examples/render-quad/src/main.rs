@@ -7,7 +7,7 @@ extern crate legion;
 
 use anyhow::{Context, Result};
 use log::warn;
-use stockton_skeleton::{error::full_error_display, Renderer, Session};
+use stockton_skeleton::{error::full_error_display, Renderer, RendererOptions, Session};
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -63,11 +63,12 @@ fn try_main() -> Result<()> {
     let state_ent = session.world.push((ExampleState::default(),));
 
     // Create the renderer
-    let renderer =
-        Renderer::<Dp<'static>>::new(&window, &mut session, ExampleDrawPassConfig { state_ent })?;
-
-    // We'll be moving it in/out of here, so we need an Option for safety.
-    let mut renderer = Some(renderer);
+    let mut renderer = Renderer::<Dp<'static>>::new(
+        &window,
+        &mut session,
+        ExampleDrawPassConfig { state_ent },
+        RendererOptions::default(),
+    )?;
 
     // Done loading - This is our main loop.
     // It just communicates events to the session and continuously ticks
@@ -79,16 +80,11 @@ fn try_main() -> Result<()> {
             session.do_update();
 
             // Render
-            let r = renderer.take().unwrap();
-            match r.render(&session) {
-                Ok(r) => {
-                    renderer = Some(r);
-                }
-                Err(e) => {
-                    println!("Error drawing: {}", full_error_display(e));
-
-                    *flow = ControlFlow::Exit;
-                }
+            // If this fails, the renderer is poisoned so we need to stop.
+            if let Err(e) = renderer.render_in_place(&session) {
+                println!("Error drawing: {}", full_error_display(e));
+
+                *flow = ControlFlow::Exit;
             }
         }
         Event::WindowEvent {
@@ -96,16 +92,18 @@ fn try_main() -> Result<()> {
             event: WindowEvent::Resized(_),
         } => {
             // (Attempt) resize
-            let r = renderer.take().unwrap();
-            match r.recreate_surface(&session) {
-                Ok(r) => {
-                    renderer = Some(r);
-                }
-                Err(e) => {
-                    println!("Error resizing: {}", full_error_display(e));
-
-                    *flow = ControlFlow::Exit;
-                }
+            if let Err(e) = renderer.recreate_surface_in_place(&session) {
+                println!("Error resizing: {}", full_error_display(e));
+
+                *flow = ControlFlow::Exit;
+            }
+        }
+        Event::WindowEvent {
+            window_id: _,
+            event: WindowEvent::ScaleFactorChanged { scale_factor, .. },
+        } => {
+            if !renderer.is_poisoned() {
+                renderer.set_pixels_per_point(scale_factor as f32);
             }
         }
         _ => (),
@@ -258,6 +258,11 @@ where
         self.max_chunk_size / MIN_BLOCKS_PER_CHUNK as u64
     }
 
+    /// Number of chunks currently allocated, including chunks that are themselves blocks of larger chunks.
+    pub fn chunk_count(&self) -> usize {
+        self.sizes.values().map(|entry| entry.chunks.len()).sum()
+    }
+
     /// Allocate memory chunk from device.
     fn alloc_chunk_from_device(
         &self,
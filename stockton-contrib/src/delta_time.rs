@@ -1,25 +1,162 @@
-use std::time::Instant;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The number of frame times kept by [`Timing::default`]
+pub const DEFAULT_HISTORY_LEN: usize = 120;
 
 #[derive(Debug, Clone)]
 pub struct Timing {
     pub delta_time: f32,
 
     pub(crate) last_frame_start: Instant,
+
+    /// The most recent frame times, oldest first
+    history: VecDeque<Duration>,
+
+    /// The most frame times to keep in `history`
+    history_len: usize,
 }
 
-impl Default for Timing {
-    fn default() -> Self {
+impl Timing {
+    /// Create a new timing resource, keeping the last `history_len` frame times.
+    pub fn new(history_len: usize) -> Self {
         Timing {
             delta_time: 0.0,
 
             last_frame_start: Instant::now(),
+            history: VecDeque::with_capacity(history_len),
+            history_len,
         }
     }
+
+    /// Record that a frame took the given time, updating `delta_time` and the history.
+    pub fn record_frame(&mut self, delta: Duration) {
+        self.delta_time = delta.as_secs_f32();
+
+        if self.history_len == 0 {
+            return;
+        }
+        if self.history.len() == self.history_len {
+            self.history.pop_front();
+        }
+        self.history.push_back(delta);
+    }
+
+    /// Get the last `n` frame times, oldest first.
+    /// This will be shorter than `n` if not enough frames have been recorded, or `n` is more than the history length.
+    pub fn last_n(&self, n: usize) -> impl Iterator<Item = Duration> + '_ {
+        self.history
+            .range(self.history.len() - n.min(self.history.len())..)
+            .copied()
+    }
+
+    /// The average frames per second over the history, or 0 if no frames have been recorded.
+    pub fn average_fps(&self) -> f32 {
+        fps_of(self.history.iter())
+    }
+
+    /// The average frames per second of the slowest 1% of frames in the history, or 0 if no frames have been recorded.
+    /// This is useful for spotting stutter, which won't show up much in [`Self::average_fps`].
+    pub fn one_percent_low(&self) -> f32 {
+        let mut sorted: Vec<Duration> = self.history.iter().copied().collect();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+
+        let count = sorted.len().div_ceil(100);
+        fps_of(sorted[..count].iter())
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::new(DEFAULT_HISTORY_LEN)
+    }
+}
+
+/// Get the average frames per second, given a list of frame times.
+fn fps_of<'a>(frames: impl ExactSizeIterator<Item = &'a Duration>) -> f32 {
+    let count = frames.len();
+    let total: Duration = frames.sum();
+    if total == Duration::from_secs(0) {
+        return 0.0;
+    }
+
+    count as f32 / total.as_secs_f32()
 }
 
 #[system]
 pub fn update_deltatime(#[resource] timing: &mut Timing) {
     let now = Instant::now();
-    timing.delta_time = now.duration_since(timing.last_frame_start).as_secs_f32();
+    timing.record_frame(now.duration_since(timing.last_frame_start));
     timing.last_frame_start = now;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(ms: &[u64]) -> Vec<Duration> {
+        ms.iter().map(|ms| Duration::from_millis(*ms)).collect()
+    }
+
+    #[test]
+    fn no_frames() {
+        let timing = Timing::new(10);
+
+        assert_eq!(timing.average_fps(), 0.0);
+        assert_eq!(timing.one_percent_low(), 0.0);
+        assert_eq!(timing.last_n(5).count(), 0);
+    }
+
+    #[test]
+    fn average_and_one_percent_low() {
+        let mut timing = Timing::new(200);
+        // 198 frames at 100fps, with 2 stutters at 10fps
+        for _ in 0..99 {
+            timing.record_frame(Duration::from_millis(10));
+        }
+        timing.record_frame(Duration::from_millis(100));
+        for _ in 0..99 {
+            timing.record_frame(Duration::from_millis(10));
+        }
+        timing.record_frame(Duration::from_millis(100));
+
+        assert_eq!(timing.delta_time, 0.1);
+        assert!((timing.average_fps() - 200.0 / 2.18).abs() < 1e-3);
+        assert!((timing.one_percent_low() - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn one_percent_low_rounds_up() {
+        let mut timing = Timing::new(10);
+        for delta in millis(&[10, 20, 50, 10]) {
+            timing.record_frame(delta);
+        }
+
+        // Less than 100 frames still counts the slowest one
+        assert!((timing.one_percent_low() - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn history_drops_oldest_frames() {
+        let mut timing = Timing::new(3);
+        for delta in millis(&[1, 2, 3, 4, 5]) {
+            timing.record_frame(delta);
+        }
+
+        assert_eq!(timing.last_n(10).collect::<Vec<_>>(), millis(&[3, 4, 5]));
+        assert_eq!(timing.last_n(2).collect::<Vec<_>>(), millis(&[4, 5]));
+        assert!((timing.average_fps() - 250.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn no_history() {
+        let mut timing = Timing::new(0);
+        timing.record_frame(Duration::from_millis(16));
+
+        assert_eq!(timing.delta_time, 0.016);
+        assert_eq!(timing.last_n(1).count(), 0);
+        assert_eq!(timing.average_fps(), 0.0);
+    }
+}
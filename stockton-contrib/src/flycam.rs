@@ -11,9 +11,28 @@ pub trait FlycamInput {
     fn get_z_axis(&self) -> &Axis;
 }
 
+/// Something the flycam can collide with, usually the map.
+pub trait FlycamCollider {
+    /// Sweep a movement of `delta` (in world space) starting at `start`, returning how far can actually be moved.
+    /// This should stop or slide along anything in the way.
+    fn sweep(&self, start: Vector3, delta: Vector3) -> Vector3;
+}
+
+/// How a flycam moves through the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlycamMode {
+    /// Move freely, ignoring anything in the way
+    #[default]
+    Noclip,
+
+    /// Stop or slide at anything in the way, using the [`FlycamCollider`] given to [`flycam_collide_move_system`]
+    Collide,
+}
+
 pub struct FlycamControlled {
     pub speed: f32,
     pub sensitivity: f32,
+    pub mode: FlycamMode,
 }
 
 impl FlycamControlled {
@@ -21,10 +40,39 @@ impl FlycamControlled {
         FlycamControlled {
             speed,
             sensitivity: (2.0 * PI) / pixels_per_360,
+            mode: FlycamMode::default(),
         }
     }
+
+    /// Set the mode this flycam moves in.
+    pub fn with_mode(mut self, mode: FlycamMode) -> Self {
+        self.mode = mode;
+        self
+    }
 }
 
+/// Get the movement for this frame, relative to the camera.
+fn flycam_delta<T>(manager: &T, timing: &Timing, flycam: &FlycamControlled) -> Vector3
+where
+    T: InputManager,
+    T::Inputs: FlycamInput,
+{
+    let inputs = manager.get_inputs();
+    Vector3::new(
+        **inputs.get_x_axis() * flycam.speed * timing.delta_time,
+        **inputs.get_y_axis() * flycam.speed * timing.delta_time,
+        **inputs.get_z_axis() * flycam.speed * timing.delta_time,
+    )
+}
+
+/// Rotate the camera by this frame's mouse movement.
+fn flycam_rotate(mouse: &Mouse, transform: &mut Transform, flycam: &FlycamControlled) {
+    let rotation = mouse.delta * flycam.sensitivity;
+    transform.rotate(Vector3::new(-rotation.y, rotation.x, 0.0));
+}
+
+/// Move flycams freely, ignoring their mode.
+/// Use [`flycam_collide_move_system`] instead if any flycams should collide with things.
 #[system(for_each)]
 pub fn flycam_move<T>(
     #[resource] manager: &T,
@@ -36,15 +84,31 @@ pub fn flycam_move<T>(
     T: 'static + InputManager,
     T::Inputs: FlycamInput,
 {
-    let inputs = manager.get_inputs();
-    let delta = Vector3::new(
-        **inputs.get_x_axis() as f32 * flycam.speed * timing.delta_time,
-        **inputs.get_y_axis() as f32 * flycam.speed * timing.delta_time,
-        **inputs.get_z_axis() as f32 * flycam.speed * timing.delta_time,
-    );
-
-    transform.translate(delta);
+    transform.translate(flycam_delta(manager, timing, flycam));
+    flycam_rotate(mouse, transform, flycam);
+}
 
-    let rotation = mouse.delta * flycam.sensitivity;
-    transform.rotate(Vector3::new(-rotation.y, rotation.x, 0.0));
+/// Move flycams, sweeping the movement of those in [`FlycamMode::Collide`] through the collider resource `C`.
+#[system(for_each)]
+pub fn flycam_collide_move<T, C>(
+    #[resource] manager: &T,
+    #[resource] timing: &Timing,
+    #[resource] mouse: &Mouse,
+    #[resource] collider: &C,
+    transform: &mut Transform,
+    flycam: &FlycamControlled,
+) where
+    T: 'static + InputManager,
+    T::Inputs: FlycamInput,
+    C: 'static + FlycamCollider + Send + Sync,
+{
+    let delta = flycam_delta(manager, timing, flycam);
+    match flycam.mode {
+        FlycamMode::Noclip => transform.translate(delta),
+        FlycamMode::Collide => {
+            let delta = transform.to_world(delta);
+            transform.position += collider.sweep(transform.position, delta);
+        }
+    }
+    flycam_rotate(mouse, transform, flycam);
 }
@@ -0,0 +1,135 @@
+//! Gamepad support, using gilrs.
+//!
+//! Gamepad events are translated into [`Action`]s, so they can be fed straight into any generated input manager.
+//! Each gamepad button and axis has its own keycode, chosen so that it won't collide with keyboard scancodes or mouse buttons:
+//!
+//!   - Buttons use `GAMEPAD_BUTTON_BASE + n`, where n is the button's position in [`BUTTONS`]. See [`button_keycode`].
+//!   - Axes use `GAMEPAD_AXIS_BASE + n`, where n is the axis' position in [`AXES`]. See [`axis_keycode`].
+//!
+//! Digital buttons are sent as `Action::KeyPress` and `Action::KeyRelease`.
+//! Sticks are sent as `Action::AxisMotion`, with values from -1 to 1.
+//! The analog triggers (`LeftTrigger2` and `RightTrigger2`) are also sent as `Action::AxisMotion`, with values from 0 to 1,
+//! using their button keycode.
+//!
+//! All connected gamepads share the same keycodes.
+//!
+//! For example, to move forward with the left stick and jump with the bottom face button:
+//! ```ignore
+//! action_schema.insert(
+//!     axis_keycode(Axis::LeftStickY).unwrap(),
+//!     (MovementInputsFields::Vertical, InputMutation::PositiveAxis),
+//! );
+//! action_schema.insert(
+//!     button_keycode(Button::South).unwrap(),
+//!     (MovementInputsFields::Jump, InputMutation::MapToButton),
+//! );
+//! ```
+
+pub use gilrs::{Axis, Button};
+
+use gilrs::{EventType, Gilrs};
+use stockton_input::Action;
+
+/// The keycode of the first gamepad button.
+pub const GAMEPAD_BUTTON_BASE: u32 = 0xFFFE_0000;
+
+/// The keycode of the first gamepad axis.
+pub const GAMEPAD_AXIS_BASE: u32 = 0xFFFE_0100;
+
+/// All buttons that can be bound, in keycode order.
+pub const BUTTONS: [Button; 19] = [
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::C,
+    Button::Z,
+    Button::LeftTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::Mode,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+/// All axes that can be bound, in keycode order.
+pub const AXES: [Axis; 8] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::LeftZ,
+    Axis::RightStickX,
+    Axis::RightStickY,
+    Axis::RightZ,
+    Axis::DPadX,
+    Axis::DPadY,
+];
+
+/// Get the keycode used for the given button, or None if it can't be bound (ie `Button::Unknown`).
+pub fn button_keycode(button: Button) -> Option<u32> {
+    BUTTONS
+        .iter()
+        .position(|x| *x == button)
+        .map(|idx| GAMEPAD_BUTTON_BASE + idx as u32)
+}
+
+/// Get the keycode used for the given axis, or None if it can't be bound (ie `Axis::Unknown`).
+pub fn axis_keycode(axis: Axis) -> Option<u32> {
+    AXES.iter()
+        .position(|x| *x == axis)
+        .map(|idx| GAMEPAD_AXIS_BASE + idx as u32)
+}
+
+/// Returns true if the given button is reported as an analog value.
+fn is_analog_button(button: Button) -> bool {
+    matches!(button, Button::LeftTrigger2 | Button::RightTrigger2)
+}
+
+/// Polls gilrs for gamepad events, and turns them into [`Action`]s.
+pub struct GamepadInput {
+    gilrs: Gilrs,
+}
+
+impl GamepadInput {
+    /// Start listening for gamepad events.
+    pub fn new() -> Result<Self, gilrs::Error> {
+        Ok(GamepadInput {
+            gilrs: Gilrs::new()?,
+        })
+    }
+
+    /// Get the actions for all gamepad events since this was last called.
+    /// This should be called once per frame, and the result passed to `InputManager::handle_frame`.
+    pub fn poll(&mut self) -> Vec<Action> {
+        let mut actions = vec![];
+        while let Some(event) = self.gilrs.next_event() {
+            let action = match event.event {
+                EventType::ButtonPressed(button, _) if !is_analog_button(button) => {
+                    button_keycode(button).map(Action::KeyPress)
+                }
+                EventType::ButtonReleased(button, _) if !is_analog_button(button) => {
+                    button_keycode(button).map(Action::KeyRelease)
+                }
+                EventType::ButtonChanged(button, value, _) if is_analog_button(button) => {
+                    button_keycode(button).map(|code| Action::AxisMotion(code, value))
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    axis_keycode(axis).map(|code| Action::AxisMotion(code, value))
+                }
+                _ => None,
+            };
+
+            if let Some(action) = action {
+                actions.push(action);
+            }
+        }
+
+        actions
+    }
+}
@@ -6,3 +6,12 @@ pub mod delta_time;
 
 #[cfg(feature = "flycam")]
 pub mod flycam;
+
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+
+#[cfg(feature = "window_events")]
+pub mod window_events;
+
+#[cfg(feature = "window_geometry")]
+pub mod window_geometry;
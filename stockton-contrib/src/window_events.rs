@@ -0,0 +1,256 @@
+//! Translating winit window events into [`Action`]s.
+//!
+//! Keyboard and mouse button events can be turned into actions with [`winit_actions`], and passed straight to any generated input manager.
+//!
+//! Keys can be identified in two ways, and which one to use depends on what the binding is for:
+//!
+//!   - [`winit_actions`] uses the key's position on the keyboard, from its scancode. See [`position_keycode`].
+//!     On an AZERTY keyboard, the key labelled Z gives the keycode of [`Key::W`], so WASD movement stays in the same place.
+//!   - [`winit_actions_by_label`] uses what's printed on the key, if it's in the [`Key`] table, and falls back to the scancode otherwise.
+//!     On an AZERTY keyboard, the key labelled Z gives the keycode of [`Key::Z`]. See [`layout_keycode`].
+//!
+//! Either way, keycodes are the same numbers as in the [`Key`] table, so schemas can be written with it.
+//! Only use one of these for a given input manager, since the same key can give different keycodes from each.
+//!
+//! Mouse buttons are sent as `Action::MousePress` and `Action::MouseRelease`, which use keycodes counting down from `u32::MAX`.
+//! The mouse wheel is sent as `Action::Scroll`, in lines. Touchpads that scroll by pixels are converted using [`PIXELS_PER_LINE`].
+//! Mouse buttons and scrolling are the same with either function.
+//!
+//! When the window loses focus, the release events for any keys being held never arrive, so they'd stay down until pressed again.
+//! Pass each window event to [`reset_on_focus_lost`] as well, which resets the input manager when that happens.
+//!
+//! If winit's event loop can't own the game loop, for example when embedding in another app, use a [`WindowPump`] to get events in batches instead.
+//!
+//! To switch between controlling a camera with the mouse and using a cursor, use [`InputMode::apply`].
+//!
+//! [`Key`]: stockton_input::Key
+//! [`Key::W`]: stockton_input::Key::W
+//! [`Key::Z`]: stockton_input::Key::Z
+
+use stockton_input::{layout_keycode, position_keycode, Action, InputManager, Mouse, MouseButton};
+use stockton_skeleton::types::Vector2;
+use winit::{
+    dpi::PhysicalPosition,
+    error::ExternalError,
+    event::{
+        ElementState, Event, KeyboardInput, MouseButton as WinitMouseButton, MouseScrollDelta,
+        WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoop},
+    platform::desktop::EventLoopExtDesktop,
+    window::Window,
+};
+
+/// The number of pixels of touchpad scrolling that count as scrolling one line.
+pub const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Get the action for the given window event, identifying keys by their position, with [`position_keycode`].
+/// Returns None if the event isn't a key or mouse button being pressed or released, or the mouse wheel scrolling.
+pub fn winit_actions(event: &WindowEvent) -> Option<Action> {
+    translate(event, position_keycode)
+}
+
+/// Get the action for the given window event, identifying keys by what's printed on them where possible.
+/// Returns None if the event isn't a key or mouse button being pressed or released, or the mouse wheel scrolling.
+pub fn winit_actions_by_label(event: &WindowEvent) -> Option<Action> {
+    translate(event, layout_keycode)
+}
+
+/// Reset the given input manager if the event is the window losing focus, returning true if it was reset.
+/// This should be called with every window event, alongside [`winit_actions`] or [`winit_actions_by_label`].
+/// Buttons that were down are released and marked as hot, so they're seen as just released on the next frame.
+pub fn reset_on_focus_lost<M: InputManager>(event: &WindowEvent, manager: &mut M) -> bool {
+    match event {
+        WindowEvent::Focused(false) => {
+            manager.reset();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Translate the given event, using `keycode` to get the keycode for keyboard events.
+fn translate(event: &WindowEvent, keycode: fn(&KeyboardInput) -> u32) -> Option<Action> {
+    match event {
+        WindowEvent::KeyboardInput { input, .. } => Some(match input.state {
+            ElementState::Pressed => Action::KeyPress(keycode(input)),
+            ElementState::Released => Action::KeyRelease(keycode(input)),
+        }),
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = mouse_button(*button);
+            Some(match state {
+                ElementState::Pressed => Action::MousePress(button),
+                ElementState::Released => Action::MouseRelease(button),
+            })
+        }
+        WindowEvent::MouseWheel { delta, .. } => Some(Action::Scroll(match delta {
+            MouseScrollDelta::LineDelta(_, y) => *y,
+            MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / PIXELS_PER_LINE,
+        })),
+        _ => None,
+    }
+}
+
+/// Convert a winit mouse button into a stockton one.
+fn mouse_button(button: WinitMouseButton) -> MouseButton {
+    match button {
+        WinitMouseButton::Left => MouseButton::Left,
+        WinitMouseButton::Right => MouseButton::Right,
+        WinitMouseButton::Middle => MouseButton::Middle,
+        WinitMouseButton::Other(x) => MouseButton::Other(x),
+    }
+}
+
+/// Owns winit's event loop, and gives back its window events in batches whenever asked.
+/// This lets the caller keep control of the game loop, rather than running everything inside `EventLoop::run`.
+///
+/// This is meant for apps with one window, so events from all windows are returned together.
+pub struct WindowPump {
+    event_loop: EventLoop<()>,
+
+    /// The scale factor from the last `ScaleFactorChanged` event, if one arrived since it was last taken.
+    scale_factor: Option<f64>,
+}
+
+impl WindowPump {
+    /// Create a pump for the given event loop.
+    pub fn new(event_loop: EventLoop<()>) -> Self {
+        WindowPump {
+            event_loop,
+            scale_factor: None,
+        }
+    }
+
+    /// Get the event loop, so windows can be created with it.
+    pub fn event_loop(&self) -> &EventLoop<()> {
+        &self.event_loop
+    }
+
+    /// Process all of the events waiting in the event loop, returning the window events among them.
+    /// This doesn't block, so should be called once per frame, and the result can be passed to [`winit_actions`].
+    ///
+    /// `ScaleFactorChanged` events borrow from the event loop, so they aren't returned. Use [`Self::take_scale_factor`] instead.
+    pub fn pump(&mut self) -> Vec<WindowEvent<'static>> {
+        let mut events = Vec::new();
+        let scale_factor = &mut self.scale_factor;
+        self.event_loop.run_return(|event, _, flow| {
+            *flow = ControlFlow::Poll;
+            match event {
+                Event::WindowEvent {
+                    event:
+                        WindowEvent::ScaleFactorChanged {
+                            scale_factor: s, ..
+                        },
+                    ..
+                } => *scale_factor = Some(s),
+                Event::WindowEvent { event, .. } => {
+                    events.extend(event.to_static());
+                }
+                // Everything that was waiting has been handled, so give control back
+                Event::MainEventsCleared => *flow = ControlFlow::Exit,
+                _ => (),
+            }
+        });
+
+        events
+    }
+
+    /// Get the new scale factor, if it changed since this was last called.
+    /// This should be passed to `Renderer::set_pixels_per_point`.
+    pub fn take_scale_factor(&mut self) -> Option<f64> {
+        self.scale_factor.take()
+    }
+}
+
+/// How the mouse should behave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputMode {
+    /// The cursor is hidden and grabbed, and mouse movement should be read as relative motion, such as for a first person camera.
+    Gameplay,
+
+    /// The cursor is visible and can leave the window, such as for menus.
+    Menu,
+}
+
+impl InputMode {
+    /// Change the window's cursor to suit this mode.
+    /// The cursor is moved to the centre of the window and `mouse` is updated to match, so switching modes doesn't cause a sudden jump in its delta.
+    ///
+    /// This version of winit can only lock the cursor to the window, not confine it to an area.
+    /// Some platforms don't support grabbing at all, in which case this returns an error after making every other change,
+    /// so it's usually fine to just warn about it.
+    pub fn apply(self, window: &Window, mouse: &mut Mouse) -> Result<(), ExternalError> {
+        let size = window.inner_size();
+        let centre = PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+
+        // Not every platform lets us move the cursor, but the mouse is still reset so nothing jumps
+        let _ = window.set_cursor_position(centre);
+        mouse.abs = Vector2::new(centre.x as f32, centre.y as f32);
+        mouse.delta = Vector2::zeros();
+
+        match self {
+            InputMode::Gameplay => {
+                window.set_cursor_visible(false);
+                window.set_cursor_grab(true)
+            }
+            InputMode::Menu => {
+                window.set_cursor_visible(true);
+                window.set_cursor_grab(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stockton_input::{Axis, Button, InputMutation, Key};
+    use stockton_input_codegen::InputManager;
+
+    #[derive(InputManager, Default, Debug, Clone)]
+    struct FocusInputs {
+        #[axis]
+        forward: Axis,
+        #[button]
+        jump: Button,
+    }
+
+    #[test]
+    fn focus_lost_resets_inputs() {
+        let mut manager = FocusInputsManager::builder()
+            .bind(
+                Key::W,
+                FocusInputsFields::Forward,
+                InputMutation::PositiveAxis,
+            )
+            .bind(
+                Key::Space,
+                FocusInputsFields::Jump,
+                InputMutation::MapToButton,
+            )
+            .build();
+        manager.handle_frame(&[
+            Action::KeyPress(Key::W as u32),
+            Action::KeyPress(Key::Space as u32),
+        ]);
+        assert_eq!(*manager.get_inputs().forward, 1.0);
+
+        assert!(!reset_on_focus_lost(
+            &WindowEvent::Focused(true),
+            &mut manager
+        ));
+        assert_eq!(*manager.get_inputs().forward, 1.0);
+
+        assert!(reset_on_focus_lost(
+            &WindowEvent::Focused(false),
+            &mut manager
+        ));
+        assert_eq!(*manager.get_inputs().forward, 0.0);
+        assert!(manager.get_inputs().jump.is_just_up());
+
+        // The key was released while unfocused, so its release never arrives, but pressing it again works as normal
+        manager.handle_frame(&[Action::KeyPress(Key::W as u32)]);
+        assert_eq!(*manager.get_inputs().forward, 1.0);
+    }
+}
@@ -0,0 +1,136 @@
+//! Saving a window's size and position, so it can be put back the next time the app is launched.
+//!
+//! Create a [`WindowGeometry`] with [`WindowGeometry::of`] once the window is open, pass it every window event with [`WindowGeometry::update`],
+//! then [`WindowGeometry::save`] it when exiting. On the next launch, [`WindowGeometry::load`] it and [`WindowGeometry::apply`] it to the new window.
+
+use std::{fs, io, path::Path};
+
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::WindowEvent,
+    window::Window,
+};
+
+/// How many pixels of a window need to be on a monitor, in each direction, for it to count as on screen.
+pub const MIN_VISIBLE: u32 = 64;
+
+/// The size and position of a window, in physical pixels.
+/// Positions are in the desktop's coordinates, which cover every monitor and can be negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowGeometry {
+    /// The position of the window's top left corner, including decorations
+    pub position: PhysicalPosition<i32>,
+
+    /// The size of the window's client area
+    pub size: PhysicalSize<u32>,
+}
+
+impl WindowGeometry {
+    /// Get the current geometry of the given window.
+    /// If the platform can't tell us where the window is, the position is left at 0, 0.
+    pub fn of(window: &Window) -> Self {
+        WindowGeometry {
+            position: window
+                .outer_position()
+                .unwrap_or_else(|_| PhysicalPosition::new(0, 0)),
+            size: window.inner_size(),
+        }
+    }
+
+    /// Update the geometry from an event for its window, returning true if it changed.
+    /// Resizes to zero are ignored, as some platforms give them when the window is minimised.
+    pub fn update(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Moved(position) if *position != self.position => {
+                self.position = *position;
+                true
+            }
+            WindowEvent::Resized(size)
+                if size.width > 0 && size.height > 0 && *size != self.size =>
+            {
+                self.size = *size;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Load a geometry saved with [`Self::save`], or None if the file doesn't exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        Self::parse(&contents)
+            .map(Some)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid window geometry"))
+    }
+
+    /// Save the geometry to the given file, creating any missing parent directories.
+    /// The file is a single line of `x y width height`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(
+            path,
+            format!(
+                "{} {} {} {}\n",
+                self.position.x, self.position.y, self.size.width, self.size.height
+            ),
+        )
+    }
+
+    /// Check if enough of the window would be on one of the available monitors for it to be moved by the user.
+    /// The top edge needs to be on the monitor, so the title bar can be grabbed.
+    pub fn is_on_screen(&self, window: &Window) -> bool {
+        window.available_monitors().any(|monitor| {
+            let (pos, size) = (monitor.position(), monitor.size());
+            let overlap = |start: i32, len: u32, mon_start: i32, mon_len: u32| {
+                let end = start as i64 + len as i64;
+                let mon_end = mon_start as i64 + mon_len as i64;
+                end.min(mon_end) - (start as i64).max(mon_start as i64)
+            };
+
+            overlap(self.position.x, self.size.width, pos.x, size.width) >= MIN_VISIBLE as i64
+                && overlap(self.position.y, MIN_VISIBLE, pos.y, size.height) >= MIN_VISIBLE as i64
+        })
+    }
+
+    /// Resize the window to this geometry, and move it if it would still be on screen.
+    /// Monitors may have been unplugged or rearranged since the geometry was saved, so the position is ignored if it's no longer visible.
+    /// Returns true if the window was moved.
+    pub fn apply(&self, window: &Window) -> bool {
+        if self.size.width > 0 && self.size.height > 0 {
+            window.set_inner_size(self.size);
+        }
+
+        if self.is_on_screen(window) {
+            window.set_outer_position(self.position);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse the contents of a file written by [`Self::save`].
+    fn parse(contents: &str) -> Option<Self> {
+        let mut parts = contents.split_whitespace();
+        let x = parts.next()?.parse().ok()?;
+        let y = parts.next()?.parse().ok()?;
+        let width = parts.next()?.parse().ok()?;
+        let height = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(WindowGeometry {
+            position: PhysicalPosition::new(x, y),
+            size: PhysicalSize::new(width, height),
+        })
+    }
+}
@@ -4,34 +4,107 @@ use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+use stockton_input::radial_deadzone;
+use syn::{
+    parenthesized, parse::ParseStream, parse_macro_input, punctuated::Punctuated, Attribute, Data,
+    DeriveInput, Error, Expr, ExprLit, ExprUnary, Fields, Generics, Ident, Lit, LitStr, Path,
+    Token, UnOp, Visibility,
+};
 
 /// Generate an input manager for the given struct.
 /// Each button in the struct should be decorated with `#[button]` and each axis with `#[axis]`.
+/// Fields with neither attribute are left alone by the manager, keeping the value they're given by `Default`.
+/// Each field can only have one of these attributes, once.
+/// Axes can be given a value to start at and reset to with `#[axis(default = 0.5)]`, otherwise they start at zero.
+/// Axes can also be limited to a range with `#[axis(min = -1.0, max = 1.0)]`, for example so that holding two keys bound to the same direction doesn't double the value. Either end can be left out.
+/// Two axes can be made into a pair, like a stick's x and y, by giving both `#[axis(pair = "move")]`. After each frame, the pair gets a radial deadzone and its combined magnitude is clamped to 1, see [`stockton_input::radial_deadzone`].
+/// The size of the deadzone is given with `#[axis(pair = "move", deadzone = 0.15)]` on either axis, and defaults to zero.
+/// Buttons and axes can be given a human readable label with `#[button(label = "Jump")]` or `#[axis(label = "Move Left/Right", default = 0.5)]`.
 /// Given struct MovementInputs, this will output struct MovementInputsManager which implements InputManager.
+/// If MovementInputs has generic parameters, the manager and snapshot have the same ones. Fields that aren't inputs are initialised with `Default`, like the rest of the struct.
 /// It also creates an enum MovementInputsFields, with values for all the buttons and axes in MovementInputs.
+/// `.name()` on a MovementInputsFields gives the name of the field it refers to, and `.label()` gives its label, or the name if it doesn't have one.
 /// You'll need to pass in an action schema to `MovementInputsManager::new()`, which is a BTreeMap<u32, (MovementInputsFields, InputMutation)>
+/// Or, build one binding at a time with `MovementInputsManager::builder().bind(Key::W, MovementInputsFields::Vertical, InputMutation::PositiveAxis).build()`.
 /// You can then call `.handle_frame` on MovementInputsManager and then read the inputs from MovementInputsManager.inputs.
-#[proc_macro_derive(InputManager, attributes(button, axis))]
+/// If you need to know which inputs changed, call `.handle_frame_changes` instead, which returns the MovementInputsFields whose values changed.
+/// To find what's bound to what, for example in a rebinding UI, use `.keycodes_for` and `.mutation_for`.
+/// To look at every input together, for example to show their current state, use `.all_inputs()`, which gives each field's name along with it as a `&dyn Input`.
+/// Code that's generic over the manager can read and write single inputs with `InputManager::value_of` and `InputManager::set_value`, for example to record and replay them.
+/// The full state of the manager can be saved with `.snapshot()`, which returns a MovementInputsSnapshot, and put back with `.restore()`.
+/// With the `serde` feature, snapshots can also be serialized, as long as MovementInputs can be too.
+/// The generated types can be customised with `#[input_manager(...)]` on MovementInputs:
+///   - `derive(Hash, Serialize)` adds derives to MovementInputsFields
+///   - `manager_derive(...)` adds derives to MovementInputsManager
+///   - `forward_docs` copies MovementInputs' doc comments onto MovementInputsManager
+#[proc_macro_derive(InputManager, attributes(button, axis, input_manager))]
 pub fn derive_inputmanager(input: TokenStream) -> TokenStream {
     let struct_data = parse_macro_input!(input as DeriveInput);
 
     let visibility = &struct_data.vis;
+    let generics = &struct_data.generics;
 
     let struct_ident = &struct_data.ident;
     let manager_ident = format_ident!("{}Manager", struct_data.ident);
     let fields_enum_ident = format_ident!("{}Fields", struct_data.ident);
+    let snapshot_ident = format_ident!("{}Snapshot", struct_data.ident);
 
-    let (buttons, axes) = get_categorised_idents(&struct_data.data);
+    let options = match parse_manager_options(&struct_data.attrs) {
+        Ok(x) => x,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let fields_derives = &options.fields_derives;
+    let manager_derives = &options.manager_derives;
+    let manager_docs = struct_data
+        .attrs
+        .iter()
+        .filter(|attr| options.forward_docs && attr.path.is_ident("doc"));
+
+    let (buttons, axes) = match get_categorised_idents(&struct_data.data) {
+        Ok(x) => x,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let (buttons, button_labels): (Vec<Ident>, Vec<Option<String>>) = buttons
+        .into_iter()
+        .map(|(ident, options)| (ident, options.label))
+        .unzip();
+    let (axes, axis_options): (Vec<Ident>, Vec<FieldOptions>) = axes.into_iter().unzip();
+    let (axis_processing, axis_pairs) = match get_axis_processing(&axis_options) {
+        Ok(x) => x,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let (axis_defaults, axis_labels): (Vec<Option<f32>>, Vec<Option<String>>) = axis_options
+        .into_iter()
+        .map(|options| (options.default, options.label))
+        .unzip();
     let caps_buttons = capitalise_idents(buttons.clone());
     let caps_axes = capitalise_idents(axes.clone());
 
     let fields_enum = gen_fields_enum(&fields_enum_ident, &caps_buttons, &caps_axes);
+    let fields_enum_impl = gen_fields_enum_impl(
+        &fields_enum_ident,
+        buttons.iter().chain(axes.iter()),
+        button_labels.iter().chain(axis_labels.iter()),
+        caps_buttons.iter().chain(caps_axes.iter()),
+    );
     let manager_struct = gen_manager_struct(
         &manager_ident,
         struct_ident,
         &fields_enum_ident,
+        &buttons,
+        &axes,
+        &axis_defaults,
+        &axis_processing,
+        generics,
+    );
+    let snapshot = gen_snapshot(
+        &snapshot_ident,
+        &manager_ident,
+        struct_ident,
+        generics,
         buttons.len(),
+        axis_processing.iter().filter(|p| p.keeps_raw()).count(),
+        visibility,
     );
     let trait_impl = gen_trait_impl(
         &manager_ident,
@@ -39,17 +112,26 @@ pub fn derive_inputmanager(input: TokenStream) -> TokenStream {
         &fields_enum_ident,
         &buttons,
         &axes,
+        &axis_defaults,
+        &axis_processing,
+        &axis_pairs,
         &caps_buttons,
         &caps_axes,
+        generics,
     );
 
     let expanded = quote! {
-        #[derive(Debug, Clone, Copy)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq #(, #fields_derives)*)]
         #visibility #fields_enum
 
-        #[derive(Debug, Clone)]
+        #fields_enum_impl
+
+        #(#manager_docs)*
+        #[derive(Debug, Clone #(, #manager_derives)*)]
         #visibility #manager_struct
 
+        #snapshot
+
         #trait_impl
 
     };
@@ -57,9 +139,212 @@ pub fn derive_inputmanager(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
-/// Gets the buttons and axes from a given struct definition
+/// Options given with `#[input_manager(...)]` on the inputs struct
+#[derive(Default)]
+struct ManagerOptions {
+    /// Extra derives for the fields enum
+    fields_derives: Vec<Path>,
+
+    /// Extra derives for the manager struct
+    manager_derives: Vec<Path>,
+
+    /// Whether to copy the inputs struct's doc comments onto the manager
+    forward_docs: bool,
+}
+
+/// Parse any `#[input_manager(...)]` attributes on the inputs struct, eg `#[input_manager(derive(Hash), forward_docs)]`.
+fn parse_manager_options(attrs: &[Attribute]) -> Result<ManagerOptions, Error> {
+    let mut options = ManagerOptions::default();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("input_manager")) {
+        attr.parse_args_with(|input: ParseStream| {
+            while !input.is_empty() {
+                let key: Ident = input.parse()?;
+
+                if key == "derive" || key == "manager_derive" {
+                    let content;
+                    parenthesized!(content in input);
+                    let paths = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+                    if key == "derive" {
+                        options.fields_derives.extend(paths);
+                    } else {
+                        options.manager_derives.extend(paths);
+                    }
+                } else if key == "forward_docs" {
+                    options.forward_docs = true;
+                } else {
+                    return Err(Error::new(
+                        key.span(),
+                        "unknown option, expected `derive`, `manager_derive` or `forward_docs`",
+                    ));
+                }
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(options)
+}
+
+/// Options given to a `#[button]` or `#[axis]` attribute
+#[derive(Default)]
+struct FieldOptions {
+    /// The value to start at and reset to. Only valid for axes.
+    default: Option<f32>,
+
+    /// A human readable name for the field
+    label: Option<String>,
+
+    /// The lowest value the field can have. Only valid for axes.
+    min: Option<f32>,
+
+    /// The highest value the field can have. Only valid for axes.
+    max: Option<f32>,
+
+    /// The name of the pair this field is in. Only valid for axes.
+    pair: Option<LitStr>,
+
+    /// The radial deadzone of the pair this field is in. Only valid for paired axes.
+    deadzone: Option<f32>,
+}
+
+/// The range a bounded axis is clamped to, with missing ends left unbounded.
+#[derive(Clone, Copy)]
+struct AxisBounds {
+    min: Option<f32>,
+    max: Option<f32>,
+}
+
+impl AxisBounds {
+    /// Get the bounds given in an axis' options, or None if it isn't bounded.
+    fn of(options: &FieldOptions) -> Option<Self> {
+        if options.min.is_none() && options.max.is_none() {
+            return None;
+        }
+
+        Some(AxisBounds {
+            min: options.min,
+            max: options.max,
+        })
+    }
+
+    /// The arguments to pass to `Axis::clamp`
+    fn clamp_args(&self) -> (TokenStream2, TokenStream2) {
+        let min = match self.min {
+            Some(min) => quote!(#min),
+            None => quote!(::std::f32::NEG_INFINITY),
+        };
+        let max = match self.max {
+            Some(max) => quote!(#max),
+            None => quote!(::std::f32::INFINITY),
+        };
+
+        (min, max)
+    }
+}
+
+/// How an axis' value is worked out from the sum of everything bound to it
+struct AxisProcessing {
+    /// The range the axis is clamped to, if any
+    bounds: Option<AxisBounds>,
+
+    /// Whether the axis is in a pair, so its value is set once each frame by `apply_axis_pairs`
+    paired: bool,
+
+    /// The value of the axis when the manager is created or reset, if it isn't zero
+    start: Option<f32>,
+}
+
+impl AxisProcessing {
+    /// Whether the sum of everything bound to the axis needs to be stored separately from its value
+    fn keeps_raw(&self) -> bool {
+        self.bounds.is_some() || self.paired
+    }
+}
+
+/// Two axes that are processed together, as indices into the axes of the inputs struct
+struct AxisPair {
+    x: usize,
+    y: usize,
+    deadzone: f32,
+}
+
+/// Work out how each axis is processed, and which axes are paired, from the options given to them.
+fn get_axis_processing(
+    options: &[FieldOptions],
+) -> Result<(Vec<AxisProcessing>, Vec<AxisPair>), Error> {
+    // The axes in each pair, in the order the pairs are first seen
+    let mut members: Vec<(&LitStr, Vec<usize>)> = vec![];
+    for (i, opts) in options.iter().enumerate() {
+        if let Some(name) = &opts.pair {
+            match members.iter_mut().find(|(n, _)| n.value() == name.value()) {
+                Some((_, axes)) => axes.push(i),
+                None => members.push((name, vec![i])),
+            }
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(members.len());
+    for (name, axes) in members {
+        let (x, y) = match axes[..] {
+            [x, y] => (x, y),
+            _ => {
+                return Err(Error::new(
+                    name.span(),
+                    format!(
+                        "axis pair `{}` needs exactly two axes, but has {}",
+                        name.value(),
+                        axes.len()
+                    ),
+                ))
+            }
+        };
+        let deadzone = match (options[x].deadzone, options[y].deadzone) {
+            (Some(a), Some(b)) if a != b => {
+                return Err(Error::new(
+                    options[y].pair.as_ref().unwrap().span(),
+                    format!("axes in pair `{}` have different deadzones", name.value()),
+                ))
+            }
+            (a, b) => a.or(b).unwrap_or(0.0),
+        };
+
+        pairs.push(AxisPair { x, y, deadzone });
+    }
+
+    let mut processing: Vec<AxisProcessing> = options
+        .iter()
+        .map(|opts| AxisProcessing {
+            bounds: AxisBounds::of(opts),
+            paired: opts.pair.is_some(),
+            start: opts.default,
+        })
+        .collect();
+
+    // Paired axes start where their defaults end up after the pair is processed
+    for pair in pairs.iter() {
+        let (x, y) = radial_deadzone(
+            options[pair.x].default.unwrap_or(0.0),
+            options[pair.y].default.unwrap_or(0.0),
+            pair.deadzone,
+        );
+        processing[pair.x].start = Some(x);
+        processing[pair.y].start = Some(y);
+    }
+
+    Ok((processing, pairs))
+}
+
+/// Gets the buttons and axes from a given struct definition, along with the options given for each.
 /// Buttons are decorated with #[button] and axes with #[axis]
-fn get_categorised_idents(data: &Data) -> (Vec<Ident>, Vec<Ident>) {
+#[allow(clippy::type_complexity)]
+fn get_categorised_idents(
+    data: &Data,
+) -> Result<(Vec<(Ident, FieldOptions)>, Vec<(Ident, FieldOptions)>), Error> {
     let mut buttons = vec![];
     let mut axes = vec![];
 
@@ -67,14 +352,29 @@ fn get_categorised_idents(data: &Data) -> (Vec<Ident>, Vec<Ident>) {
         Data::Struct(ref s) => match &s.fields {
             Fields::Named(fields) => {
                 for field in fields.named.iter() {
-                    let attrs = field.attrs.iter().map(|a| a.parse_meta().unwrap());
-                    for attr in attrs {
-                        if attr.path().is_ident("button") {
-                            buttons.push(field.ident.as_ref().unwrap().clone());
-                            break;
-                        } else if attr.path().is_ident("axis") {
-                            axes.push(field.ident.as_ref().unwrap().clone());
-                            break;
+                    let mut seen: Option<&Attribute> = None;
+                    for attr in field.attrs.iter() {
+                        let is_axis = attr.path.is_ident("axis");
+                        if !is_axis && !attr.path.is_ident("button") {
+                            continue;
+                        }
+
+                        if let Some(first) = seen {
+                            let mut err = Error::new_spanned(
+                                attr,
+                                "each field can only have one `#[button]` or `#[axis]` attribute",
+                            );
+                            err.combine(Error::new_spanned(first, "first attribute is here"));
+                            return Err(err);
+                        }
+                        seen = Some(attr);
+
+                        let options = parse_field_options(attr, is_axis)?;
+                        let ident = field.ident.as_ref().unwrap().clone();
+                        if is_axis {
+                            axes.push((ident, options));
+                        } else {
+                            buttons.push((ident, options));
                         }
                     }
                 }
@@ -86,7 +386,124 @@ fn get_categorised_idents(data: &Data) -> (Vec<Ident>, Vec<Ident>) {
         }
     };
 
-    (buttons, axes)
+    Ok((buttons, axes))
+}
+
+/// Parse the options given to a `#[button]` or `#[axis]` attribute, eg `#[axis(default = 0.5, label = "Move Up/Down")]`.
+/// Both can be given a `label`, but only axes can have a `default`, as buttons always start released, or a `min` and `max`.
+fn parse_field_options(attr: &Attribute, is_axis: bool) -> Result<FieldOptions, Error> {
+    let mut options = FieldOptions::default();
+    if attr.tokens.is_empty() {
+        return Ok(options);
+    }
+
+    let options = attr.parse_args_with(|input: ParseStream| {
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+
+            if key == "label" {
+                let value: LitStr = input.parse()?;
+                options.label = Some(value.value());
+            } else if key == "default" && is_axis {
+                let value: Expr = input.parse()?;
+                options.default = Some(parse_f32_literal(&value)?);
+            } else if key == "min" && is_axis {
+                let value: Expr = input.parse()?;
+                options.min = Some(parse_f32_literal(&value)?);
+            } else if key == "max" && is_axis {
+                let value: Expr = input.parse()?;
+                options.max = Some(parse_f32_literal(&value)?);
+            } else if key == "pair" && is_axis {
+                options.pair = Some(input.parse()?);
+            } else if key == "deadzone" && is_axis {
+                let value: Expr = input.parse()?;
+                options.deadzone = Some(parse_f32_literal(&value)?);
+            } else if key == "default" {
+                return Err(Error::new(
+                    key.span(),
+                    "buttons can't have a default, and always start released",
+                ));
+            } else if key == "min" || key == "max" {
+                return Err(Error::new(
+                    key.span(),
+                    "buttons can't have a range, as they're only ever up or down",
+                ));
+            } else if key == "pair" || key == "deadzone" {
+                return Err(Error::new(key.span(), "only axes can be paired"));
+            } else if is_axis {
+                return Err(Error::new(
+                    key.span(),
+                    "unknown axis option, expected `default`, `label`, `min`, `max`, `pair` or `deadzone`",
+                ));
+            } else {
+                return Err(Error::new(
+                    key.span(),
+                    "unknown button option, expected `label`",
+                ));
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(options)
+    })?;
+
+    let min = options.min.unwrap_or(f32::NEG_INFINITY);
+    let max = options.max.unwrap_or(f32::INFINITY);
+    if min > max {
+        return Err(Error::new_spanned(
+            attr,
+            "axis `min` must not be greater than its `max`",
+        ));
+    }
+    if let Some(default) = options.default {
+        if default < min || default > max {
+            return Err(Error::new_spanned(
+                attr,
+                "axis default must be between its `min` and `max`",
+            ));
+        }
+    }
+    if let Some(deadzone) = options.deadzone {
+        if options.pair.is_none() {
+            return Err(Error::new_spanned(
+                attr,
+                "only paired axes can have a deadzone, eg `#[axis(pair = \"move\", deadzone = 0.15)]`",
+            ));
+        }
+        if !(0.0..1.0).contains(&deadzone) {
+            return Err(Error::new_spanned(
+                attr,
+                "axis deadzone must be at least 0 and less than 1",
+            ));
+        }
+    }
+
+    Ok(options)
+}
+
+/// Get the value of a (possibly negated) numeric literal, as used for an axis' default value and range.
+fn parse_f32_literal(expr: &Expr) -> Result<f32, Error> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Float(f), ..
+        }) if matches!(f.suffix(), "" | "f32") => f.base10_parse(),
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(i), ..
+        }) if i.suffix().is_empty() => i.base10_parse(),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => Ok(-parse_f32_literal(expr)?),
+        _ => Err(Error::new_spanned(
+            expr,
+            "axis options must be f32 literals, eg `#[axis(default = 0.5)]`",
+        )),
+    }
 }
 
 /// Convert a vector of idents to UpperCamel, as used in enums.
@@ -125,158 +542,729 @@ fn gen_fields_enum(
     )
 }
 
-/// Generates a manager struct for the given inputs struct with buttons_len buttons.
+/// Generate the `name` and `label` methods for a fields enum.
+/// `fields`, `labels` and `fields_caps` should all be in the same order.
+///
+/// Example output:
+/// ```ignore
+/// impl MovementInputsFields {
+///     pub fn name(&self) -> &'static str {
+///         match *self {
+///             MovementInputsFields::Jump => "jump",
+///             MovementInputsFields::Vertical => "vertical",
+///             MovementInputsFields::Horizontal => "horizontal",
+///         }
+///     }
+///
+///     pub fn label(&self) -> &'static str {
+///         match *self {
+///             MovementInputsFields::Jump => "jump",
+///             // Given `#[axis(label = "Move Up/Down")]`
+///             MovementInputsFields::Vertical => "Move Up/Down",
+///             MovementInputsFields::Horizontal => "horizontal",
+///         }
+///     }
+/// }
+/// ```
+fn gen_fields_enum_impl<'a>(
+    fields_enum_ident: &Ident,
+    fields: impl Iterator<Item = &'a Ident>,
+    labels: impl Iterator<Item = &'a Option<String>>,
+    fields_caps: impl Iterator<Item = &'a Ident>,
+) -> TokenStream2 {
+    let (names, labels): (Vec<String>, Vec<String>) = fields
+        .zip(labels)
+        .map(|(field, label)| {
+            let name = field.to_string();
+            let label = label.clone().unwrap_or_else(|| name.clone());
+            (name, label)
+        })
+        .unzip();
+    let fields_caps: Vec<&Ident> = fields_caps.collect();
+
+    quote!(
+        impl #fields_enum_ident {
+            /// The name of the field this refers to, as written in the inputs struct.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    #(#fields_enum_ident::#fields_caps => #names,)*
+                }
+            }
+
+            /// A human readable name for the field this refers to.
+            /// This is the label given in its attribute, or its name if it wasn't given one.
+            pub fn label(&self) -> &'static str {
+                match *self {
+                    #(#fields_enum_ident::#fields_caps => #labels,)*
+                }
+            }
+        }
+    )
+}
+
+/// Generates a manager struct for the given inputs struct with the given buttons and axes.
 ///
 /// Example output:
 /// ```ignore
 /// struct MovementInputsManager {
 ///     inputs: MovementInputs,
 ///     actions: BTreeMap<Keycode, ActionResponse>,
-///     just_hot: [bool; 1]
+///     is_down: BTreeMap<Keycode, bool>,
+///     analog: BTreeMap<Keycode, f32>,
+///     just_hot: [bool; 1],
+///     // The sum of everything bound to each axis with a `min`, `max` or `pair`, before it's processed
+///     raw: [f32; 1]
 /// }
 ///
 /// impl MovementInputsManager {
 ///     pub fn new(actions: BTreeMap<Keycode, ActionResponse>) -> Self {
+///         let mut inputs: MovementInputs = Default::default();
+///         // For each axis with a default value, eg `#[axis(default = 0.5)]`
+///         inputs.vertical = Axis::new(0.5);
+///
 ///         MovementInputsManager {
-///             inputs: MovementInputs {
-///                 vertical: Axis::zero(),
-///                 horizontal: Axis::zero(),
-///                 jump: Button::new()
-///             },
+///             inputs,
 ///             actions,
-///             just_hot: [false]
+///             is_down,
+///             analog: BTreeMap::new(),
+///             just_hot: [false],
+///             raw: [0.5]
 ///         }
 ///     }
+///
+///     pub fn builder() -> ActionSchemaBuilder<MovementInputsFields, Self> {
+///         ActionSchemaBuilder::new(Self::new)
+///     }
+///
+///     pub fn keycodes_for(&self, field: MovementInputsFields) -> Vec<u32> {
+///         self.actions
+///             .iter()
+///             .filter(|(_, (f, _))| *f == field)
+///             .map(|(k, _)| *k)
+///             .collect()
+///     }
+///
+///     pub fn mutation_for(&self, keycode: u32) -> Option<(MovementInputsFields, InputMutation)> {
+///         self.actions.get(&keycode).copied()
+///     }
+///
+///     pub fn all_inputs(&self) -> Vec<(&'static str, &dyn Input)> {
+///         vec![("jump", &self.inputs.jump as &dyn Input), ("vertical", &self.inputs.vertical as &dyn Input)]
+///     }
 /// }
 /// ```
+#[allow(clippy::too_many_arguments)]
 fn gen_manager_struct(
     ident: &Ident,
     struct_ident: &Ident,
     fields_enum_ident: &Ident,
-    buttons_len: usize,
+    buttons: &[Ident],
+    axes: &[Ident],
+    axis_defaults: &[Option<f32>],
+    axis_processing: &[AxisProcessing],
+    generics: &Generics,
 ) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let buttons_len = buttons.len();
     let jh_falses = (0..buttons_len).map(|_| quote!(false));
+    let input_names = buttons.iter().chain(axes.iter()).map(|f| f.to_string());
+    let input_fields = buttons.iter().chain(axes.iter());
+    let raw_inits = gen_raw_inits(axis_defaults, axis_processing);
+    let raw_len = raw_inits.len();
+    let axis_inits = axes
+        .iter()
+        .zip(axis_processing.iter())
+        .filter_map(|(axis, processing)| {
+            let start = processing.start?;
+            Some(quote!(inputs.#axis = ::stockton_input::Axis::new(#start);))
+        });
     quote!(
-        struct #ident {
-            inputs: #struct_ident,
+        struct #ident #impl_generics #where_clause {
+            inputs: #struct_ident #ty_generics,
             actions: ::std::collections::BTreeMap<u32, (#fields_enum_ident, ::stockton_input::InputMutation)>,
             is_down: ::std::collections::BTreeMap<u32, bool>,
-            just_hot: [bool; #buttons_len]
+            analog: ::std::collections::BTreeMap<u32, f32>,
+            just_hot: [bool; #buttons_len],
+            raw: [f32; #raw_len]
         }
 
-        impl #ident {
-            pub fn new(actions: ::std::collections::BTreeMap<u32, (#fields_enum_ident, ::stockton_input::InputMutation)>) -> Self {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            pub fn new(actions: ::std::collections::BTreeMap<u32, (#fields_enum_ident, ::stockton_input::InputMutation)>) -> Self
+            where
+                #struct_ident #ty_generics: Default,
+            {
                 let mut is_down = ::std::collections::BTreeMap::new();
                 for (k,_) in actions.iter() {
                     is_down.insert(*k, false);
                 }
 
+                let mut inputs: #struct_ident #ty_generics = Default::default();
+                #(#axis_inits)*
+
                 #ident {
-                    inputs: Default::default(),
+                    inputs,
                     actions,
                     is_down,
-                    just_hot: [#(#jh_falses),*]
+                    analog: ::std::collections::BTreeMap::new(),
+                    just_hot: [#(#jh_falses),*],
+                    raw: [#(#raw_inits),*]
+                }
+            }
+
+            /// Start building an action schema, which creates a manager when it's built.
+            pub fn builder() -> ::stockton_input::ActionSchemaBuilder<#fields_enum_ident, Self>
+            where
+                #struct_ident #ty_generics: Default,
+            {
+                ::stockton_input::ActionSchemaBuilder::new(Self::new)
+            }
+
+            /// Get every keycode bound to the given field, in ascending order.
+            pub fn keycodes_for(&self, field: #fields_enum_ident) -> Vec<u32> {
+                self.actions
+                    .iter()
+                    .filter(|(_, (f, _))| *f == field)
+                    .map(|(k, _)| *k)
+                    .collect()
+            }
+
+            /// Get the field and mutation the given keycode is bound to, if any.
+            pub fn mutation_for(&self, keycode: u32) -> Option<(#fields_enum_ident, ::stockton_input::InputMutation)> {
+                self.actions.get(&keycode).copied()
+            }
+
+            /// Get every input along with the name of its field, buttons first and then axes.
+            pub fn all_inputs(&self) -> Vec<(&'static str, &dyn ::stockton_input::Input)> {
+                vec![#((#input_names, &self.inputs.#input_fields as &dyn ::stockton_input::Input)),*]
+            }
+        }
+    )
+}
+
+/// Generate a snapshot struct holding all of a manager's state except its bindings, and methods on the manager to take and restore them.
+///
+/// Example output:
+/// ```ignore
+/// #[derive(Debug, Clone)]
+/// struct MovementInputsSnapshot {
+///     inputs: MovementInputs,
+///     is_down: BTreeMap<u32, bool>,
+///     analog: BTreeMap<u32, f32>,
+///     just_hot: [bool; 1],
+///     raw: [f32; 1],
+/// }
+///
+/// impl MovementInputsSnapshot {
+///     pub fn inputs(&self) -> &MovementInputs {
+///         &self.inputs
+///     }
+/// }
+///
+/// impl MovementInputsManager {
+///     pub fn snapshot(&self) -> MovementInputsSnapshot {
+///         MovementInputsSnapshot {
+///             inputs: self.inputs.clone(),
+///             is_down: self.is_down.clone(),
+///             analog: self.analog.clone(),
+///             just_hot: self.just_hot,
+///             raw: self.raw,
+///         }
+///     }
+///
+///     pub fn restore(&mut self, snapshot: MovementInputsSnapshot) {
+///         self.inputs = snapshot.inputs;
+///         for (keycode, is_down) in self.is_down.iter_mut() {
+///             *is_down = snapshot.is_down.get(keycode).copied().unwrap_or(false);
+///         }
+///         let actions = &self.actions;
+///         self.analog = snapshot
+///             .analog
+///             .into_iter()
+///             .filter(|(keycode, _)| actions.contains_key(keycode))
+///             .collect();
+///         self.just_hot = snapshot.just_hot;
+///         self.raw = snapshot.raw;
+///     }
+/// }
+/// ```
+fn gen_snapshot(
+    ident: &Ident,
+    manager: &Ident,
+    struct_ident: &Ident,
+    generics: &Generics,
+    buttons_len: usize,
+    raw_len: usize,
+    visibility: &Visibility,
+) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let serde_derive = if cfg!(feature = "serde") {
+        quote!(#[derive(::serde::Serialize, ::serde::Deserialize)])
+    } else {
+        quote!()
+    };
+
+    quote!(
+        /// The state of an input manager at one point in time, so it can be restored later (for example, to re-simulate frames).
+        /// This doesn't include which keys are bound to which inputs.
+        #[derive(Debug, Clone)]
+        #serde_derive
+        #visibility struct #ident #impl_generics #where_clause {
+            inputs: #struct_ident #ty_generics,
+            is_down: ::std::collections::BTreeMap<u32, bool>,
+            analog: ::std::collections::BTreeMap<u32, f32>,
+            just_hot: [bool; #buttons_len],
+            raw: [f32; #raw_len],
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Get the inputs as they were when the snapshot was taken.
+            pub fn inputs(&self) -> &#struct_ident #ty_generics {
+                &self.inputs
+            }
+        }
+
+        impl #impl_generics #manager #ty_generics #where_clause {
+            /// Save the current state of every input, including which keys are held down.
+            pub fn snapshot(&self) -> #ident #ty_generics
+            where
+                #struct_ident #ty_generics: Clone,
+            {
+                #ident {
+                    inputs: self.inputs.clone(),
+                    is_down: self.is_down.clone(),
+                    analog: self.analog.clone(),
+                    just_hot: self.just_hot,
+                    raw: self.raw,
+                }
+            }
+
+            /// Go back to the state saved in the given snapshot.
+            /// This keeps the current bindings, so snapshots taken with different ones can still be restored:
+            /// keys the snapshot doesn't know about are treated as released, and ones that aren't bound any more are ignored.
+            pub fn restore(&mut self, snapshot: #ident #ty_generics) {
+                self.inputs = snapshot.inputs;
+                for (keycode, is_down) in self.is_down.iter_mut() {
+                    *is_down = snapshot.is_down.get(keycode).copied().unwrap_or(false);
                 }
+                let actions = &self.actions;
+                self.analog = snapshot
+                    .analog
+                    .into_iter()
+                    .filter(|(keycode, _)| actions.contains_key(keycode))
+                    .collect();
+                self.just_hot = snapshot.just_hot;
+                self.raw = snapshot.raw;
             }
         }
     )
 }
 
 /// Implements the InputManager trait on a manager struct generated by gen_manager_struct.
+/// Also generates `handle_frame_changes`, which handle_frame is implemented with.
 ///
 /// Example output:
 /// ```ignore
-/// impl InputManager<Action> for MovementInputsManager {
-///     fn handle_frame<X: IntoIterator<Item = Action>>(&mut self, actions: X) -> () {
+/// impl MovementInputsManager {
+///     /// Handle a single action, returning the field whose value it changed, if any.
+///     fn handle_action(&mut self, action: &Action) -> Option<MovementInputsFields> {
+///         let keycode = action.keycode();
+///         let (field, mutation) = self.actions.get(&keycode)?;
+///
+///         let val = match action {
+///             // Analog input bound to an axis, so apply the change since the last motion
+///             Action::AxisMotion(_, value) if !matches!(mutation, InputMutation::MapToButton) => {
+///                 value - self.analog.insert(keycode, *value).unwrap_or(0.0)
+///             }
+///             // Scrolling is already relative, and is kept so it can be taken back off next frame
+///             Action::Scroll(delta) if !matches!(mutation, InputMutation::MapToButton) => {
+///                 *self.analog.entry(keycode).or_insert(0.0) += delta;
+///                 *delta
+///             }
+///             _ => {
+///                 if self.is_down.get(&keycode).copied().unwrap_or(false) == action.is_down() {
+///                     // Duplicate event
+///                     return None;
+///                 }
+///                 self.is_down.insert(keycode, action.is_down());
+///
+///                 if action.is_down() { 1.0 } else { -1.0 }
+///             }
+///         };
+///         let val = match mutation {
+///             InputMutation::NegativeAxis => -val,
+///             _ => val,
+///         };
+///
+///         let changed = match *field {
+///             MovementInputsFields::Jump => {
+///                 let was_down = self.inputs.jump.is_down();
+///                 self.inputs.jump.modify_inputs(val > 0.0);
+///                 self.just_hot[0] = true;
+///                 was_down != self.inputs.jump.is_down()
+///             },
+///             // Given `#[axis(default = 0.5, min = -1.0, max = 1.0)]`
+///             MovementInputsFields::Vertical => {
+///                 let old = *self.inputs.vertical;
+///                 self.raw[0] += val;
+///                 *self.inputs.vertical = self.raw[0];
+///                 self.inputs.vertical.clamp(-1.0, 1.0);
+///                 old != *self.inputs.vertical
+///             },
+///             MovementInputsFields::Horizontal => {
+///                 let old = *self.inputs.horizontal;
+///                 self.inputs.horizontal.modify(val);
+///                 old != *self.inputs.horizontal
+///             }
+///         };
+///
+///         if changed { Some(*field) } else { None }
+///     }
+///
+///     /// Like `handle_frame`, but returns every field whose value changed, in the order they first changed.
+///     pub fn handle_frame_changes<'a, X: IntoIterator<Item = &'a Action>>(&mut self, actions: X) -> Vec<MovementInputsFields> {
 ///         // Set just hots back
 ///         if self.just_hot[0] {
 ///             self.inputs.jump.set_not_hot();
 ///             self.just_hot[0] = false;
 ///         }
 ///
-///         // Deal with actions
-///         for action in actions {
-///             let mutation = self.actions.get(&action.keycode());
+///         // Scrolling only lasts for one frame, so buttons bound to it are released, and axes have last frame's scroll taken off
+///         let mut changes = Vec::new();
+///         if self.is_down.get(&SCROLL_KEYCODE) == Some(&true) {
+///             changes.extend(self.handle_action(&Action::Scroll(0.0)));
+///         }
+///         if self.analog.get(&SCROLL_KEYCODE).map_or(false, |x| *x != 0.0) {
+///             changes.extend(self.handle_action(&Action::AxisMotion(SCROLL_KEYCODE, 0.0)));
+///         }
 ///
-///             if let Some((field, mutation)) = mutation {
-///                 let mut val = match mutation {
-///                     InputMutation::MapToButton | InputMutation::PositiveAxis => 1,
-///                     InputMutation::NegativeAxis => -1
-///                 };
-///                 if !action.is_down() {
-///                     val *= -1
+///         for action in actions {
+///             if let Some(field) = self.handle_action(action) {
+///                 if !changes.contains(&field) {
+///                     changes.push(field);
 ///                 }
+///             }
+///         }
+///         // If there are any paired axes, see gen_apply_axis_pairs
+///         self.apply_axis_pairs(&mut changes);
+///
+///         changes
+///     }
+/// }
+///
+/// impl InputManager for MovementInputsManager {
+///     type Inputs = MovementInputs;
+///     type Fields = MovementInputsFields;
 ///
-///                 match field {
-///                     MovementInputsFields::Jump => {
-///                         self.inputs.jump.modify_inputs(val > 0);
-///                         self.just_hot[0] = true;
-///                     },
-///                     MovementInputsFields::Vertical => {
-///                         self.inputs.vertical.modify(val);
-///                     },
-///                     MovementInputsFields::Horizontal => {
-///                         self.inputs.horizontal.modify(val);
+///     fn handle_frame<'a, X: IntoIterator<Item = &'a Action>>(&mut self, actions: X) -> () {
+///         self.handle_frame_changes(actions);
+///     }
+///
+///     fn value_of(&self, field: MovementInputsFields) -> InputValue {
+///         match field {
+///             MovementInputsFields::Jump => InputValue::Button(self.inputs.jump.is_down()),
+///             MovementInputsFields::Vertical => InputValue::Axis(*self.inputs.vertical),
+///         }
+///     }
+///
+///     fn set_value(&mut self, field: MovementInputsFields, value: InputValue) {
+///         match (field, value) {
+///             (MovementInputsFields::Jump, InputValue::Button(x)) => {
+///                 if !x {
+///                     // Forget any bound keys being held, so their releases are ignored
+///                     for (keycode, (bound, _)) in self.actions.iter() {
+///                         if *bound == field {
+///                             self.is_down.insert(*keycode, false);
+///                         }
 ///                     }
 ///                 }
+///                 self.inputs.jump.set_down(x);
+///                 self.just_hot[0] = true;
+///             }
+///             (MovementInputsFields::Vertical, InputValue::Axis(x)) => {
+///                 *self.inputs.vertical = x;
+///                 self.raw[0] = x;
 ///             }
+///             _ => {}
 ///         }
 ///     }
+///
+///     fn reset(&mut self) {
+///         for is_down in self.is_down.values_mut() {
+///             *is_down = false;
+///         }
+///         self.analog.clear();
+///
+///         self.inputs.jump.reset();
+///         self.just_hot[0] = true;
+///
+///         *self.inputs.vertical = 0.5;
+///         self.raw[0] = 0.5;
+///         self.inputs.horizontal.reset();
+///     }
 /// }
 /// ```
+#[allow(clippy::too_many_arguments)]
 fn gen_trait_impl(
     manager: &Ident,
     struct_ident: &Ident,
     fields_enum: &Ident,
     buttons: &[Ident],
     axes: &[Ident],
+    axis_defaults: &[Option<f32>],
+    axis_processing: &[AxisProcessing],
+    axis_pairs: &[AxisPair],
     buttons_caps: &[Ident],
     axes_caps: &[Ident],
+    generics: &Generics,
 ) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let just_hot_resets = gen_just_hot_resets(buttons);
-    let field_match_modify =
-        gen_field_mutation(buttons, axes, buttons_caps, axes_caps, fields_enum);
+    let input_resets = gen_input_resets(buttons, axes, axis_defaults, axis_processing);
+    let field_match_modify = gen_field_mutation(
+        buttons,
+        axes,
+        axis_processing,
+        buttons_caps,
+        axes_caps,
+        fields_enum,
+    );
+    let (value_arms, set_value_arms) = gen_value_arms(
+        buttons,
+        axes,
+        axis_processing,
+        buttons_caps,
+        axes_caps,
+        fields_enum,
+    );
+    let (apply_axis_pairs, apply_axis_pairs_call) = if axis_pairs.is_empty() {
+        (quote!(), quote!())
+    } else {
+        (
+            gen_apply_axis_pairs(axes, axis_processing, axis_pairs, axes_caps, fields_enum),
+            quote!(self.apply_axis_pairs(&mut changes);),
+        )
+    };
 
+    // The methods' own generics have unusual names so they can't clash with the inputs struct's
     quote!(
-        impl InputManager for #manager {
-            type Inputs = #struct_ident;
-
-            fn handle_frame<'a, X: IntoIterator<Item = &'a ::stockton_input::Action>>(&mut self, actions: X) -> () {
-                #(#just_hot_resets)*
+        impl #impl_generics #manager #ty_generics #where_clause {
+            /// Handle a single action, returning the field whose value it changed, if any.
+            // If there are no fields, everything after matching on one is unreachable
+            #[allow(unreachable_code)]
+            fn handle_action(&mut self, action: &::stockton_input::Action) -> Option<#fields_enum> {
+                use ::stockton_input::{Action, InputMutation};
 
-                for action in actions {
-                    let mutation = self.actions.get(&action.keycode());
+                let keycode = action.keycode();
+                let (field, mutation) = self.actions.get(&keycode)?;
 
-                    if let Some((field, mutation)) = mutation {
-                        if *self.is_down.get(&action.keycode()).unwrap() == action.is_down() {
+                let val = match action {
+                    // Analog input bound to an axis, so apply the change since the last motion
+                    Action::AxisMotion(_, value) if !matches!(mutation, InputMutation::MapToButton) => {
+                        value - self.analog.insert(keycode, *value).unwrap_or(0.0)
+                    }
+                    // Scrolling is already relative, and is kept so it can be taken back off next frame
+                    Action::Scroll(delta) if !matches!(mutation, InputMutation::MapToButton) => {
+                        *self.analog.entry(keycode).or_insert(0.0) += delta;
+                        *delta
+                    }
+                    _ => {
+                        if self.is_down.get(&keycode).copied().unwrap_or(false) == action.is_down() {
                             // Duplicate event
-                            continue;
+                            return None;
                         }
+                        self.is_down.insert(keycode, action.is_down());
 
-                        self.is_down.insert(action.keycode(), action.is_down());
+                        if action.is_down() { 1.0 } else { -1.0 }
+                    }
+                };
+                let val = match mutation {
+                    InputMutation::NegativeAxis => -val,
+                    _ => val,
+                };
 
-                        use ::stockton_input::InputMutation;
+                let changed = #field_match_modify;
 
-                        let mut val = match mutation {
-                            InputMutation::MapToButton | InputMutation::PositiveAxis => 1,
-                            InputMutation::NegativeAxis => -1
-                        };
-                        if !action.is_down() {
-                            val *= -1
-                        }
+                if changed { Some(*field) } else { None }
+            }
+
+            /// Like `handle_frame`, but returns every field whose value changed, in the order they first changed.
+            pub fn handle_frame_changes<'__actions, __X: IntoIterator<Item = &'__actions ::stockton_input::Action>>(&mut self, actions: __X) -> Vec<#fields_enum> {
+                #(#just_hot_resets)*
+
+                // Scrolling only lasts for one frame, so buttons bound to it are released, and axes have last frame's scroll taken off
+                let mut changes = Vec::new();
+                if self.is_down.get(&::stockton_input::SCROLL_KEYCODE) == Some(&true) {
+                    changes.extend(self.handle_action(&::stockton_input::Action::Scroll(0.0)));
+                }
+                if self.analog.get(&::stockton_input::SCROLL_KEYCODE).map_or(false, |x| *x != 0.0) {
+                    changes.extend(self.handle_action(&::stockton_input::Action::AxisMotion(::stockton_input::SCROLL_KEYCODE, 0.0)));
+                }
 
-                        #field_match_modify
+                for action in actions {
+                    if let Some(field) = self.handle_action(action) {
+                        if !changes.contains(&field) {
+                            changes.push(field);
+                        }
                     }
                 }
+                #apply_axis_pairs_call
+
+                changes
+            }
+
+            #apply_axis_pairs
+        }
+
+        impl #impl_generics InputManager for #manager #ty_generics #where_clause {
+            type Inputs = #struct_ident #ty_generics;
+            type Fields = #fields_enum;
+
+            fn handle_frame<'__actions, __X: IntoIterator<Item = &'__actions ::stockton_input::Action>>(&mut self, actions: __X) -> () {
+                self.handle_frame_changes(actions);
             }
 
             fn get_inputs(&self) -> &Self::Inputs {
                 &self.inputs
             }
+
+            fn value_of(&self, field: #fields_enum) -> ::stockton_input::InputValue {
+                match field {
+                    #(#value_arms),*
+                }
+            }
+
+            fn set_value(&mut self, field: #fields_enum, value: ::stockton_input::InputValue) {
+                match (field, value) {
+                    #(#set_value_arms)*
+                    _ => {}
+                }
+            }
+
+            fn reset(&mut self) {
+                for is_down in self.is_down.values_mut() {
+                    *is_down = false;
+                }
+                self.analog.clear();
+
+                #(#input_resets)*
+            }
         }
     )
 }
 
+/// Generate the match arms for `value_of` and `set_value`.
+/// Axes that keep a raw value have it set too, so later actions carry on from the new value.
+/// Releasing a button also forgets which of its keys are held, so releasing them later doesn't release it twice.
+/// Used by gen_trait_impl.
+fn gen_value_arms(
+    buttons: &[Ident],
+    axes: &[Ident],
+    axis_processing: &[AxisProcessing],
+    buttons_caps: &[Ident],
+    axes_caps: &[Ident],
+    fields_enum_ident: &Ident,
+) -> (Vec<TokenStream2>, Vec<TokenStream2>) {
+    let button_values = buttons.iter().zip(buttons_caps.iter()).map(|(field, cap)| {
+        quote!(#fields_enum_ident::#cap => ::stockton_input::InputValue::Button(self.inputs.#field.is_down()))
+    });
+    let axis_values = axes.iter().zip(axes_caps.iter()).map(|(field, cap)| {
+        quote!(#fields_enum_ident::#cap => ::stockton_input::InputValue::Axis(*self.inputs.#field))
+    });
+
+    let button_sets =
+        buttons
+            .iter()
+            .enumerate()
+            .zip(buttons_caps.iter())
+            .map(|((idx, field), cap)| {
+                quote!(
+                    (#fields_enum_ident::#cap, ::stockton_input::InputValue::Button(x)) => {
+                        if !x {
+                            // Forget any bound keys being held, so their releases are ignored
+                            for (keycode, (bound, _)) in self.actions.iter() {
+                                if *bound == field {
+                                    self.is_down.insert(*keycode, false);
+                                }
+                            }
+                        }
+                        self.inputs.#field.set_down(x);
+                        self.just_hot[#idx] = true;
+                    }
+                )
+            });
+    let axis_sets = axes
+        .iter()
+        .zip(raw_indices(axis_processing))
+        .zip(axes_caps.iter())
+        .map(|((field, raw_idx), cap)| {
+            let set_raw = raw_idx.map(|idx| quote!(self.raw[#idx] = x;));
+            quote!(
+                (#fields_enum_ident::#cap, ::stockton_input::InputValue::Axis(x)) => {
+                    *self.inputs.#field = x;
+                    #set_raw
+                }
+            )
+        });
+
+    (
+        button_values.chain(axis_values).collect(),
+        button_sets.chain(axis_sets).collect(),
+    )
+}
+
+/// Generate the statements used to release every button and set every axis back to its default.
+/// Buttons are marked as just hot, so that `is_just_up` works as expected for a frame.
+/// Used by gen_trait_impl.
+fn gen_input_resets(
+    buttons: &[Ident],
+    axes: &[Ident],
+    axis_defaults: &[Option<f32>],
+    axis_processing: &[AxisProcessing],
+) -> Vec<TokenStream2> {
+    let button_resets = buttons.iter().enumerate().map(|(i, v)| {
+        quote!(
+            self.inputs.#v.reset();
+            self.just_hot[#i] = true;
+        )
+    });
+    let axis_resets =
+        axes.iter()
+            .zip(axis_processing.iter())
+            .map(|(v, processing)| match processing.start {
+                Some(start) => quote!(
+                    *self.inputs.#v = #start;
+                ),
+                None => quote!(
+                    self.inputs.#v.reset();
+                ),
+            });
+    let raw_inits = gen_raw_inits(axis_defaults, axis_processing);
+    let raw_resets = raw_inits.iter().enumerate().map(|(i, init)| {
+        quote!(
+            self.raw[#i] = #init;
+        )
+    });
+
+    button_resets.chain(axis_resets).chain(raw_resets).collect()
+}
+
+/// Generate the starting raw value of each axis that keeps one, which is its default or zero.
+/// Used by gen_manager_struct and gen_input_resets.
+fn gen_raw_inits(
+    axis_defaults: &[Option<f32>],
+    axis_processing: &[AxisProcessing],
+) -> Vec<TokenStream2> {
+    axis_defaults
+        .iter()
+        .zip(axis_processing.iter())
+        .filter(|(_, processing)| processing.keeps_raw())
+        .map(|(default, _)| {
+            let default = default.unwrap_or(0.0);
+            quote!(#default)
+        })
+        .collect()
+}
+
 /// Generate the if statements used to reset self.just_hot at the start of each frame
 /// Used by gen_trait_impl.
 fn gen_just_hot_resets(buttons: &[Ident]) -> Vec<TokenStream2> {
@@ -295,10 +1283,12 @@ fn gen_just_hot_resets(buttons: &[Ident]) -> Vec<TokenStream2> {
 }
 
 /// Generate the code that actually mutates an input field by matching on a fields enum.
+/// The generated match evaluates to whether the value of the field changed.
 /// Used by gen_trait_impl.
 fn gen_field_mutation(
     buttons: &[Ident],
     axes: &[Ident],
+    axis_processing: &[AxisProcessing],
     buttons_caps: &[Ident],
     axes_caps: &[Ident],
     fields_enum_ident: &Ident,
@@ -306,17 +1296,19 @@ fn gen_field_mutation(
     let arms = {
         let mut btn_arms: Vec<TokenStream2> =
             gen_mutate_match_arms_buttons(buttons, buttons_caps, fields_enum_ident);
-        let mut axes_arms = gen_mutate_match_arms_axes(axes, axes_caps, fields_enum_ident);
+        let mut axes_arms =
+            gen_mutate_match_arms_axes(axes, axis_processing, axes_caps, fields_enum_ident);
 
         btn_arms.append(&mut axes_arms);
 
         btn_arms
     };
 
+    // Matching by value means this still compiles if there are no fields at all
     quote!(
-        match field {
+        match *field {
             #(#arms),*
-        };
+        }
     )
 }
 
@@ -333,8 +1325,10 @@ fn gen_mutate_match_arms_buttons(
         .map(|((idx, field), cap)| {
             quote!(
                 #fields_enum_ident::#cap => {
-                    self.inputs.#field.modify_inputs(val > 0);
+                    let was_down = self.inputs.#field.is_down();
+                    self.inputs.#field.modify_inputs(val > 0.0);
                     self.just_hot[#idx] = true;
+                    was_down != self.inputs.#field.is_down()
                 }
             )
         })
@@ -342,19 +1336,142 @@ fn gen_mutate_match_arms_buttons(
 }
 
 /// Used by gen_field_mutation.
+/// Bounded axes keep their raw value separately, so that releasing one of several held keys doesn't take the axis past where the others leave it.
+/// Paired axes only have their raw value changed, and are set by `apply_axis_pairs` at the end of the frame.
 fn gen_mutate_match_arms_axes(
     axes: &[Ident],
+    axis_processing: &[AxisProcessing],
     axes_caps: &[Ident],
     fields_enum_ident: &Ident,
 ) -> Vec<TokenStream2> {
     axes.iter()
+        .zip(axis_processing.iter())
+        .zip(raw_indices(axis_processing))
         .zip(axes_caps.iter())
-        .map(|(field, cap)| {
-            quote!(
-                #fields_enum_ident::#cap => {
-                    self.inputs.#field.modify(val);
+        .map(|(((field, processing), raw_idx), cap)| {
+            match (raw_idx, processing.paired, &processing.bounds) {
+                (Some(idx), true, _) => quote!(
+                    #fields_enum_ident::#cap => {
+                        self.raw[#idx] += val;
+                        false
+                    }
+                ),
+                (Some(idx), false, Some(bounds)) => {
+                    let (min, max) = bounds.clamp_args();
+                    quote!(
+                        #fields_enum_ident::#cap => {
+                            let old = *self.inputs.#field;
+                            self.raw[#idx] += val;
+                            *self.inputs.#field = self.raw[#idx];
+                            self.inputs.#field.clamp(#min, #max);
+                            old != *self.inputs.#field
+                        }
+                    )
                 }
-            )
+                _ => quote!(
+                    #fields_enum_ident::#cap => {
+                        let old = *self.inputs.#field;
+                        self.inputs.#field.modify(val);
+                        old != *self.inputs.#field
+                    }
+                ),
+            }
         })
         .collect::<Vec<TokenStream2>>()
 }
+
+/// Get the index into `raw` of each axis, or None if it doesn't keep a raw value.
+fn raw_indices(axis_processing: &[AxisProcessing]) -> Vec<Option<usize>> {
+    let mut next = 0;
+    axis_processing
+        .iter()
+        .map(|processing| {
+            if processing.keeps_raw() {
+                next += 1;
+                Some(next - 1)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Generate the method that sets each pair of axes from their raw values, once every action in a frame has been handled.
+/// Used by gen_trait_impl.
+///
+/// Example output:
+/// ```ignore
+/// /// Set each pair of axes from their raw values, with a radial deadzone and their combined magnitude clamped to 1.
+/// /// Any fields whose values changed are added to `changes`.
+/// fn apply_axis_pairs(&mut self, changes: &mut Vec<MovementInputsFields>) {
+///     // Given `#[axis(pair = "move", deadzone = 0.15)]` on vertical and horizontal
+///     {
+///         let (x, y) = radial_deadzone(self.raw[0], self.raw[1], 0.15);
+///         if *self.inputs.vertical != x {
+///             *self.inputs.vertical = x;
+///             if !changes.contains(&MovementInputsFields::Vertical) {
+///                 changes.push(MovementInputsFields::Vertical);
+///             }
+///         }
+///         if *self.inputs.horizontal != y {
+///             *self.inputs.horizontal = y;
+///             if !changes.contains(&MovementInputsFields::Horizontal) {
+///                 changes.push(MovementInputsFields::Horizontal);
+///             }
+///         }
+///     }
+/// }
+/// ```
+fn gen_apply_axis_pairs(
+    axes: &[Ident],
+    axis_processing: &[AxisProcessing],
+    axis_pairs: &[AxisPair],
+    axes_caps: &[Ident],
+    fields_enum_ident: &Ident,
+) -> TokenStream2 {
+    let raw_indices = raw_indices(axis_processing);
+
+    // The raw value of the given axis, clamped to its bounds if it has any
+    let raw_value = |i: usize| {
+        let idx = raw_indices[i].unwrap();
+        match &axis_processing[i].bounds {
+            Some(bounds) => {
+                let (min, max) = bounds.clamp_args();
+                quote!(self.raw[#idx].clamp(#min, #max))
+            }
+            None => quote!(self.raw[#idx]),
+        }
+    };
+    let set_value = |i: usize, value: TokenStream2| {
+        let field = &axes[i];
+        let cap = &axes_caps[i];
+        quote!(
+            if *self.inputs.#field != #value {
+                *self.inputs.#field = #value;
+                if !changes.contains(&#fields_enum_ident::#cap) {
+                    changes.push(#fields_enum_ident::#cap);
+                }
+            }
+        )
+    };
+
+    let pairs = axis_pairs.iter().map(|pair| {
+        let (x_raw, y_raw) = (raw_value(pair.x), raw_value(pair.y));
+        let deadzone = pair.deadzone;
+        let (set_x, set_y) = (set_value(pair.x, quote!(x)), set_value(pair.y, quote!(y)));
+
+        quote!({
+            let (x, y) = ::stockton_input::radial_deadzone(#x_raw, #y_raw, #deadzone);
+            #set_x
+            #set_y
+        })
+    });
+
+    quote!(
+        /// Set each pair of axes from their raw values, with a radial deadzone and their combined magnitude clamped to 1.
+        /// Any fields whose values changed are added to `changes`.
+        fn apply_axis_pairs(&mut self, changes: &mut Vec<#fields_enum_ident>) {
+            #(#pairs)*
+        }
+    )
+}
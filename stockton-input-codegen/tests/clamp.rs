@@ -0,0 +1,93 @@
+//! Clamping axes with `#[axis(min = .., max = ..)]`.
+
+use std::collections::BTreeMap;
+
+use stockton_input::{Action, Axis, InputManager, InputMutation};
+use stockton_input_codegen::InputManager;
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct ClampedInputs {
+    #[axis(min = -1.0, max = 1.0)]
+    walk: Axis,
+    #[axis(max = 0.5)]
+    throttle: Axis,
+    #[axis]
+    unclamped: Axis,
+}
+
+fn manager() -> ClampedInputsManager {
+    let mut actions = BTreeMap::new();
+    // A normal key and a sprint key both push walk forwards
+    actions.insert(1, (ClampedInputsFields::Walk, InputMutation::PositiveAxis));
+    actions.insert(2, (ClampedInputsFields::Walk, InputMutation::PositiveAxis));
+    actions.insert(3, (ClampedInputsFields::Walk, InputMutation::NegativeAxis));
+    actions.insert(4, (ClampedInputsFields::Walk, InputMutation::NegativeAxis));
+    actions.insert(
+        5,
+        (ClampedInputsFields::Throttle, InputMutation::PositiveAxis),
+    );
+    actions.insert(
+        6,
+        (ClampedInputsFields::Unclamped, InputMutation::PositiveAxis),
+    );
+    actions.insert(
+        7,
+        (ClampedInputsFields::Unclamped, InputMutation::PositiveAxis),
+    );
+
+    ClampedInputsManager::new(actions)
+}
+
+#[test]
+fn two_contributors_stay_clamped() {
+    let mut manager = manager();
+
+    manager.handle_frame(&[Action::KeyPress(1), Action::KeyPress(2)]);
+    assert_eq!(*manager.get_inputs().walk, 1.0);
+
+    // Releasing one contributor leaves the other one holding it at the max
+    let changed = manager.handle_frame_changes(&[Action::KeyRelease(1)]);
+    assert_eq!(*manager.get_inputs().walk, 1.0);
+    assert!(changed.is_empty());
+
+    manager.handle_frame(&[Action::KeyRelease(2)]);
+    assert_eq!(*manager.get_inputs().walk, 0.0);
+}
+
+#[test]
+fn both_ends_are_clamped() {
+    let mut manager = manager();
+
+    manager.handle_frame(&[Action::KeyPress(3), Action::KeyPress(4)]);
+    assert_eq!(*manager.get_inputs().walk, -1.0);
+
+    manager.handle_frame(&[Action::KeyPress(1), Action::KeyPress(2)]);
+    assert_eq!(*manager.get_inputs().walk, 0.0);
+
+    manager.handle_frame(&[Action::KeyRelease(3), Action::KeyRelease(4)]);
+    assert_eq!(*manager.get_inputs().walk, 1.0);
+}
+
+#[test]
+fn only_given_bounds_are_clamped() {
+    let mut manager = manager();
+
+    manager.handle_frame(&[
+        Action::KeyPress(5),
+        Action::KeyPress(6),
+        Action::KeyPress(7),
+    ]);
+    assert_eq!(*manager.get_inputs().throttle, 0.5);
+    assert_eq!(*manager.get_inputs().unclamped, 2.0);
+}
+
+#[test]
+fn analog_values_are_clamped() {
+    let mut manager = manager();
+
+    manager.handle_frame(&[Action::AxisMotion(1, 3.0)]);
+    assert_eq!(*manager.get_inputs().walk, 1.0);
+
+    manager.handle_frame(&[Action::AxisMotion(1, 0.25)]);
+    assert_eq!(*manager.get_inputs().walk, 0.25);
+}
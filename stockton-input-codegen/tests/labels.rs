@@ -0,0 +1,63 @@
+//! Getting the original field names and labels back from the generated fields enum.
+
+use stockton_input::{Axis, Button, InputManager};
+use stockton_input_codegen::InputManager;
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct LabelledInputs {
+    #[axis(label = "Move Left/Right", default = 0.5)]
+    move_horizontal: Axis,
+    #[axis]
+    vertical: Axis,
+    #[button(label = "Jump!")]
+    jump: Button,
+    #[button]
+    fire_secondary: Button,
+}
+
+const FIELDS: [LabelledInputsFields; 4] = [
+    LabelledInputsFields::MoveHorizontal,
+    LabelledInputsFields::Vertical,
+    LabelledInputsFields::Jump,
+    LabelledInputsFields::FireSecondary,
+];
+
+#[test]
+fn names_are_the_original_fields() {
+    let names: Vec<&str> = FIELDS.iter().map(|f| f.name()).collect();
+
+    assert_eq!(
+        names,
+        ["move_horizontal", "vertical", "jump", "fire_secondary"]
+    );
+}
+
+#[test]
+fn labels_default_to_names() {
+    assert_eq!(
+        LabelledInputsFields::MoveHorizontal.label(),
+        "Move Left/Right"
+    );
+    assert_eq!(LabelledInputsFields::Vertical.label(), "vertical");
+    assert_eq!(LabelledInputsFields::Jump.label(), "Jump!");
+    assert_eq!(
+        LabelledInputsFields::FireSecondary.label(),
+        "fire_secondary"
+    );
+}
+
+#[test]
+fn names_match_all_inputs() {
+    let manager = LabelledInputsManager::new(Default::default());
+    let mut from_manager: Vec<&str> = manager
+        .all_inputs()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let mut from_fields: Vec<&str> = FIELDS.iter().map(|f| f.name()).collect();
+    from_manager.sort_unstable();
+    from_fields.sort_unstable();
+
+    assert_eq!(from_manager, from_fields);
+    assert_eq!(*manager.get_inputs().move_horizontal, 0.5);
+}
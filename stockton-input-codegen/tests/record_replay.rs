@@ -0,0 +1,116 @@
+//! Recording inputs with `value_of` and replaying them with `set_value`.
+
+use std::collections::BTreeMap;
+
+use stockton_input::{Action, Axis, Button, InputManager, InputMutation, InputValue};
+use stockton_input_codegen::InputManager;
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct RecordedInputs {
+    #[axis(min = -1.0, max = 1.0)]
+    x: Axis,
+    #[button]
+    fire: Button,
+}
+
+const FIELDS: [RecordedInputsFields; 2] = [RecordedInputsFields::X, RecordedInputsFields::Fire];
+
+fn manager() -> RecordedInputsManager {
+    let mut actions = BTreeMap::new();
+    actions.insert(1, (RecordedInputsFields::X, InputMutation::PositiveAxis));
+    actions.insert(2, (RecordedInputsFields::Fire, InputMutation::MapToButton));
+    actions.insert(3, (RecordedInputsFields::Fire, InputMutation::MapToButton));
+
+    RecordedInputsManager::new(actions)
+}
+
+fn record<M: InputManager>(manager: &M, fields: &[M::Fields]) -> Vec<InputValue> {
+    fields
+        .iter()
+        .map(|field| manager.value_of(*field))
+        .collect()
+}
+
+#[test]
+fn round_trip() {
+    let mut live = manager();
+    let mut replay = manager();
+
+    let frames = [
+        vec![Action::KeyPress(1), Action::KeyPress(2)],
+        vec![Action::KeyPress(3)],
+        vec![Action::KeyRelease(2), Action::KeyRelease(3)],
+        vec![Action::KeyRelease(1)],
+        vec![],
+    ];
+    for actions in frames.iter() {
+        live.handle_frame(actions);
+        let recorded = record(&live, &FIELDS);
+
+        replay.handle_frame(&[]);
+        for (field, value) in FIELDS.iter().zip(recorded.iter()) {
+            replay.set_value(*field, *value);
+        }
+
+        assert_eq!(record(&replay, &FIELDS), recorded);
+        let (live_fire, replay_fire) = (&live.get_inputs().fire, &replay.get_inputs().fire);
+        assert_eq!(live_fire.is_just_down(), replay_fire.is_just_down());
+        assert_eq!(live_fire.is_just_up(), replay_fire.is_just_up());
+    }
+}
+
+#[test]
+fn values_of_the_wrong_kind_are_ignored() {
+    let mut manager = manager();
+
+    manager.set_value(RecordedInputsFields::X, InputValue::Button(true));
+    manager.set_value(RecordedInputsFields::Fire, InputValue::Axis(1.0));
+
+    assert_eq!(
+        record(&manager, &FIELDS),
+        [InputValue::Axis(0.0), InputValue::Button(false)]
+    );
+}
+
+#[test]
+fn actions_carry_on_from_set_values() {
+    let mut manager = manager();
+
+    manager.set_value(RecordedInputsFields::X, InputValue::Axis(0.5));
+    manager.handle_frame(&[Action::KeyPress(1)]);
+    assert_eq!(*manager.get_inputs().x, 1.0);
+
+    manager.handle_frame(&[Action::KeyRelease(1)]);
+    assert_eq!(*manager.get_inputs().x, 0.5);
+}
+
+#[test]
+fn releasing_a_held_button() {
+    let mut manager = manager();
+    manager.handle_frame(&[Action::KeyPress(2)]);
+
+    manager.handle_frame(&[]);
+    manager.set_value(RecordedInputsFields::Fire, InputValue::Button(false));
+    assert!(manager.get_inputs().fire.is_just_up());
+
+    // The key's release is ignored, since it's no longer counted as held
+    manager.handle_frame(&[Action::KeyRelease(2)]);
+    assert!(manager.get_inputs().fire.is_up());
+    assert!(!manager.get_inputs().fire.is_hot);
+
+    manager.handle_frame(&[Action::KeyPress(2)]);
+    assert!(manager.get_inputs().fire.is_just_down());
+}
+
+#[test]
+fn pressing_a_button_held_by_several_keys() {
+    let mut manager = manager();
+    manager.handle_frame(&[Action::KeyPress(2), Action::KeyPress(3)]);
+
+    manager.set_value(RecordedInputsFields::Fire, InputValue::Button(true));
+
+    manager.handle_frame(&[Action::KeyRelease(2)]);
+    assert!(manager.get_inputs().fire.is_down());
+    manager.handle_frame(&[Action::KeyRelease(3)]);
+    assert!(manager.get_inputs().fire.is_just_up());
+}
@@ -0,0 +1,81 @@
+//! Scrolling only lasting for the frame it happened in.
+
+use stockton_input::{Action, Axis, Button, InputManager, InputMutation, SCROLL_KEYCODE};
+use stockton_input_codegen::InputManager;
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct ZoomInputs {
+    #[axis]
+    zoom: Axis,
+    #[button]
+    next: Button,
+}
+
+fn axis_manager() -> ZoomInputsManager {
+    ZoomInputsManager::builder()
+        .bind(
+            SCROLL_KEYCODE,
+            ZoomInputsFields::Zoom,
+            InputMutation::PositiveAxis,
+        )
+        .build()
+}
+
+#[test]
+fn scrolled_axis_resets_next_frame() {
+    let mut manager = axis_manager();
+
+    manager.handle_frame(&[Action::Scroll(1.0), Action::Scroll(0.5)]);
+    assert_eq!(*manager.get_inputs().zoom, 1.5);
+
+    assert_eq!(
+        manager.handle_frame_changes(&[]),
+        vec![ZoomInputsFields::Zoom]
+    );
+    assert_eq!(*manager.get_inputs().zoom, 0.0);
+
+    assert!(manager.handle_frame_changes(&[]).is_empty());
+}
+
+#[test]
+fn scrolling_every_frame_does_not_build_up() {
+    let mut manager = axis_manager();
+
+    manager.handle_frame(&[Action::Scroll(1.0)]);
+    manager.handle_frame(&[Action::Scroll(2.0)]);
+    assert_eq!(*manager.get_inputs().zoom, 2.0);
+}
+
+#[test]
+fn negative_scroll_axis_resets() {
+    let mut manager = ZoomInputsManager::builder()
+        .bind(
+            SCROLL_KEYCODE,
+            ZoomInputsFields::Zoom,
+            InputMutation::NegativeAxis,
+        )
+        .build();
+
+    manager.handle_frame(&[Action::Scroll(1.0)]);
+    assert_eq!(*manager.get_inputs().zoom, -1.0);
+
+    manager.handle_frame(&[]);
+    assert_eq!(*manager.get_inputs().zoom, 0.0);
+}
+
+#[test]
+fn scrolled_button_is_pressed_for_one_frame() {
+    let mut manager = ZoomInputsManager::builder()
+        .bind(
+            SCROLL_KEYCODE,
+            ZoomInputsFields::Next,
+            InputMutation::MapToButton,
+        )
+        .build();
+
+    manager.handle_frame(&[Action::Scroll(1.0)]);
+    assert!(manager.get_inputs().next.is_just_down());
+
+    manager.handle_frame(&[]);
+    assert!(manager.get_inputs().next.is_just_up());
+}
@@ -0,0 +1,80 @@
+//! Structs without any axes, without any buttons, or without either.
+
+use std::collections::BTreeMap;
+
+use stockton_input::{Action, Axis, Button, InputManager, InputMutation};
+use stockton_input_codegen::InputManager;
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct OnlyAxes {
+    #[axis]
+    x: Axis,
+    #[axis]
+    y: Axis,
+}
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct OnlyButtons {
+    #[button]
+    a: Button,
+    #[button]
+    b: Button,
+}
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct NoInputs {
+    other: u32,
+}
+
+#[derive(InputManager, Default, Debug, Clone)]
+struct Empty {}
+
+#[test]
+fn only_axes() {
+    let mut actions = BTreeMap::new();
+    actions.insert(1, (OnlyAxesFields::X, InputMutation::PositiveAxis));
+    actions.insert(2, (OnlyAxesFields::Y, InputMutation::NegativeAxis));
+    let mut manager = OnlyAxesManager::new(actions);
+
+    let changed = manager.handle_frame_changes(&[Action::KeyPress(1), Action::KeyPress(2)]);
+    assert_eq!(changed, vec![OnlyAxesFields::X, OnlyAxesFields::Y]);
+    assert_eq!(*manager.get_inputs().x, 1.0);
+    assert_eq!(*manager.get_inputs().y, -1.0);
+
+    manager.reset();
+    assert_eq!(*manager.get_inputs().x, 0.0);
+    assert_eq!(*manager.get_inputs().y, 0.0);
+}
+
+#[test]
+fn only_buttons() {
+    let mut actions = BTreeMap::new();
+    actions.insert(1, (OnlyButtonsFields::A, InputMutation::MapToButton));
+    let mut manager = OnlyButtonsManager::new(actions);
+
+    manager.handle_frame(&[Action::KeyPress(1)]);
+    assert!(manager.get_inputs().a.is_just_down());
+    assert!(manager.get_inputs().b.is_up());
+
+    manager.handle_frame(&[]);
+    assert!(manager.get_inputs().a.is_down());
+    assert!(!manager.get_inputs().a.is_hot);
+
+    manager.reset();
+    assert!(manager.get_inputs().a.is_just_up());
+}
+
+#[test]
+fn no_inputs() {
+    let mut manager = NoInputsManager::new(BTreeMap::new());
+
+    assert!(manager
+        .handle_frame_changes(&[Action::KeyPress(1)])
+        .is_empty());
+    manager.reset();
+    assert_eq!(manager.get_inputs().other, 0);
+
+    let mut manager = EmptyManager::new(BTreeMap::new());
+    manager.handle_frame(&[Action::KeyPress(1), Action::KeyRelease(1)]);
+    manager.reset();
+}
@@ -0,0 +1,123 @@
+//! Taking snapshots of a manager's state and restoring them.
+
+use std::collections::BTreeMap;
+
+use stockton_input::{Action, Axis, Button, InputManager, InputMutation};
+use stockton_input_codegen::InputManager;
+
+#[derive(InputManager, Default, Debug, Clone, PartialEq)]
+struct SnapshotInputs {
+    #[axis]
+    vertical: Axis,
+    #[axis]
+    stick: Axis,
+    #[button]
+    jump: Button,
+}
+
+fn manager() -> SnapshotInputsManager {
+    let mut actions = BTreeMap::new();
+    actions.insert(
+        1,
+        (SnapshotInputsFields::Vertical, InputMutation::PositiveAxis),
+    );
+    actions.insert(
+        2,
+        (SnapshotInputsFields::Vertical, InputMutation::NegativeAxis),
+    );
+    actions.insert(
+        3,
+        (SnapshotInputsFields::Stick, InputMutation::PositiveAxis),
+    );
+    actions.insert(4, (SnapshotInputsFields::Jump, InputMutation::MapToButton));
+
+    SnapshotInputsManager::new(actions)
+}
+
+#[test]
+fn restoring_gives_the_same_state() {
+    let mut rolled_back = manager();
+    let mut reference = manager();
+
+    let before = [
+        Action::KeyPress(1),
+        Action::AxisMotion(3, 0.25),
+        Action::KeyPress(4),
+    ];
+    rolled_back.handle_frame(&before);
+    reference.handle_frame(&before);
+    let snapshot = rolled_back.snapshot();
+    assert_eq!(snapshot.inputs(), reference.get_inputs());
+
+    // Go off in a different direction, then come back
+    rolled_back.handle_frame(&[
+        Action::KeyRelease(1),
+        Action::KeyPress(2),
+        Action::AxisMotion(3, -1.0),
+        Action::KeyRelease(4),
+    ]);
+    assert_ne!(rolled_back.get_inputs(), reference.get_inputs());
+    rolled_back.restore(snapshot.clone());
+    assert_eq!(rolled_back.get_inputs(), reference.get_inputs());
+    assert_eq!(rolled_back.snapshot().inputs(), snapshot.inputs());
+
+    // Held keys and analog values are restored too, so both carry on the same way
+    let after = [
+        vec![],
+        vec![Action::KeyRelease(1), Action::KeyPress(2)],
+        vec![Action::AxisMotion(3, 0.5), Action::KeyRelease(4)],
+        vec![Action::KeyRelease(2)],
+    ];
+    for actions in after.iter() {
+        rolled_back.handle_frame(actions);
+        reference.handle_frame(actions);
+        assert_eq!(rolled_back.get_inputs(), reference.get_inputs());
+        assert_eq!(
+            rolled_back.get_inputs().jump.is_just_up(),
+            reference.get_inputs().jump.is_just_up()
+        );
+    }
+}
+
+#[test]
+fn restoring_a_fresh_snapshot_resets() {
+    let mut manager = manager();
+    let fresh = manager.snapshot();
+
+    manager.handle_frame(&[Action::KeyPress(1), Action::KeyPress(4)]);
+    manager.restore(fresh);
+    assert_eq!(manager.get_inputs(), &SnapshotInputs::default());
+
+    // The keys aren't counted as held any more, so releasing them does nothing
+    manager.handle_frame(&[Action::KeyRelease(1), Action::KeyRelease(4)]);
+    assert_eq!(manager.get_inputs(), &SnapshotInputs::default());
+}
+
+#[test]
+fn restoring_after_rebinding() {
+    let mut old = manager();
+    old.handle_frame(&[Action::KeyPress(1), Action::AxisMotion(3, 0.5)]);
+    let snapshot = old.snapshot();
+
+    // Vertical is now on 5 and 6 instead of 1 and 2
+    let mut actions = BTreeMap::new();
+    actions.insert(
+        5,
+        (SnapshotInputsFields::Vertical, InputMutation::PositiveAxis),
+    );
+    actions.insert(
+        6,
+        (SnapshotInputsFields::Vertical, InputMutation::NegativeAxis),
+    );
+    actions.insert(4, (SnapshotInputsFields::Jump, InputMutation::MapToButton));
+    let mut rebound = SnapshotInputsManager::new(actions);
+    rebound.restore(snapshot);
+    assert_eq!(*rebound.get_inputs().vertical, 1.0);
+
+    // Pressing a newly bound key works, and keys that aren't bound any more are ignored
+    rebound.handle_frame(&[Action::KeyPress(6)]);
+    assert_eq!(*rebound.get_inputs().vertical, 0.0);
+    rebound.handle_frame(&[Action::KeyRelease(1), Action::AxisMotion(3, 1.0)]);
+    assert_eq!(*rebound.get_inputs().vertical, 0.0);
+    assert_eq!(*rebound.get_inputs().stick, 0.5);
+}
@@ -1,28 +1,53 @@
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A linear axis, usually with a value from -1 to 1.
-pub struct Axis(i8);
+pub struct Axis(f32);
 
 impl Axis {
     /// Get a new instance with the value set to zero
     pub fn zero() -> Self {
-        Axis(0)
+        Axis(0.0)
+    }
+
+    /// Get a new instance with the given value
+    pub fn new(value: f32) -> Self {
+        Axis(value)
     }
 
     /// Get the normalized value, ie always positive.
-    pub fn normalized(&self) -> i8 {
-        if self.0 < 0 {
-            -self.0
-        } else {
-            self.0
-        }
+    pub fn normalized(&self) -> f32 {
+        self.0.abs()
     }
 
-    pub fn modify(&mut self, val: i8) {
+    pub fn modify(&mut self, val: f32) {
         self.0 += val
     }
+
+    /// Limit the value to between `min` and `max`, inclusive.
+    pub fn clamp(&mut self, min: f32, max: f32) {
+        self.0 = self.0.clamp(min, max)
+    }
+
+    /// Set the value back to zero.
+    pub fn reset(&mut self) {
+        self.0 = 0.0
+    }
+}
+
+/// Apply a radial deadzone to a pair of axes, such as a stick's x and y, and clamp their combined magnitude to 1.
+/// Anything within `deadzone` of the centre becomes zero, and the rest is rescaled so the output still goes smoothly from 0 to 1.
+/// This keeps diagonals from being any faster than straight lines. `deadzone` should be at least 0 and less than 1.
+pub fn radial_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= deadzone {
+        return (0.0, 0.0);
+    }
+
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
 }
 
 impl Default for Axis {
@@ -32,7 +57,7 @@ impl Default for Axis {
 }
 
 impl Deref for Axis {
-    type Target = i8;
+    type Target = f32;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
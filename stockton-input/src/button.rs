@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A boolean input, with additional tracking for if it just changed state.
 pub struct Button {
     /// How many of the mapped inputs are currently pressed.
@@ -35,10 +36,11 @@ impl Button {
     }
 
     pub fn modify_inputs(&mut self, add: bool) {
+        // Releases can outnumber presses if the button was set directly while keys were held
         self.inputs_down = if add {
-            self.inputs_down + 1
+            self.inputs_down.saturating_add(1)
         } else {
-            self.inputs_down - 1
+            self.inputs_down.saturating_sub(1)
         };
 
         if self.inputs_down == 1 || self.inputs_down == 0 {
@@ -46,6 +48,25 @@ impl Button {
         }
     }
 
+    /// Release the button, regardless of how many mapped inputs are currently pressed.
+    /// If the button was down, it will be marked as hot.
+    pub fn reset(&mut self) {
+        if self.is_down() {
+            self.inputs_down = 0;
+            self.is_hot = true;
+        }
+    }
+
+    /// Press or release the button directly.
+    /// Pressing a button that's already down keeps track of the mapped inputs holding it, otherwise it's pressed as if exactly one was.
+    /// If this changes whether the button is down, it will be marked as hot.
+    pub fn set_down(&mut self, down: bool) {
+        if self.is_down() != down {
+            self.is_hot = true;
+            self.inputs_down = down as u8;
+        }
+    }
+
     pub fn set_not_hot(&mut self) {
         self.is_hot = false;
     }
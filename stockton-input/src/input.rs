@@ -0,0 +1,63 @@
+use crate::{Axis, Button};
+use std::fmt::Debug;
+
+/// The kind of an [`Input`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputKind {
+    Button,
+    Axis,
+}
+
+/// Either an [`Axis`] or a [`Button`], so that every input of a manager can be looked at together, for example to show their current state.
+/// This is sealed, so can't be implemented outside of this crate.
+pub trait Input: private::Sealed + Debug {
+    /// Which kind of input this is.
+    fn kind(&self) -> InputKind;
+
+    /// Set the input back to its resting state.
+    fn reset(&mut self);
+
+    /// Get this input as an axis, if it is one.
+    fn as_axis(&self) -> Option<&Axis> {
+        None
+    }
+
+    /// Get this input as a button, if it is one.
+    fn as_button(&self) -> Option<&Button> {
+        None
+    }
+}
+
+impl Input for Axis {
+    fn kind(&self) -> InputKind {
+        InputKind::Axis
+    }
+
+    fn reset(&mut self) {
+        Axis::reset(self)
+    }
+
+    fn as_axis(&self) -> Option<&Axis> {
+        Some(self)
+    }
+}
+
+impl Input for Button {
+    fn kind(&self) -> InputKind {
+        InputKind::Button
+    }
+
+    fn reset(&mut self) {
+        Button::reset(self)
+    }
+
+    fn as_button(&self) -> Option<&Button> {
+        Some(self)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::Axis {}
+    impl Sealed for super::Button {}
+}
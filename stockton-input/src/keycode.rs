@@ -0,0 +1,378 @@
+//! Keycodes for common keys, so they don't need to be written as raw numbers.
+//!
+//! Keycodes in stockton are scancodes from the standard PC layout (scancode set 1), which is what winit's `KeyboardInput` gives on Windows and Linux.
+//! On macOS, winit gives the Mac's own virtual keycode instead, so use [`position_keycode`] rather than the scancode directly,
+//! which converts those to the matching key in this table.
+//! Scancodes refer to the position of a key, not what's printed on it, so on an AZERTY keyboard the key for `Key::W` is labelled Z.
+//! This is usually what you want for movement, but not for keys with a meaning, such as `I` for inventory.
+//!
+//! To bind keys by what's printed on them instead, use [`layout_keycode`] when turning winit events into actions.
+//! This uses winit's `VirtualKeyCode` where it maps to a [`Key`], and gives the keycode of that key on a US QWERTY keyboard,
+//! so schemas written with [`Key`] work on any layout.
+//!
+//! Only keys found on both PC and Mac keyboards are included.
+
+use winit::event::{KeyboardInput, VirtualKeyCode};
+
+/// A key on the main block of the keyboard, or a function key.
+/// This can be used anywhere a keycode is needed with `Key::W as u32` or `u32::from(Key::W)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum Key {
+    Escape = 1,
+    Num1 = 2,
+    Num2 = 3,
+    Num3 = 4,
+    Num4 = 5,
+    Num5 = 6,
+    Num6 = 7,
+    Num7 = 8,
+    Num8 = 9,
+    Num9 = 10,
+    Num0 = 11,
+    Minus = 12,
+    Equals = 13,
+    Backspace = 14,
+    Tab = 15,
+    Q = 16,
+    W = 17,
+    E = 18,
+    R = 19,
+    T = 20,
+    Y = 21,
+    U = 22,
+    I = 23,
+    O = 24,
+    P = 25,
+    LeftBracket = 26,
+    RightBracket = 27,
+    Enter = 28,
+    LeftControl = 29,
+    A = 30,
+    S = 31,
+    D = 32,
+    F = 33,
+    G = 34,
+    H = 35,
+    J = 36,
+    K = 37,
+    L = 38,
+    Semicolon = 39,
+    Apostrophe = 40,
+    Grave = 41,
+    LeftShift = 42,
+    Backslash = 43,
+    Z = 44,
+    X = 45,
+    C = 46,
+    V = 47,
+    B = 48,
+    N = 49,
+    M = 50,
+    Comma = 51,
+    Period = 52,
+    Slash = 53,
+    RightShift = 54,
+    LeftAlt = 56,
+    Space = 57,
+    CapsLock = 58,
+    F1 = 59,
+    F2 = 60,
+    F3 = 61,
+    F4 = 62,
+    F5 = 63,
+    F6 = 64,
+    F7 = 65,
+    F8 = 66,
+    F9 = 67,
+    F10 = 68,
+}
+
+impl From<Key> for u32 {
+    fn from(key: Key) -> u32 {
+        key as u32
+    }
+}
+
+impl Key {
+    /// Get the key with the given keycode, if it's one of the keys in this table.
+    pub fn from_keycode(keycode: u32) -> Option<Key> {
+        Some(match keycode {
+            1 => Key::Escape,
+            2 => Key::Num1,
+            3 => Key::Num2,
+            4 => Key::Num3,
+            5 => Key::Num4,
+            6 => Key::Num5,
+            7 => Key::Num6,
+            8 => Key::Num7,
+            9 => Key::Num8,
+            10 => Key::Num9,
+            11 => Key::Num0,
+            12 => Key::Minus,
+            13 => Key::Equals,
+            14 => Key::Backspace,
+            15 => Key::Tab,
+            16 => Key::Q,
+            17 => Key::W,
+            18 => Key::E,
+            19 => Key::R,
+            20 => Key::T,
+            21 => Key::Y,
+            22 => Key::U,
+            23 => Key::I,
+            24 => Key::O,
+            25 => Key::P,
+            26 => Key::LeftBracket,
+            27 => Key::RightBracket,
+            28 => Key::Enter,
+            29 => Key::LeftControl,
+            30 => Key::A,
+            31 => Key::S,
+            32 => Key::D,
+            33 => Key::F,
+            34 => Key::G,
+            35 => Key::H,
+            36 => Key::J,
+            37 => Key::K,
+            38 => Key::L,
+            39 => Key::Semicolon,
+            40 => Key::Apostrophe,
+            41 => Key::Grave,
+            42 => Key::LeftShift,
+            43 => Key::Backslash,
+            44 => Key::Z,
+            45 => Key::X,
+            46 => Key::C,
+            47 => Key::V,
+            48 => Key::B,
+            49 => Key::N,
+            50 => Key::M,
+            51 => Key::Comma,
+            52 => Key::Period,
+            53 => Key::Slash,
+            54 => Key::RightShift,
+            56 => Key::LeftAlt,
+            57 => Key::Space,
+            58 => Key::CapsLock,
+            59 => Key::F1,
+            60 => Key::F2,
+            61 => Key::F3,
+            62 => Key::F4,
+            63 => Key::F5,
+            64 => Key::F6,
+            65 => Key::F7,
+            66 => Key::F8,
+            67 => Key::F9,
+            68 => Key::F10,
+            _ => return None,
+        })
+    }
+
+    /// Get the key in the same position as the given Mac virtual keycode, which is what winit gives as the scancode on macOS.
+    pub fn from_mac_keycode(keycode: u32) -> Option<Key> {
+        Some(match keycode {
+            0x00 => Key::A,
+            0x01 => Key::S,
+            0x02 => Key::D,
+            0x03 => Key::F,
+            0x04 => Key::H,
+            0x05 => Key::G,
+            0x06 => Key::Z,
+            0x07 => Key::X,
+            0x08 => Key::C,
+            0x09 => Key::V,
+            0x0B => Key::B,
+            0x0C => Key::Q,
+            0x0D => Key::W,
+            0x0E => Key::E,
+            0x0F => Key::R,
+            0x10 => Key::Y,
+            0x11 => Key::T,
+            0x12 => Key::Num1,
+            0x13 => Key::Num2,
+            0x14 => Key::Num3,
+            0x15 => Key::Num4,
+            0x16 => Key::Num6,
+            0x17 => Key::Num5,
+            0x18 => Key::Equals,
+            0x19 => Key::Num9,
+            0x1A => Key::Num7,
+            0x1B => Key::Minus,
+            0x1C => Key::Num8,
+            0x1D => Key::Num0,
+            0x1E => Key::RightBracket,
+            0x1F => Key::O,
+            0x20 => Key::U,
+            0x21 => Key::LeftBracket,
+            0x22 => Key::I,
+            0x23 => Key::P,
+            0x24 => Key::Enter,
+            0x25 => Key::L,
+            0x26 => Key::J,
+            0x27 => Key::Apostrophe,
+            0x28 => Key::K,
+            0x29 => Key::Semicolon,
+            0x2A => Key::Backslash,
+            0x2B => Key::Comma,
+            0x2C => Key::Slash,
+            0x2D => Key::N,
+            0x2E => Key::M,
+            0x2F => Key::Period,
+            0x30 => Key::Tab,
+            0x31 => Key::Space,
+            0x32 => Key::Grave,
+            0x33 => Key::Backspace,
+            0x35 => Key::Escape,
+            0x38 => Key::LeftShift,
+            0x39 => Key::CapsLock,
+            0x3A => Key::LeftAlt,
+            0x3B => Key::LeftControl,
+            0x3C => Key::RightShift,
+            0x60 => Key::F5,
+            0x61 => Key::F6,
+            0x62 => Key::F7,
+            0x63 => Key::F3,
+            0x64 => Key::F8,
+            0x65 => Key::F9,
+            0x6D => Key::F10,
+            0x76 => Key::F4,
+            0x78 => Key::F2,
+            0x7A => Key::F1,
+            _ => return None,
+        })
+    }
+
+    /// Get the key with the given label, if it's one of the keys in this table.
+    pub fn from_virtual_keycode(code: VirtualKeyCode) -> Option<Key> {
+        use VirtualKeyCode as V;
+        Some(match code {
+            V::Escape => Key::Escape,
+            V::Key1 => Key::Num1,
+            V::Key2 => Key::Num2,
+            V::Key3 => Key::Num3,
+            V::Key4 => Key::Num4,
+            V::Key5 => Key::Num5,
+            V::Key6 => Key::Num6,
+            V::Key7 => Key::Num7,
+            V::Key8 => Key::Num8,
+            V::Key9 => Key::Num9,
+            V::Key0 => Key::Num0,
+            V::Minus => Key::Minus,
+            V::Equals => Key::Equals,
+            V::Back => Key::Backspace,
+            V::Tab => Key::Tab,
+            V::Q => Key::Q,
+            V::W => Key::W,
+            V::E => Key::E,
+            V::R => Key::R,
+            V::T => Key::T,
+            V::Y => Key::Y,
+            V::U => Key::U,
+            V::I => Key::I,
+            V::O => Key::O,
+            V::P => Key::P,
+            V::LBracket => Key::LeftBracket,
+            V::RBracket => Key::RightBracket,
+            V::Return => Key::Enter,
+            V::LControl => Key::LeftControl,
+            V::A => Key::A,
+            V::S => Key::S,
+            V::D => Key::D,
+            V::F => Key::F,
+            V::G => Key::G,
+            V::H => Key::H,
+            V::J => Key::J,
+            V::K => Key::K,
+            V::L => Key::L,
+            V::Semicolon => Key::Semicolon,
+            V::Apostrophe => Key::Apostrophe,
+            V::Grave => Key::Grave,
+            V::LShift => Key::LeftShift,
+            V::Backslash => Key::Backslash,
+            V::Z => Key::Z,
+            V::X => Key::X,
+            V::C => Key::C,
+            V::V => Key::V,
+            V::B => Key::B,
+            V::N => Key::N,
+            V::M => Key::M,
+            V::Comma => Key::Comma,
+            V::Period => Key::Period,
+            V::Slash => Key::Slash,
+            V::RShift => Key::RightShift,
+            V::LAlt => Key::LeftAlt,
+            V::Space => Key::Space,
+            V::Capital => Key::CapsLock,
+            V::F1 => Key::F1,
+            V::F2 => Key::F2,
+            V::F3 => Key::F3,
+            V::F4 => Key::F4,
+            V::F5 => Key::F5,
+            V::F6 => Key::F6,
+            V::F7 => Key::F7,
+            V::F8 => Key::F8,
+            V::F9 => Key::F9,
+            V::F10 => Key::F10,
+            _ => return None,
+        })
+    }
+}
+
+/// Added to Mac virtual keycodes that aren't in the [`Key`] table, so they can't be mistaken for a key that is.
+pub const MAC_KEYCODE_OFFSET: u32 = 0x1000;
+
+/// Get the keycode to use for the given keyboard input, going by where the key is rather than what's printed on it.
+/// On macOS, the Mac virtual keycode winit gives is converted with [`Key::from_mac_keycode`], and keys not in the table are offset by [`MAC_KEYCODE_OFFSET`].
+/// Everywhere else, this is just the scancode.
+pub fn position_keycode(input: &KeyboardInput) -> u32 {
+    if cfg!(target_os = "macos") {
+        Key::from_mac_keycode(input.scancode)
+            .map(u32::from)
+            .unwrap_or(input.scancode + MAC_KEYCODE_OFFSET)
+    } else {
+        input.scancode
+    }
+}
+
+/// Get the keycode to use for the given keyboard input, going by what's printed on the key rather than where it is.
+/// If the key's label is in the [`Key`] table, this is the keycode for that key. Otherwise, it falls back to [`position_keycode`].
+///
+/// Use this or [`position_keycode`] consistently, since the same physical key can give different keycodes from each.
+pub fn layout_keycode(input: &KeyboardInput) -> u32 {
+    input
+        .virtual_keycode
+        .and_then(Key::from_virtual_keycode)
+        .map(u32::from)
+        .unwrap_or_else(|| position_keycode(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_keycodes_map_to_distinct_keys() {
+        let keys: Vec<Key> = (0..0x80).filter_map(Key::from_mac_keycode).collect();
+        let mut deduped = keys.clone();
+        deduped.sort();
+        deduped.dedup();
+
+        assert_eq!(keys.len(), deduped.len());
+        assert_eq!(Key::from_mac_keycode(0x0D), Some(Key::W));
+        assert_eq!(Key::from_mac_keycode(0x35), Some(Key::Escape));
+    }
+
+    #[test]
+    fn every_key_has_a_mac_keycode() {
+        for keycode in 0..128 {
+            if let Some(key) = Key::from_keycode(keycode) {
+                assert!(
+                    (0..0x80).any(|mac| Key::from_mac_keycode(mac) == Some(key)),
+                    "{:?} has no Mac keycode",
+                    key
+                );
+            }
+        }
+    }
+}
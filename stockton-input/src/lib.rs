@@ -1,9 +1,13 @@
 pub mod axis;
 pub mod button;
+pub mod input;
+pub mod keycode;
 pub mod manager;
 pub mod mouse;
 
-pub use axis::Axis;
+pub use axis::{radial_deadzone, Axis};
 pub use button::Button;
+pub use input::{Input, InputKind};
+pub use keycode::{layout_keycode, position_keycode, Key, MAC_KEYCODE_OFFSET};
 pub use manager::*;
 pub use mouse::Mouse;
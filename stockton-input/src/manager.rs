@@ -1,3 +1,6 @@
+use crate::Key;
+use std::collections::BTreeMap;
+
 /// A thing that pressing a button can do to an input.
 #[derive(Debug, Clone, Copy)]
 pub enum InputMutation {
@@ -15,7 +18,8 @@ pub enum MouseButton {
 }
 
 impl MouseButton {
-    fn keycode(&self) -> u32 {
+    /// Get the keycode used for this button. These count down from `u32::MAX`, so they won't collide with keyboard scancodes.
+    pub fn keycode(&self) -> u32 {
         u32::MAX
             - match self {
                 MouseButton::Left => 0,
@@ -26,6 +30,18 @@ impl MouseButton {
     }
 }
 
+impl From<MouseButton> for u32 {
+    fn from(button: MouseButton) -> u32 {
+        button.keycode()
+    }
+}
+
+/// The keycode used for scrolling, which is just below the range used by mouse buttons.
+pub const SCROLL_KEYCODE: u32 = u32::MAX - 256;
+
+/// How far an analog input needs to move before it counts as pressed, when bound to a button.
+pub const ANALOG_PRESS_THRESHOLD: f32 = 0.5;
+
 /// A key being pressed or released
 #[derive(Debug, Clone, Copy)]
 pub enum Action {
@@ -33,6 +49,16 @@ pub enum Action {
     KeyRelease(u32),
     MousePress(MouseButton),
     MouseRelease(MouseButton),
+
+    /// An analog input (such as a gamepad stick) moved to the given value, usually from -1 to 1.
+    /// When bound to an axis, the axis follows the value. When bound to a button,
+    /// the button is down while the value is at least [`ANALOG_PRESS_THRESHOLD`] away from zero.
+    AxisMotion(u32, f32),
+
+    /// The mouse wheel scrolled by the given number of lines, with positive values being up.
+    /// This uses [`SCROLL_KEYCODE`]. When bound to an axis, the axis changes by this amount for one frame.
+    /// When bound to a button, the button is pressed for one frame.
+    Scroll(f32),
 }
 
 impl Action {
@@ -42,21 +68,90 @@ impl Action {
             Action::KeyRelease(x) => *x,
             Action::MousePress(x) => x.keycode(),
             Action::MouseRelease(x) => x.keycode(),
+            Action::AxisMotion(x, _) => *x,
+            Action::Scroll(_) => SCROLL_KEYCODE,
+        }
+    }
+
+    /// Get the key this action is for, if it's a key press or release for a key in the [`Key`] table.
+    pub fn key(&self) -> Option<Key> {
+        match self {
+            Action::KeyPress(x) | Action::KeyRelease(x) => Key::from_keycode(*x),
+            _ => None,
         }
     }
+
     pub fn is_down(&self) -> bool {
         match self {
             Action::KeyPress(_) => true,
             Action::MousePress(_) => true,
             Action::KeyRelease(_) => false,
             Action::MouseRelease(_) => false,
+            Action::AxisMotion(_, x) => x.abs() >= ANALOG_PRESS_THRESHOLD,
+            Action::Scroll(x) => *x != 0.0,
         }
     }
 }
 
+/// Builds an action schema one binding at a time, then turns it into something else, usually an input manager.
+/// Generated input managers give one of these from their `builder` method.
+pub struct ActionSchemaBuilder<F, M> {
+    actions: BTreeMap<u32, (F, InputMutation)>,
+    finish: fn(BTreeMap<u32, (F, InputMutation)>) -> M,
+}
+
+impl<F, M> ActionSchemaBuilder<F, M> {
+    /// Create a builder with no bindings, which gives the finished schema to `finish` when it's built.
+    pub fn new(finish: fn(BTreeMap<u32, (F, InputMutation)>) -> M) -> Self {
+        ActionSchemaBuilder {
+            actions: BTreeMap::new(),
+            finish,
+        }
+    }
+
+    /// Bind a key to the given field. `key` can be a [`crate::Key`] or a raw keycode.
+    /// Binding a key that's already bound replaces its old binding.
+    pub fn bind(mut self, key: impl Into<u32>, field: F, mutation: InputMutation) -> Self {
+        self.actions.insert(key.into(), (field, mutation));
+        self
+    }
+
+    /// Finish the schema, and create whatever it's for.
+    pub fn build(self) -> M {
+        (self.finish)(self.actions)
+    }
+}
+
+/// The value of a single button or axis, used to read and write inputs without knowing the concrete inputs type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputValue {
+    Axis(f32),
+
+    /// Whether the button is down
+    Button(bool),
+}
+
 pub trait InputManager {
     type Inputs;
 
+    /// The enum with a value for each button and axis, eg `MovementInputsFields`.
+    type Fields: Copy;
+
     fn handle_frame<'a, X: IntoIterator<Item = &'a Action>>(&mut self, actions: X);
     fn get_inputs(&self) -> &Self::Inputs;
+
+    /// Get the current value of the given field.
+    fn value_of(&self, field: Self::Fields) -> InputValue;
+
+    /// Set the given field straight to a value, for example when replaying recorded inputs.
+    /// Axes skip any bounds or pairing. Releasing a button forgets any bound keys being held, so their releases are ignored,
+    /// and pressing one that isn't down acts as if exactly one bound key is held.
+    /// Later actions carry on from the new value. Values of the wrong kind for the field are ignored.
+    fn set_value(&mut self, field: Self::Fields, value: InputValue);
+
+    /// Release all buttons and set every axis back to its default (zero if none), forgetting which keys are held down.
+    /// This should be called when the window loses focus (`WindowEvent::Focused(false)`),
+    /// since the release events for any held keys will never arrive. With winit, `stockton_contrib::window_events::reset_on_focus_lost` does this.
+    fn reset(&mut self);
 }
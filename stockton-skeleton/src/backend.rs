@@ -0,0 +1,33 @@
+//! The gfx-hal backend used for rendering, picked by Cargo feature.
+//!
+//! Everything else refers to the backend through this module, so only one backend is ever compiled in.
+//! The available backends are:
+//!
+//!  - `vulkan` (default): The only backend that's fully supported. Needs a Vulkan driver, which most CI machines don't have.
+//!  - `gl`: Runs on machines with only OpenGL (or GLES/WebGL), including software renderers like llvmpipe, so can be used for automated rendering tests.
+//!    gfx-hal's GL support is incomplete, so some features (eg imageless framebuffers, separate samplers, some formats) may not work, and performance is much worse.
+//!  - `empty`: A backend that doesn't do anything, so nothing can actually be rendered. This is only useful for checking that everything compiles without any graphics drivers.
+//!
+//! To use a backend other than Vulkan, turn off default features and enable it, eg `default-features = false, features = ["gl", "rendy-pools"]`.
+//! If more than one is enabled, the first in the list above is used.
+
+#[cfg(feature = "vulkan")]
+pub use gfx_backend_vulkan::{Backend, Instance};
+/// The name of the backend being used
+#[cfg(feature = "vulkan")]
+pub const NAME: &str = "vulkan";
+
+#[cfg(all(feature = "gl", not(feature = "vulkan")))]
+pub use gfx_backend_gl::{Backend, Instance};
+/// The name of the backend being used
+#[cfg(all(feature = "gl", not(feature = "vulkan")))]
+pub const NAME: &str = "gl";
+
+#[cfg(all(feature = "empty", not(any(feature = "vulkan", feature = "gl"))))]
+pub use gfx_backend_empty::{Backend, Instance};
+/// The name of the backend being used
+#[cfg(all(feature = "empty", not(any(feature = "vulkan", feature = "gl"))))]
+pub const NAME: &str = "empty";
+
+#[cfg(not(any(feature = "vulkan", feature = "gl", feature = "empty")))]
+compile_error!("No backend selected. Enable one of the `vulkan`, `gl` or `empty` features.");
@@ -3,12 +3,13 @@
 use super::staged::StagedBuffer;
 use crate::{
     context::RenderingContext,
+    draw_passes::FrameContext,
     mem::{MappableBlock, MemoryPool},
 };
 
 use anyhow::{Context, Result};
-use hal::buffer::Usage;
-use std::mem::ManuallyDrop;
+use hal::{buffer::Usage, IndexType};
+use std::{convert::TryInto, mem::ManuallyDrop};
 
 /// Initial size of vertex buffer. TODO: Way of overriding this
 pub const INITIAL_VERT_SIZE: u64 = 3 * 3000;
@@ -16,32 +17,91 @@ pub const INITIAL_VERT_SIZE: u64 = 3 * 3000;
 /// Initial size of index buffer. TODO: Way of overriding this
 pub const INITIAL_INDEX_SIZE: u64 = 3000;
 
-/// A vertex and index buffer set for drawing
-pub struct DrawBuffers<'a, T: Sized, P: MemoryPool, SP: MemoryPool> {
+/// A type that can be used for indices in an index buffer.
+/// This is implemented for `u16` and `u32`.
+pub trait DrawIndex: private::Sealed + Copy + Sized {
+    /// The index type to use when binding an index buffer of this type.
+    const INDEX_TYPE: IndexType;
+
+    /// The most vertices that indices of this type can refer to.
+    /// Meshes with more vertices than this need a wider index type.
+    const MAX_VERTICES: usize;
+
+    /// Convert a vertex index to this type, or None if it's too big.
+    fn from_usize(idx: usize) -> Option<Self>;
+}
+
+impl DrawIndex for u16 {
+    const INDEX_TYPE: IndexType = IndexType::U16;
+    const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+    fn from_usize(idx: usize) -> Option<Self> {
+        idx.try_into().ok()
+    }
+}
+
+impl DrawIndex for u32 {
+    const INDEX_TYPE: IndexType = IndexType::U32;
+    const MAX_VERTICES: usize = (u32::MAX as usize).saturating_add(1);
+
+    fn from_usize(idx: usize) -> Option<Self> {
+        idx.try_into().ok()
+    }
+}
+
+/// Get the index type needed to draw a mesh with the given number of vertices.
+/// Use this to decide whether to create `DrawBuffers` with `u16` or `u32` indices.
+pub fn index_type_for(vertex_count: usize) -> IndexType {
+    if vertex_count <= u16::MAX_VERTICES {
+        IndexType::U16
+    } else {
+        IndexType::U32
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+}
+
+/// A vertex and index buffer pair, making up one of the sets held by [`DrawBuffers`].
+pub struct DrawBufferSet<'a, T: Sized, P: MemoryPool, SP: MemoryPool, I: DrawIndex = u16> {
     pub vertex_buffer: ManuallyDrop<StagedBuffer<'a, T, P, SP>>,
-    pub index_buffer: ManuallyDrop<StagedBuffer<'a, (u16, u16, u16), P, SP>>,
+    pub index_buffer: ManuallyDrop<StagedBuffer<'a, (I, I, I), P, SP>>,
 }
 
-impl<'a, T, P, SP> DrawBuffers<'a, T, P, SP>
+impl<'a, T, P, SP, I> DrawBufferSet<'a, T, P, SP, I>
 where
     P: MemoryPool,
     SP: MemoryPool,
     SP::Block: MappableBlock,
+    I: DrawIndex,
 {
-    /// Create a new set of drawbuffers given a render context.
-    /// This will allocate memory from `P` and `SP`, and currently has a fixed size (WIP).
-    pub fn from_context(context: &mut RenderingContext) -> Result<Self> {
+    fn from_context(context: &mut RenderingContext) -> Result<Self> {
         let vert = StagedBuffer::from_context(context, Usage::VERTEX, INITIAL_VERT_SIZE)
             .context("Error creating vertex buffer")?;
-        let index = StagedBuffer::from_context(context, Usage::INDEX, INITIAL_INDEX_SIZE)
-            .context("Error creating index buffer")?;
+        let index = match StagedBuffer::from_context(context, Usage::INDEX, INITIAL_INDEX_SIZE)
+            .context("Error creating index buffer")
+        {
+            Ok(x) => x,
+            Err(e) => {
+                vert.deactivate(context);
+                return Err(e);
+            }
+        };
 
-        Ok(DrawBuffers {
+        Ok(DrawBufferSet {
             vertex_buffer: ManuallyDrop::new(vert),
             index_buffer: ManuallyDrop::new(index),
         })
     }
 
+    /// The index type that should be used when binding the index buffer.
+    pub fn index_type(&self) -> IndexType {
+        I::INDEX_TYPE
+    }
+
     /// Destroy all Vulkan objects. Should be called before dropping.
     pub fn deactivate(self, context: &mut RenderingContext) {
         unsafe {
@@ -52,3 +112,92 @@ where
         }
     }
 }
+
+/// Vertex and index buffers for drawing, with one set for each frame in flight.
+/// Use [`DrawBuffers::for_frame`] to get the set for the frame being drawn, so that writes for one frame don't change data the GPU may still be reading for another.
+/// `I` is the type used for indices, which should be `u32` if there may be more than 65536 vertices.
+pub struct DrawBuffers<'a, T: Sized, P: MemoryPool, SP: MemoryPool, I: DrawIndex = u16> {
+    sets: Vec<DrawBufferSet<'a, T, P, SP, I>>,
+}
+
+impl<'a, T, P, SP, I> DrawBuffers<'a, T, P, SP, I>
+where
+    P: MemoryPool,
+    SP: MemoryPool,
+    SP::Block: MappableBlock,
+    I: DrawIndex,
+{
+    /// Create a new set of drawbuffers given a render context, with one set for each frame in flight.
+    /// This will allocate memory from `P` and `SP`, and currently has a fixed size (WIP).
+    pub fn from_context(context: &mut RenderingContext) -> Result<Self> {
+        let count = context.frames_in_flight();
+        let mut sets = Vec::with_capacity(count);
+        for _ in 0..count {
+            match DrawBufferSet::from_context(context) {
+                Ok(x) => sets.push(x),
+                Err(e) => {
+                    for set in sets {
+                        set.deactivate(context);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(DrawBuffers { sets })
+    }
+
+    /// Get the set of buffers to use for the given frame.
+    /// Each set is only written to and committed for one of the frames in flight, so any changes need to be made again for every set.
+    pub fn for_frame(&mut self, frame: &FrameContext) -> &mut DrawBufferSet<'a, T, P, SP, I> {
+        let idx = frame.frame_index % self.sets.len();
+        &mut self.sets[idx]
+    }
+
+    /// The number of sets of buffers, which is the number of frames in flight when they were created.
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Check if there are no sets of buffers.
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// The index type that should be used when binding the index buffer.
+    pub fn index_type(&self) -> IndexType {
+        I::INDEX_TYPE
+    }
+
+    /// Destroy all Vulkan objects. Should be called before dropping.
+    pub fn deactivate(self, context: &mut RenderingContext) {
+        for set in self.sets {
+            set.deactivate(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u16_indices() {
+        assert_eq!(u16::from_usize(65535), Some(65535));
+        assert_eq!(u16::from_usize(65536), None);
+    }
+
+    #[test]
+    fn u32_indices() {
+        assert_eq!(u32::from_usize(65536), Some(65536));
+        assert_eq!(u32::from_usize(u32::MAX as usize), Some(u32::MAX));
+    }
+
+    #[test]
+    fn index_type_for_vertex_count() {
+        assert_eq!(index_type_for(0), IndexType::U16);
+        assert_eq!(index_type_for(65536), IndexType::U16);
+        assert_eq!(index_type_for(65537), IndexType::U32);
+        assert_eq!(index_type_for(70_000), IndexType::U32);
+    }
+}
@@ -19,20 +19,26 @@
 //! )
 //! # }
 /// ```
-use std::mem::ManuallyDrop;
+use std::{iter::once, mem::ManuallyDrop, ptr::copy_nonoverlapping};
 
+use super::staging::StagingBuffer;
 use crate::{
     context::RenderingContext,
-    error::LockPoisoned,
-    mem::{Block, MemoryPool},
+    error::{LockPoisoned, UsageError},
+    mem::{Block, MemoryPool, StagingPool},
     types::*,
     utils::get_pixel_size,
 };
 use anyhow::{Context, Result};
 use hal::{
+    command::BufferImageCopy,
     format::{Aspects, Format, Swizzle},
-    image::{SamplerDesc, SubresourceRange, Usage, ViewKind},
-    memory::SparseFlags,
+    image::{
+        Access, Extent, Layout, Offset, SamplerDesc, SubresourceLayers, SubresourceRange, Usage,
+        ViewKind,
+    },
+    memory::{Barrier, Dependencies, SparseFlags},
+    pso::PipelineStage,
 };
 
 pub const COLOR_RESOURCES: SubresourceRange = SubresourceRange {
@@ -263,6 +269,166 @@ impl<P: MemoryPool> SampledImage<P> {
         })
     }
 
+    /// Create an image and sampler that are ready to be bound, optionally filling the image with `data`.
+    /// `data` should be rows of pixels in `spec.format`, with no padding. If it's not given, the image's contents are undefined.
+    /// Either way, the image is left in `Layout::ShaderReadOnlyOptimal`. `TRANSFER_DST` and `SAMPLED` are added to the usage given in `spec`.
+    /// This submits to the draw queue and blocks until it's done, so it's best used while initialising.
+    /// P will usually be [`crate::mem::TexturesPool`].
+    pub fn from_spec(
+        context: &mut RenderingContext,
+        spec: &ImageSpec,
+        sampler_desc: &SamplerDesc,
+        data: Option<&[u8]>,
+    ) -> Result<Self> {
+        let spec = ImageSpec {
+            usage: spec.usage | Usage::TRANSFER_DST | Usage::SAMPLED,
+            ..spec.clone()
+        };
+        if let Some(data) = data {
+            let expected = (get_pixel_size(spec.format) * spec.width * spec.height) as usize;
+            if data.len() != expected {
+                return Err(UsageError::ImageDataSize {
+                    expected,
+                    actual: data.len(),
+                }
+                .into());
+            }
+        }
+
+        let img = Self::from_context(context, &spec, sampler_desc)?;
+        let staging = match data
+            .map(|data| Self::stage(context, &img, data))
+            .transpose()
+        {
+            Ok(s) => s,
+            Err(e) => {
+                img.deactivate_with_context(context);
+                return Err(e);
+            }
+        };
+
+        let result = img.upload(context, &spec, staging.as_ref());
+        if let Some(staging) = staging {
+            staging.deactivate_context(context);
+        }
+
+        match result {
+            Ok(()) => Ok(img),
+            Err(e) => {
+                img.deactivate_with_context(context);
+                Err(e)
+            }
+        }
+    }
+
+    /// Create a staging buffer holding `data`, padded to the image's row size.
+    fn stage(
+        context: &mut RenderingContext,
+        img: &Self,
+        data: &[u8],
+    ) -> Result<StagingBuffer<StagingPool>> {
+        let row_size = img.row_size() as usize;
+        let unpadded_row_size = data.len() / img.height() as usize;
+        let total_size = (row_size * img.height() as usize) as u64;
+
+        let mut staging = StagingBuffer::<StagingPool>::from_context(context, total_size)
+            .context("Error creating staging buffer")?;
+
+        let mut device = context.lock_device()?;
+        let written = staging
+            .map(&mut device, 0..total_size)
+            .context("Error mapping staging buffer")
+            .and_then(|ptr| {
+                for (y, row) in data.chunks_exact(unpadded_row_size).enumerate() {
+                    // Safety: The buffer has `row_size` bytes for each row, which is at least `unpadded_row_size`.
+                    unsafe { copy_nonoverlapping(row.as_ptr(), ptr.add(y * row_size), row.len()) };
+                }
+                staging.unmap(&mut device)
+            });
+        drop(device);
+
+        match written {
+            Ok(()) => Ok(staging),
+            Err(e) => {
+                staging.deactivate_context(context);
+                Err(e)
+            }
+        }
+    }
+
+    /// Copy from `staging` into the image if it's given, and move the image to `Layout::ShaderReadOnlyOptimal`, blocking until it's done.
+    fn upload(
+        &self,
+        context: &mut RenderingContext,
+        spec: &ImageSpec,
+        staging: Option<&StagingBuffer<StagingPool>>,
+    ) -> Result<()> {
+        context
+            .run_one_time_commands(|buf| unsafe {
+                match staging {
+                    Some(staging) => {
+                        buf.pipeline_barrier(
+                            PipelineStage::TOP_OF_PIPE..PipelineStage::TRANSFER,
+                            Dependencies::empty(),
+                            once(Barrier::Image {
+                                states: (Access::empty(), Layout::Undefined)
+                                    ..(Access::TRANSFER_WRITE, Layout::TransferDstOptimal),
+                                target: self.img(),
+                                families: None,
+                                range: spec.resources.clone(),
+                            }),
+                        );
+                        buf.copy_buffer_to_image(
+                            staging.buf(),
+                            self.img(),
+                            Layout::TransferDstOptimal,
+                            once(BufferImageCopy {
+                                buffer_offset: 0,
+                                buffer_width: self.row_size() / get_pixel_size(spec.format),
+                                buffer_height: self.height(),
+                                image_layers: SubresourceLayers {
+                                    aspects: spec.resources.aspects,
+                                    level: 0,
+                                    layers: 0..1,
+                                },
+                                image_offset: Offset { x: 0, y: 0, z: 0 },
+                                image_extent: Extent {
+                                    width: spec.width,
+                                    height: spec.height,
+                                    depth: 1,
+                                },
+                            }),
+                        );
+                        buf.pipeline_barrier(
+                            PipelineStage::TRANSFER..PipelineStage::FRAGMENT_SHADER,
+                            Dependencies::empty(),
+                            once(Barrier::Image {
+                                states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+                                    ..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+                                target: self.img(),
+                                families: None,
+                                range: spec.resources.clone(),
+                            }),
+                        );
+                    }
+                    None => {
+                        buf.pipeline_barrier(
+                            PipelineStage::TOP_OF_PIPE..PipelineStage::FRAGMENT_SHADER,
+                            Dependencies::empty(),
+                            once(Barrier::Image {
+                                states: (Access::empty(), Layout::Undefined)
+                                    ..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+                                target: self.img(),
+                                families: None,
+                                range: spec.resources.clone(),
+                            }),
+                        );
+                    }
+                }
+            })
+            .context("Error uploading image")
+    }
+
     /// Destroy all vulkan objects. Must be called before dropping.
     pub fn deactivate_with_context(self, context: &mut RenderingContext) {
         let mut device = context.lock_device().unwrap();
@@ -10,7 +10,7 @@ use crate::{
 use core::mem::{size_of, ManuallyDrop};
 use std::{
     convert::TryInto,
-    ops::{Index, IndexMut},
+    ops::{Index, IndexMut, Range},
 };
 
 use anyhow::{Context, Result};
@@ -18,6 +18,7 @@ use hal::{buffer::Usage, command::BufferCopy, memory::SparseFlags};
 
 /// A GPU buffer that is written to using a staging buffer. The staging buffer and the GPU buffers are the same size,
 /// so this isn't optimal in a lot of cases.
+/// Writes through `IndexMut` are tracked, so only the changed parts are copied when committing.
 pub struct StagedBuffer<'a, T: Sized, P: MemoryPool, SP: MemoryPool> {
     /// CPU-visible buffer
     staged_buffer: ManuallyDrop<BufferT>,
@@ -36,6 +37,9 @@ pub struct StagedBuffer<'a, T: Sized, P: MemoryPool, SP: MemoryPool> {
 
     /// The highest index in the buffer that's been written to.
     highest_used: usize,
+
+    /// Ranges of indices that have been written to since changes were last committed, in the order they were written.
+    dirty: Vec<Range<usize>>,
 }
 
 impl<'a, T, P, SP> StagedBuffer<'a, T, P, SP>
@@ -105,6 +109,7 @@ where
             memory: ManuallyDrop::new(memory),
             staged_mapped_memory,
             highest_used: 0,
+            dirty: Vec::new(),
         })
     }
 
@@ -140,15 +145,26 @@ where
     }
 
     /// Record the command(s) required to commit changes to this buffer to the given command buffer.
+    /// Only the parts written to since the last commit are copied, and nothing is recorded if there weren't any.
+    /// Those parts are no longer tracked once this returns, so if the command buffer is never submitted, call [`Self::mark_all_dirty`] so they're copied next time.
+    /// Draw passes can do this from [`crate::draw_passes::DrawPass::frame_cancelled`].
     pub fn record_commit_cmds(&mut self, buf: &mut CommandBufferT) -> Result<()> {
+        let ranges = self.take_dirty_ranges();
+        if ranges.is_empty() {
+            return Ok(());
+        }
+
         unsafe {
             buf.copy_buffer(
                 &self.staged_buffer,
                 &self.buffer,
-                std::iter::once(BufferCopy {
-                    src: 0,
-                    dst: 0,
-                    size: ((self.highest_used + 1) * size_of::<T>()) as u64,
+                ranges.into_iter().map(|range| {
+                    let offset = (range.start * size_of::<T>()) as u64;
+                    BufferCopy {
+                        src: offset,
+                        dst: offset,
+                        size: (range.len() * size_of::<T>()) as u64,
+                    }
                 }),
             );
         }
@@ -156,6 +172,28 @@ where
         Ok(())
     }
 
+    /// Mark the whole used part of the buffer as changed, so it's all copied on the next commit.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.clear();
+        self.dirty.push(0..self.highest_used + 1);
+    }
+
+    /// Get the ranges written to since the last commit, sorted and with overlapping or adjacent ranges merged, and start tracking again.
+    fn take_dirty_ranges(&mut self) -> Vec<Range<usize>> {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.sort_unstable_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(dirty.len());
+        for range in dirty {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        merged
+    }
+
     /// Get the highest byte in this buffer that's been written to (by the CPU)
     pub fn highest_used(&self) -> usize {
         self.highest_used
@@ -196,6 +234,14 @@ impl<'a, T: Sized, P: MemoryPool, SP: MemoryPool> IndexMut<usize> for StagedBuff
         if index > self.highest_used {
             self.highest_used = index;
         }
+
+        // Most writes are sequential, so try to extend the last range before adding a new one
+        match self.dirty.last_mut() {
+            Some(last) if last.contains(&index) => (),
+            Some(last) if last.end == index => last.end += 1,
+            _ => self.dirty.push(index..index + 1),
+        }
+
         &mut self.staged_mapped_memory[index]
     }
 }
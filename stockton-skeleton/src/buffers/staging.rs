@@ -12,7 +12,7 @@ use std::{mem::ManuallyDrop, ops::Range};
 use anyhow::{Context, Result};
 use hal::{buffer::Usage, memory::SparseFlags};
 
-/// A buffer that can be written to by the CPU. Usage will be `Usage::TRANSFER_SRC`.
+/// A buffer that can be written to by the CPU. Usage will be `Usage::TRANSFER_SRC`, unless it was created with [`StagingBuffer::readback_from_context`].
 pub struct StagingBuffer<P: MemoryPool> {
     buf: ManuallyDrop<BufferT>,
     mem: ManuallyDrop<P::Block>,
@@ -37,11 +37,29 @@ where
         Self::from_device_pool(&mut device, &mut mempool, size)
     }
 
+    /// Create a new buffer for reading data back from the GPU, from the given RenderingContext. `size` is in bytes.
+    /// Its usage will be `Usage::TRANSFER_DST`, so it can be copied into and then mapped.
+    pub fn readback_from_context(context: &mut RenderingContext, size: u64) -> Result<Self> {
+        context.ensure_memory_pool::<P>()?;
+
+        let mut device = context.lock_device()?;
+        let mut mempool = context
+            .existing_memory_pool()
+            .unwrap()
+            .write()
+            .map_err(|_| LockPoisoned::MemoryPool)?;
+
+        Self::with_usage(&mut device, &mut mempool, size, Usage::TRANSFER_DST)
+    }
+
     /// Create a new staging buffer from the given device and memory pool. `size` is in bytes.
     pub fn from_device_pool(device: &mut DeviceT, mempool: &mut P, size: u64) -> Result<Self> {
-        let mut buffer =
-            unsafe { device.create_buffer(size, Usage::TRANSFER_SRC, SparseFlags::empty()) }
-                .context("Error creating buffer")?;
+        Self::with_usage(device, mempool, size, Usage::TRANSFER_SRC)
+    }
+
+    fn with_usage(device: &mut DeviceT, mempool: &mut P, size: u64, usage: Usage) -> Result<Self> {
+        let mut buffer = unsafe { device.create_buffer(size, usage, SparseFlags::empty()) }
+            .context("Error creating buffer")?;
 
         let requirements = unsafe { device.get_buffer_requirements(&buffer) };
 
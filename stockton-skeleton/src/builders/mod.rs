@@ -1,7 +1,9 @@
 mod pipeline;
+mod push_constants;
 mod renderpass;
 mod shader;
 
 pub use pipeline::*;
+pub use push_constants::*;
 pub use renderpass::*;
 pub use shader::*;
@@ -1,17 +1,22 @@
-use super::{renderpass::RenderpassSpec, shader::ShaderDesc};
-use crate::{error::EnvironmentError, types::*, utils::get_pixel_size};
+use super::{push_constants::PushConstants, renderpass::MultiRenderpassSpec, shader::ShaderDesc};
+use crate::{
+    error::{EnvironmentError, UsageError},
+    types::*,
+    utils::get_pixel_size,
+};
 
-use std::{mem::ManuallyDrop, ops::Range};
+use std::{iter::once, mem::ManuallyDrop, ops::Range};
 
 use anyhow::{Context, Result};
 use hal::{
     format::Format,
     pso::{
-        AttributeDesc, BakedStates, BasePipeline, BlendDesc, BufferIndex, DepthStencilDesc,
-        ElemStride, Element, GraphicsPipelineDesc, InputAssemblerDesc, PipelineCreationFlags,
-        PrimitiveAssemblerDesc, Rasterizer, Rect, ShaderStageFlags, VertexBufferDesc,
-        VertexInputRate, Viewport,
+        AttributeDesc, BakedStates, BasePipeline, BlendDesc, BlendOp, BlendState, BufferIndex,
+        ColorBlendDesc, ColorMask, DepthStencilDesc, ElemStride, Element, Factor,
+        GraphicsPipelineDesc, InputAssemblerDesc, PipelineCreationFlags, PrimitiveAssemblerDesc,
+        Rasterizer, Rect, ShaderStageFlags, VertexBufferDesc, VertexInputRate, Viewport,
     },
+    Limits,
 };
 use shaderc::Compiler;
 
@@ -45,6 +50,21 @@ impl VertexBufferSpec {
     }
 }
 
+/// A type that can be stored in a vertex buffer, and knows the format of each of its attributes.
+/// This allows draw passes to be generic over the vertex type they use.
+pub trait VertexLayout: Copy + Sized {
+    /// The format of each attribute, in the order they're stored in memory.
+    fn attributes() -> Vec<Format>;
+
+    /// Get the spec for a vertex buffer of this type.
+    fn buffer_spec(rate: VertexInputRate) -> VertexBufferSpec {
+        VertexBufferSpec {
+            attributes: Self::attributes(),
+            rate,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct VertexPrimitiveAssemblerSpec {
     buffers: Vec<VertexBufferDesc>,
@@ -81,16 +101,68 @@ impl VertexPrimitiveAssemblerSpec {
     }
 }
 
+/// Common blending setups for a pipeline with a single colour attachment.
+/// These can be passed straight to [`PipelineSpecBuilder::blender`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// No blending. Fragments overwrite whatever is already in the attachment.
+    Opaque,
+
+    /// Standard transparency, ie `src * src_alpha + dst * (1 - src_alpha)`.
+    AlphaBlend,
+
+    /// Fragments are added onto whatever is already in the attachment. Useful for glows and particles.
+    Additive,
+}
+
+impl Default for BlendMode {
+    /// Alpha blending is used by default, so that transparent textures render as expected.
+    fn default() -> Self {
+        BlendMode::AlphaBlend
+    }
+}
+
+impl From<BlendMode> for BlendDesc {
+    fn from(mode: BlendMode) -> Self {
+        let blend = match mode {
+            BlendMode::Opaque => None,
+            BlendMode::AlphaBlend => Some(BlendState {
+                color: BlendOp::ALPHA,
+                alpha: BlendOp::ALPHA,
+            }),
+            BlendMode::Additive => Some(BlendState {
+                color: BlendOp::Add {
+                    src: Factor::SrcAlpha,
+                    dst: Factor::One,
+                },
+                alpha: BlendOp::ADD,
+            }),
+        };
+
+        BlendDesc {
+            logic_op: None,
+            targets: vec![ColorBlendDesc {
+                mask: ColorMask::ALL,
+                blend,
+            }],
+        }
+    }
+}
+
 #[derive(Builder, Debug)]
 #[builder(public)]
 pub struct PipelineSpec {
     rasterizer: Rasterizer,
     depth_stencil: DepthStencilDesc,
+
+    /// How to blend fragments with the colour attachment.
+    /// Accepts either a raw [`BlendDesc`] or a [`BlendMode`], and defaults to [`BlendMode::default`].
+    #[builder(setter(into), default = "BlendMode::default().into()")]
     blender: BlendDesc,
     primitive_assembler: VertexPrimitiveAssemblerSpec,
 
     shader_vertex: ShaderDesc,
-    #[builder(setter(strip_option))]
+    #[builder(setter(strip_option), default)]
     shader_fragment: Option<ShaderDesc>,
     #[builder(setter(strip_option), default)]
     shader_geom: Option<ShaderDesc>,
@@ -105,22 +177,55 @@ pub struct PipelineSpec {
     #[builder(default = "false")]
     dynamic_scissor: bool,
 
-    renderpass: RenderpassSpec,
+    /// The renderpass the pipeline will be used in.
+    /// Accepts either a [`super::RenderpassSpec`] or a [`MultiRenderpassSpec`].
+    #[builder(setter(into))]
+    renderpass: MultiRenderpassSpec,
+
+    /// The index of the subpass the pipeline will be used in. Defaults to the first one.
+    #[builder(default = "0")]
+    subpass: u8,
+}
+
+impl PipelineSpecBuilder {
+    /// Declare a push constant range big enough for `T`, starting at `offset` bytes.
+    pub fn push_constants_of<T: PushConstants>(
+        &mut self,
+        stages: ShaderStageFlags,
+        offset: u32,
+    ) -> &mut Self {
+        self.push_constants
+            .get_or_insert_with(Vec::new)
+            .push((stages, T::range(offset)));
+        self
+    }
 }
 
 impl PipelineSpec {
+    /// Build the pipeline and its renderpass.
+    /// `limits` should be from [`crate::context::RenderingContext::physical_device_properties`], and is used to check the spec is supported before building it.
     pub fn build<'b, T: Iterator<Item = &'b DescriptorSetLayoutT> + std::fmt::Debug>(
         self,
         device: &mut DeviceT,
         extent: hal::image::Extent,
+        limits: &Limits,
         set_layouts: T,
     ) -> Result<CompletePipeline> {
+        self.validate_push_constants(limits)?;
+        if self.subpass as usize >= self.renderpass.subpasses.len() {
+            return Err(UsageError::SubpassOutOfRange {
+                index: self.subpass,
+                count: self.renderpass.subpasses.len(),
+            }
+            .into());
+        }
+
         // Renderpass
         let renderpass = self.renderpass.build_renderpass(device)?;
 
         // Subpass
         let subpass = hal::pass::Subpass {
-            index: 0,
+            index: self.subpass,
             main_pass: &renderpass,
         };
 
@@ -167,7 +272,10 @@ impl PipelineSpec {
 
         // Pipeline layout
         let layout = unsafe {
-            device.create_pipeline_layout(set_layouts.into_iter(), self.push_constants.into_iter())
+            device.create_pipeline_layout(
+                set_layouts.into_iter(),
+                self.push_constants.iter().cloned(),
+            )
         }
         .context("Error creating pipeline layout")?;
 
@@ -227,8 +335,31 @@ impl PipelineSpec {
             gm_module,
             ts_module,
             render_area: extent.rect(),
+            push_constants: self.push_constants,
+            dynamic_viewport: self.dynamic_viewport,
+            dynamic_scissor: self.dynamic_scissor,
         })
     }
+
+    /// Check the push constant ranges fit in the device's limit, since exceeding it gives an unhelpful error when creating the pipeline.
+    fn validate_push_constants(&self, limits: &Limits) -> Result<()> {
+        let max = limits.max_push_constants_size;
+        match self
+            .push_constants
+            .iter()
+            .max_by_key(|(_, range)| range.end)
+        {
+            Some((stages, range)) if range.end as usize > max => {
+                Err(UsageError::PushConstantsTooLarge {
+                    stages: *stages,
+                    range: range.clone(),
+                    max,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 pub struct CompletePipeline {
@@ -250,9 +381,65 @@ pub struct CompletePipeline {
     pub ts_module: Option<(ShaderModuleT, ShaderModuleT)>,
 
     pub render_area: Rect,
+
+    /// The push constant ranges declared in the pipeline layout
+    pub push_constants: Vec<(ShaderStageFlags, Range<u32>)>,
+
+    /// Whether the viewport and scissor were left dynamic, and so need to be set with [`Self::set_viewport`]
+    pub dynamic_viewport: bool,
+    pub dynamic_scissor: bool,
 }
 
 impl CompletePipeline {
+    /// Record commands to set the viewport and scissor to the given rect, if they're dynamic.
+    /// Usually `rect` should be [`crate::draw_passes::FrameContext::viewport`].
+    pub fn set_viewport(&self, cmd_buffer: &mut CommandBufferT, rect: Rect) {
+        unsafe {
+            if self.dynamic_viewport {
+                cmd_buffer.set_viewports(
+                    0,
+                    once(Viewport {
+                        rect,
+                        depth: (0.0..1.0),
+                    }),
+                );
+            }
+            if self.dynamic_scissor {
+                cmd_buffer.set_scissors(0, once(rect));
+            }
+        }
+    }
+
+    /// Record a command to push the given constants at `offset` bytes.
+    /// This will fail if they don't fit within a range declared for all of `stages`.
+    pub fn push_constants<T: PushConstants>(
+        &self,
+        cmd_buffer: &mut CommandBufferT,
+        stages: ShaderStageFlags,
+        offset: u32,
+        constants: &T,
+    ) -> Result<()> {
+        let range = T::range(offset);
+        if !self
+            .push_constants
+            .iter()
+            .any(|(s, r)| s.contains(stages) && r.start <= range.start && range.end <= r.end)
+        {
+            return Err(UsageError::PushConstantsOutOfRange.into());
+        }
+
+        unsafe {
+            cmd_buffer.push_graphics_constants(
+                &self.pipeline_layout,
+                stages,
+                offset,
+                constants.as_words(),
+            );
+        }
+
+        Ok(())
+    }
+
     /// Deactivate vulkan resources. Use before dropping
     pub fn deactivate(mut self, device: &mut DeviceT) {
         unsafe {
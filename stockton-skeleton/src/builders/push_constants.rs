@@ -0,0 +1,43 @@
+//! Typed push constants, to avoid casting to `&[u32]` by hand.
+
+use crate::types::*;
+
+use std::{
+    mem::{align_of, size_of},
+    ops::Range,
+    slice,
+};
+
+/// A type that can be pushed to shaders as push constants.
+///
+/// # Safety
+/// Implementors must be plain old data: `#[repr(C)]` (or a primitive), with no padding bytes,
+/// a size that is a multiple of 4 bytes and an alignment of at least 4 bytes.
+pub unsafe trait PushConstants: Copy {
+    /// The size of the constants, in bytes.
+    fn size() -> u32 {
+        size_of::<Self>() as u32
+    }
+
+    /// The range these constants take up when placed at `offset` bytes.
+    fn range(offset: u32) -> Range<u32> {
+        offset..offset + Self::size()
+    }
+
+    /// Get the constants as the words that should be passed to `push_graphics_constants`.
+    fn as_words(&self) -> &[u32] {
+        debug_assert!(size_of::<Self>() % 4 == 0);
+        debug_assert!(align_of::<Self>() >= align_of::<u32>());
+
+        // Safety: Guaranteed by the requirements of the trait.
+        unsafe { slice::from_raw_parts(self as *const Self as *const u32, size_of::<Self>() / 4) }
+    }
+}
+
+unsafe impl PushConstants for u32 {}
+unsafe impl PushConstants for i32 {}
+unsafe impl PushConstants for f32 {}
+unsafe impl<T: PushConstants, const N: usize> PushConstants for [T; N] {}
+unsafe impl PushConstants for Vector2 {}
+unsafe impl PushConstants for Vector3 {}
+unsafe impl PushConstants for Matrix4 {}
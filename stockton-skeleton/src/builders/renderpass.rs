@@ -1,9 +1,71 @@
-use crate::types::*;
+//! Describing renderpasses, either with a single subpass ([`RenderpassSpec`]), or several ([`MultiRenderpassSpec`]).
+//!
+//! For example, a deferred renderer might write a G-buffer in one subpass, then read it as input attachments in a lighting subpass:
+//! ```rust
+//! # use stockton_skeleton::builders::{MultiRenderpassSpec, SubpassSpec};
+//! # use gfx_hal::{format::Format, image::{Access, Layout}, pass::*, pso::PipelineStage, memory::Dependencies};
+//! # fn gbuffer_spec(swapchain_format: Format, depth_format: Format) -> MultiRenderpassSpec {
+//! let gbuffer = |format| Attachment {
+//!     format: Some(format),
+//!     samples: 1,
+//!     ops: AttachmentOps::new(AttachmentLoadOp::Clear, AttachmentStoreOp::DontCare),
+//!     stencil_ops: AttachmentOps::DONT_CARE,
+//!     layouts: Layout::Undefined..Layout::ShaderReadOnlyOptimal,
+//! };
+//!
+//! MultiRenderpassSpec {
+//!     attachments: vec![
+//!         // 0: Output
+//!         Attachment {
+//!             format: Some(swapchain_format),
+//!             samples: 1,
+//!             ops: AttachmentOps::new(AttachmentLoadOp::Clear, AttachmentStoreOp::Store),
+//!             stencil_ops: AttachmentOps::DONT_CARE,
+//!             layouts: Layout::Undefined..Layout::ColorAttachmentOptimal,
+//!         },
+//!         // 1: Albedo, 2: Normals
+//!         gbuffer(Format::Rgba8Unorm),
+//!         gbuffer(Format::Rgba16Sfloat),
+//!         // 3: Depth
+//!         Attachment {
+//!             format: Some(depth_format),
+//!             samples: 1,
+//!             ops: AttachmentOps::new(AttachmentLoadOp::Clear, AttachmentStoreOp::DontCare),
+//!             stencil_ops: AttachmentOps::DONT_CARE,
+//!             layouts: Layout::Undefined..Layout::DepthStencilAttachmentOptimal,
+//!         },
+//!     ],
+//!     subpasses: vec![
+//!         // Write the G-buffer
+//!         SubpassSpec {
+//!             colors: vec![(1, Layout::ColorAttachmentOptimal), (2, Layout::ColorAttachmentOptimal)],
+//!             depth: Some((3, Layout::DepthStencilAttachmentOptimal)),
+//!             ..SubpassSpec::default()
+//!         },
+//!         // Read it for lighting
+//!         SubpassSpec {
+//!             colors: vec![(0, Layout::ColorAttachmentOptimal)],
+//!             inputs: vec![(1, Layout::ShaderReadOnlyOptimal), (2, Layout::ShaderReadOnlyOptimal)],
+//!             ..SubpassSpec::default()
+//!         },
+//!     ],
+//!     dependencies: vec![SubpassDependency {
+//!         passes: Some(0)..Some(1),
+//!         stages: PipelineStage::COLOR_ATTACHMENT_OUTPUT..PipelineStage::FRAGMENT_SHADER,
+//!         accesses: Access::COLOR_ATTACHMENT_WRITE..Access::INPUT_ATTACHMENT_READ,
+//!         flags: Dependencies::BY_REGION,
+//!     }],
+//! }
+//! # }
+//! ```
+//!
+//! Then build a pipeline for each subpass with [`super::PipelineSpecBuilder::subpass`], giving each the same spec.
+//! Each [`super::CompletePipeline`] has its own renderpass, but they're all compatible, so draw with the first one's and call `next_subpass` between pipelines.
 
-use std::iter::{empty, once};
+use crate::{error::UsageError, types::*};
 
 use anyhow::Result;
-use hal::pass::{Attachment, AttachmentLayout, AttachmentRef, SubpassDesc};
+use hal::pass::{Attachment, AttachmentLayout, AttachmentRef, SubpassDependency, SubpassDesc};
 
 /// An attachment for a renderpass
 #[derive(Debug, Clone)]
@@ -15,6 +77,8 @@ pub struct AttachmentSpec {
     pub used_layout: AttachmentLayout,
 }
 
+/// A renderpass with a single subpass.
+/// Attachments are numbered in the order colors, depth, inputs, resolves, then preserves.
 #[derive(Debug, Clone)]
 pub struct RenderpassSpec {
     pub colors: Vec<AttachmentSpec>,
@@ -26,9 +90,100 @@ pub struct RenderpassSpec {
 
 impl RenderpassSpec {
     pub fn build_renderpass(self, device: &mut DeviceT) -> Result<RenderPassT> {
+        MultiRenderpassSpec::from(self).build_renderpass(device)
+    }
+}
+
+/// One subpass of a [`MultiRenderpassSpec`], referring to attachments by their index.
+#[derive(Debug, Clone, Default)]
+pub struct SubpassSpec {
+    pub colors: Vec<AttachmentRef>,
+    pub depth: Option<AttachmentRef>,
+    pub inputs: Vec<AttachmentRef>,
+    pub resolves: Vec<AttachmentRef>,
+    pub preserves: Vec<usize>,
+}
+
+impl SubpassSpec {
+    /// Get the indices of every attachment this subpass refers to.
+    fn attachment_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.colors
+            .iter()
+            .chain(self.depth.iter())
+            .chain(self.inputs.iter())
+            .chain(self.resolves.iter())
+            .map(|(id, _)| *id)
+            .chain(self.preserves.iter().copied())
+    }
+}
+
+/// A renderpass with any number of subpasses, which share a list of attachments.
+#[derive(Debug, Clone)]
+pub struct MultiRenderpassSpec {
+    pub attachments: Vec<Attachment>,
+    pub subpasses: Vec<SubpassSpec>,
+    pub dependencies: Vec<SubpassDependency>,
+}
+
+impl MultiRenderpassSpec {
+    pub fn build_renderpass(self, device: &mut DeviceT) -> Result<RenderPassT> {
+        self.validate()?;
+
+        let subpasses = self.subpasses.iter().map(|sp| SubpassDesc {
+            colors: sp.colors.as_slice(),
+            depth_stencil: sp.depth.as_ref(),
+            inputs: sp.inputs.as_slice(),
+            resolves: sp.resolves.as_slice(),
+            preserves: sp.preserves.as_slice(),
+        });
+
+        Ok(unsafe {
+            device.create_render_pass(
+                self.attachments.iter().cloned(),
+                subpasses,
+                self.dependencies.iter().cloned(),
+            )?
+        })
+    }
+
+    /// Check every attachment and subpass referred to exists, since the backend won't always catch it.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let subpass_count = self.subpasses.len();
+        if subpass_count == 0 {
+            return Err(UsageError::NoSubpasses.into());
+        }
+
+        for (subpass, sp) in self.subpasses.iter().enumerate() {
+            if let Some(attachment) = sp.attachment_ids().find(|id| *id >= self.attachments.len()) {
+                return Err(UsageError::AttachmentOutOfRange {
+                    subpass,
+                    attachment,
+                    count: self.attachments.len(),
+                }
+                .into());
+            }
+        }
+
+        for dep in self.dependencies.iter() {
+            let ends = dep.passes.start.iter().chain(dep.passes.end.iter());
+            if let Some(index) = ends.copied().find(|id| *id as usize >= subpass_count) {
+                return Err(UsageError::SubpassOutOfRange {
+                    index,
+                    count: subpass_count,
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<RenderpassSpec> for MultiRenderpassSpec {
+    fn from(spec: RenderpassSpec) -> Self {
         let mut next_offset = 0;
 
-        let colors: Vec<AttachmentRef> = self
+        let colors: Vec<AttachmentRef> = spec
             .colors
             .iter()
             .enumerate()
@@ -36,12 +191,12 @@ impl RenderpassSpec {
             .collect();
         next_offset = colors.len();
 
-        let depth_stencil = self.depth.as_ref().map(|x| (next_offset, x.used_layout));
-        if depth_stencil.is_some() {
+        let depth = spec.depth.as_ref().map(|x| (next_offset, x.used_layout));
+        if depth.is_some() {
             next_offset += 1;
         }
 
-        let inputs: Vec<AttachmentRef> = self
+        let inputs: Vec<AttachmentRef> = spec
             .inputs
             .iter()
             .enumerate()
@@ -49,7 +204,7 @@ impl RenderpassSpec {
             .collect();
         next_offset += inputs.len();
 
-        let resolves: Vec<AttachmentRef> = self
+        let resolves: Vec<AttachmentRef> = spec
             .resolves
             .iter()
             .enumerate()
@@ -57,30 +212,33 @@ impl RenderpassSpec {
             .collect();
         next_offset += resolves.len();
 
-        let preserves: Vec<usize> = self
+        let preserves: Vec<usize> = spec
             .preserves
             .iter()
             .enumerate()
             .map(|(i, _a)| next_offset + i)
             .collect();
 
-        let sp_desc = SubpassDesc {
-            colors: colors.as_slice(),
-            depth_stencil: depth_stencil.as_ref(),
-            inputs: inputs.as_slice(),
-            resolves: resolves.as_slice(),
-            preserves: preserves.as_slice(),
-        };
-
-        let all_attachments = self
+        let attachments = spec
             .colors
             .into_iter()
             .map(|x| x.attachment)
-            .chain(self.depth.into_iter().map(|x| x.attachment))
-            .chain(self.inputs.into_iter().map(|x| x.attachment))
-            .chain(self.resolves.into_iter().map(|x| x.attachment))
-            .chain(self.preserves.into_iter());
+            .chain(spec.depth.into_iter().map(|x| x.attachment))
+            .chain(spec.inputs.into_iter().map(|x| x.attachment))
+            .chain(spec.resolves.into_iter().map(|x| x.attachment))
+            .chain(spec.preserves.into_iter())
+            .collect();
 
-        Ok(unsafe { device.create_render_pass(all_attachments, once(sp_desc), empty())? })
+        MultiRenderpassSpec {
+            attachments,
+            subpasses: vec![SubpassSpec {
+                colors,
+                depth,
+                inputs,
+                resolves,
+                preserves,
+            }],
+            dependencies: vec![],
+        }
     }
 }
@@ -5,6 +5,21 @@ pub use shaderc::ShaderKind;
 use anyhow::{Context, Result};
 use hal::pso::Specialization;
 use shaderc::Compiler;
+use thiserror::Error;
+
+/// An error encountered while compiling a shader.
+/// This can be retrieved from the errors returned by the pipeline builders with [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Error)]
+pub enum ShaderCompilationError {
+    /// The shader source is invalid.
+    /// Contains the number of errors and shaderc's diagnostics, which include the line of each error.
+    #[error("{0} error(s) compiling {1:?} shader:\n{2}")]
+    Invalid(u32, ShaderKind, String),
+
+    /// Shaderc failed for some other reason.
+    #[error("Error compiling {0:?} shader")]
+    Other(ShaderKind, #[source] shaderc::Error),
+}
 
 #[derive(Debug, Clone)]
 pub struct ShaderDesc {
@@ -17,7 +32,12 @@ impl ShaderDesc {
     pub fn compile(&self, compiler: &mut Compiler, device: &mut DeviceT) -> Result<ShaderModuleT> {
         let artifact = compiler
             .compile_into_spirv(&self.source, self.kind, "shader", &self.entry, None)
-            .context("Shader compilation failed")?;
+            .map_err(|e| match e {
+                shaderc::Error::CompilationError(n, diagnostics) => {
+                    ShaderCompilationError::Invalid(n, self.kind, diagnostics)
+                }
+                e => ShaderCompilationError::Other(self.kind, e),
+            })?;
 
         // Make into shader module
         Ok(unsafe {
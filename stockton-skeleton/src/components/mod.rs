@@ -1,7 +1,7 @@
-use na::{Mat4, Vec4};
+use hal::pso::{Comparison, DepthTest};
 use std::f32::consts::PI;
 
-use crate::types::Vector3;
+use crate::types::{point_to_homogeneous, Matrix4, Vector3};
 
 /// 90 degrees in radians
 const R89: f32 = (PI / 180.0) * 89.0;
@@ -38,13 +38,16 @@ impl Transform {
     }
 
     pub fn translate(&mut self, delta: Vector3) {
+        self.position += self.to_world(delta);
+    }
+
+    /// Convert a movement relative to this object's rotation into world space.
+    pub fn to_world(&self, delta: Vector3) -> Vector3 {
         let rot_matrix =
-            Mat4::from_euler_angles(-self.rotation.x, self.rotation.y, self.rotation.z);
+            Matrix4::from_euler_angles(-self.rotation.x, self.rotation.y, self.rotation.z);
 
-        let new = rot_matrix * Vec4::new(delta.x, delta.y, delta.z, 1.0);
-        self.position.x += new.x;
-        self.position.y += new.y;
-        self.position.z += new.z;
+        let new = rot_matrix * point_to_homogeneous(&delta);
+        Vector3::new(new.x, new.y, new.z)
     }
 }
 
@@ -58,4 +61,43 @@ pub struct CameraSettings {
 
     /// Far clipping plane (world units)
     pub far: f32,
+
+    /// Map the near plane to a depth of 1 and the far plane to 0, rather than the other way around.
+    /// This gives much better depth precision in the distance, but draw passes must use
+    /// [`Self::depth_comparison`] and [`Self::depth_clear_value`] for it to work.
+    pub reversed_z: bool,
+}
+
+impl CameraSettings {
+    /// Get the projection matrix for the given aspect ratio, with depth in the range `0..1`.
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4 {
+        match self.reversed_z {
+            true => na::perspective_lh_zo(aspect_ratio, self.fov, self.far, self.near),
+            false => na::perspective_lh_zo(aspect_ratio, self.fov, self.near, self.far),
+        }
+    }
+
+    /// The depth comparison that should be used when depth testing.
+    pub fn depth_comparison(&self) -> Comparison {
+        match self.reversed_z {
+            true => Comparison::Greater,
+            false => Comparison::Less,
+        }
+    }
+
+    /// The depth test to use in pipelines drawing from this camera, which writes depth and uses [`Self::depth_comparison`].
+    pub fn depth_test(&self) -> DepthTest {
+        DepthTest {
+            fun: self.depth_comparison(),
+            write: true,
+        }
+    }
+
+    /// The value the depth buffer should be cleared to at the start of each frame.
+    pub fn depth_clear_value(&self) -> f32 {
+        match self.reversed_z {
+            true => 0.0,
+            false => 1.0,
+        }
+    }
 }
@@ -2,8 +2,10 @@
 //! This relies on draw passes for the actual drawing logic.
 
 use std::{
-    any::{Any, TypeId},
+    any::{type_name, Any, TypeId},
     collections::HashMap,
+    fmt::{self, Display, Formatter},
+    iter::{empty, once},
     marker::PhantomData,
     mem::ManuallyDrop,
     ptr::read,
@@ -12,15 +14,23 @@ use std::{
 
 use anyhow::{anyhow, Context, Result};
 use hal::{
-    format::{ChannelType, Format, ImageFeature},
-    image::{Extent, FramebufferAttachment, Usage, ViewCapabilities},
+    adapter::AdapterInfo,
+    buffer::{Access as BufferAccess, SubRange},
+    command::{BufferImageCopy, CommandBufferFlags, Level},
+    format::{Aspects, ChannelType, Format, ImageFeature},
+    image::{
+        Access, Extent, FramebufferAttachment, Layout, Offset, SubresourceLayers, Usage,
+        ViewCapabilities,
+    },
+    memory::{Barrier, Dependencies},
     pool::CommandPoolCreateFlags,
-    pso::Viewport,
+    pso::{PipelineStage, Viewport},
     queue::QueueFamilyId,
     window::{CompositeAlphaMode, PresentMode},
-    PhysicalDeviceProperties,
+    Features, PhysicalDeviceProperties,
 };
-use log::debug;
+use image::RgbaImage;
+use log::{debug, warn};
 
 use winit::window::Window;
 
@@ -29,15 +39,31 @@ use super::{
     queue_negotiator::{DrawQueue, QueueNegotiator},
     target::TargetChain,
 };
+
+pub use crate::target::PendingFrame;
 use crate::{
-    draw_passes::Singular,
+    backend as back,
+    buffers::{
+        image::{BoundImageView, ImageSpec, COLOR_RESOURCES, DEPTH_RESOURCES},
+        staging::StagingBuffer,
+    },
+    draw_passes::{
+        util::{queue_draw_checked, TargetSpecificResources},
+        FrameContext, PassPosition, Singular,
+    },
     error::{EnvironmentError, LockPoisoned, UsageError},
-    mem::MemoryPool,
-    queue_negotiator::{QueueFamilyNegotiator, QueueFamilySelector, SharedQueue},
+    mem::{MemoryPool, PoolStats, RenderTargetPool, StagingPool},
+    observer::{lock_device_observed, notify, RenderEvent, RenderObserver},
+    queue_negotiator::{
+        QueueFamilyNegotiator, QueueFamilyProperties, QueueFamilySelector, SharedQueue,
+    },
     session::Session,
     types::*,
 };
 
+/// The format of images drawn with [`RenderingContext::draw_to_image`].
+pub const READBACK_FORMAT: Format = Format::Rgba8Srgb;
+
 /// The actual data behind [`StatefulRenderingContext`]
 struct InnerRenderingContext {
     /// Vulkan Instance
@@ -52,12 +78,20 @@ struct InnerRenderingContext {
     /// The properties of the physical device we're using
     physical_device_properties: PhysicalDeviceProperties,
 
+    /// The optional device features that were enabled, see [`RenderingContext::enabled_features`]
+    enabled_features: Features,
+
     /// Swapchain and stuff
     target_chain: ManuallyDrop<TargetChain>,
 
-    // Command pool and buffers
-    /// The command pool used for our buffers
-    cmd_pool: ManuallyDrop<CommandPoolT>,
+    /// The number of frames drawn successfully, see [`RenderingContext::frame_number`]
+    frames_drawn: u64,
+
+    /// The index of the resources used by the last frame drawn, or None if no frames have been drawn since the surface was last created
+    last_frame_index: Option<usize>,
+
+    /// Receives events from the renderer, see [`RenderingContext::set_observer`]
+    observer: Option<Arc<dyn RenderObserver>>,
 
     /// The queue negotiator to use
     queue_negotiator: QueueNegotiator,
@@ -65,14 +99,81 @@ struct InnerRenderingContext {
     /// The queue to use for drawing
     queue: Arc<RwLock<QueueT>>,
 
+    /// Command pool on the draw queue's family, used by [`RenderingContext::run_one_time_commands`]
+    cmd_pool: ManuallyDrop<CommandPoolT>,
+
     ///  Number of pixels per standard point
     pixels_per_point: f32,
 
     /// The list of memory pools
-    memory_pools: HashMap<TypeId, Box<dyn Any>>,
+    memory_pools: HashMap<TypeId, Box<dyn StoredMemoryPool>>,
 
     /// Shared properties for this context
     properties: ContextProperties,
+
+    /// The options the context was created with. These are kept so they're used again when the surface is recreated.
+    options: RendererOptions,
+}
+
+/// Options for creating a [`RenderingContext`], or a [`crate::Renderer`].
+/// The default picks everything based on what the surface supports.
+#[derive(Debug, Clone, Default)]
+pub struct RendererOptions {
+    /// The number of swapchain images to ask for, which is clamped to what the surface supports.
+    /// More images means more frames in flight, which can reduce stutter at the cost of latency and memory.
+    /// If this is None, a sensible default is picked based on the present mode. Check [`ContextProperties::image_count`] for the actual number.
+    pub image_count: Option<u32>,
+
+    /// The most frames to queue at once, which is clamped between 1 and the number of swapchain images.
+    /// Fewer frames in flight means less latency, and more means better throughput when the CPU or GPU is the bottleneck.
+    /// If this is None, there is one frame in flight for each swapchain image. Check [`ContextProperties::frames_in_flight`] for the actual number.
+    pub frames_in_flight: Option<u32>,
+
+    /// Formats to use for swapchain images, most preferred first. The first one the surface supports is used, see [`ContextProperties::find_best_with_preference`].
+    /// If none of them are supported, an SRGB format is used as usual.
+    pub preferred_formats: Vec<Format>,
+
+    /// Fall back to swapchain properties that might not look or perform as well if the surface doesn't support what we'd like.
+    /// This lets minimal or virtual GPUs, such as those used in CI, be used at all. See [`ContextProperties::find_best_relaxed`] for what's relaxed.
+    pub relaxed_properties: bool,
+}
+
+/// A memory pool stored in the context, with its type erased.
+trait StoredMemoryPool {
+    fn as_any(&self) -> &dyn Any;
+    fn name(&self) -> &'static str;
+    fn stats(&self) -> Option<PoolStats>;
+    fn deactivate(
+        self: Box<Self>,
+        context: &mut StatefulRenderingContext<DeactivatedMemoryPools>,
+    ) -> Result<()>;
+}
+
+impl<P: MemoryPool> StoredMemoryPool for Arc<RwLock<P>> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &'static str {
+        type_name::<P>()
+    }
+
+    fn stats(&self) -> Option<PoolStats> {
+        self.read().ok()?.stats()
+    }
+
+    fn deactivate(
+        self: Box<Self>,
+        context: &mut StatefulRenderingContext<DeactivatedMemoryPools>,
+    ) -> Result<()> {
+        let pool = Arc::try_unwrap(*self)
+            .map_err(|_| UsageError::MemoryPoolInUse(type_name::<P>()))?
+            .into_inner()
+            .map_err(|_| LockPoisoned::MemoryPool)?;
+        pool.deactivate(context);
+
+        Ok(())
+    }
 }
 
 /// A type enum for different states the `RenderingContext` can be in.
@@ -112,9 +213,11 @@ pub type RenderingContext = StatefulRenderingContext<Normal>;
 
 /// Methods only implemented in normal operation
 impl StatefulRenderingContext<Normal> {
-    /// Create a new RenderingContext for the given window.
+    /// Create a new RenderingContext for the given window, picking swapchain properties as described in `options`.
+    /// Any features the draw pass needs (see [`IntoDrawPass::required_features`]) are enabled, or an error is returned if the device doesn't support them.
     pub fn new<IDP: IntoDrawPass<DP, Singular>, DP: DrawPass<Singular>>(
         window: &Window,
+        options: RendererOptions,
     ) -> Result<Self> {
         // Create surface
         let (instance, surface, mut adapters) = unsafe {
@@ -148,6 +251,21 @@ impl StatefulRenderingContext<Normal> {
             (qn, dq.surface)
         };
 
+        // Features the draw pass needs, and optional features that draw passes can use if the device supports them
+        let supported_features = adapter.physical_device.features();
+        let required_features = IDP::required_features();
+        if !supported_features.contains(required_features) {
+            return Err(
+                EnvironmentError::MissingFeatures(required_features - supported_features).into(),
+            );
+        }
+        let enabled_features = required_features
+            | (supported_features
+                & (Features::DEPTH_CLAMP
+                    | Features::SAMPLER_ANISOTROPY
+                    | Features::LINE_WIDTH
+                    | Features::POINT_SIZE));
+
         // Device & Queue groups
         let (device_lock, queue_groups) = {
             // TODO: This sucks, but hal is restrictive on how we can pass this specific argument.
@@ -158,7 +276,7 @@ impl StatefulRenderingContext<Normal> {
             let gpu = unsafe {
                 adapter
                     .physical_device
-                    .open(&open_spec.as_vec(), hal::Features::empty())
+                    .open(&open_spec.as_vec(), enabled_features)
                     .context("Error opening logical device")?
             };
 
@@ -168,34 +286,38 @@ impl StatefulRenderingContext<Normal> {
         let mut queue_negotiator = family_negotiator.finish(queue_groups);
 
         // Context properties
-        let properties = ContextProperties::find_best(&adapter, &surface)
-            .context("Error getting context properties")?;
+        let properties = ContextProperties::find_best_inner(
+            &adapter,
+            &surface,
+            &options.preferred_formats,
+            options.image_count,
+            options.frames_in_flight,
+            options.relaxed_properties,
+        )
+        .context("Error getting context properties")?;
 
         debug!("Detected context properties: {:?}", properties);
 
-        let (cmd_pool, target_chain) = {
+        let (target_chain, cmd_pool) = {
             // Lock device
             let mut device = device_lock
                 .write()
                 .map_err(|_| LockPoisoned::Device)
                 .context("Error getting device lock")?;
 
-            // Command pool
-            let mut cmd_pool = unsafe {
-                device.create_command_pool(
-                    queue_negotiator
-                        .family::<DrawQueue>()
-                        .ok_or(EnvironmentError::NoSuitableFamilies)?,
-                    CommandPoolCreateFlags::RESET_INDIVIDUAL,
-                )
-            }
-            .context("Error creating draw command pool")?;
+            let family = queue_negotiator
+                .family::<DrawQueue>()
+                .ok_or(EnvironmentError::NoSuitableFamilies)?;
 
             // Swapchain and associated resources
-            let target_chain = TargetChain::new(&mut device, surface, &mut cmd_pool, &properties)
+            let target_chain = TargetChain::new(&mut device, surface, family, &properties)
                 .context("Error creating target chain")?;
 
-            (cmd_pool, target_chain)
+            let cmd_pool =
+                unsafe { device.create_command_pool(family, CommandPoolCreateFlags::TRANSIENT) }
+                    .context("Error creating command pool")?;
+
+            (target_chain, cmd_pool)
         };
 
         let queue = queue_negotiator
@@ -208,17 +330,22 @@ impl StatefulRenderingContext<Normal> {
 
                 device: device_lock,
                 physical_device_properties: adapter.physical_device.properties(),
+                enabled_features,
                 adapter,
 
                 queue_negotiator,
                 queue,
+                cmd_pool: ManuallyDrop::new(cmd_pool),
 
                 target_chain: ManuallyDrop::new(target_chain),
-                cmd_pool: ManuallyDrop::new(cmd_pool),
+                frames_drawn: 0,
+                last_frame_index: None,
+                observer: None,
 
                 pixels_per_point: window.scale_factor() as f32,
                 memory_pools: HashMap::new(),
                 properties,
+                options,
             }),
             PhantomData,
         ))
@@ -246,11 +373,43 @@ impl StatefulRenderingContext<Normal> {
         dp: &mut DP,
     ) -> Result<()> {
         // Lock device & queue. We can't use our nice convenience function, because of borrowing issues
-        let mut device = self
+        let mut device = lock_device_observed(&self.0.device, self.0.observer.as_deref())
+            .context("Error getting device lock")?;
+        let mut queue = self
             .0
-            .device
+            .queue
             .write()
-            .map_err(|_| LockPoisoned::Device)
+            .map_err(|_| LockPoisoned::Queue)
+            .context("Error getting draw queue lock")?;
+
+        let frame = self
+            .0
+            .target_chain
+            .do_draw_with(&mut device, &mut queue, dp, session, self.0.frames_drawn)
+            .context("Error preparing next target")?;
+
+        self.0.frames_drawn += 1;
+        self.0.last_frame_index = Some(frame.frame_index);
+
+        Ok(())
+    }
+
+    /// Acquire the next swapchain image and get its frame's resources ready, without drawing anything yet.
+    /// The returned frame can be recorded with [`PendingFrame::record`], on any thread, then must be given back with [`Self::submit_frame`] or [`Self::cancel_frame`].
+    /// The frame is given the current [`Self::frame_number`], so only one frame should be pending at a time, and it must be given back before the surface is recreated.
+    pub fn begin_frame(&mut self) -> Result<PendingFrame> {
+        let mut device = lock_device_observed(&self.0.device, self.0.observer.as_deref())
+            .context("Error getting device lock")?;
+
+        self.0
+            .target_chain
+            .begin_frame(&mut device, self.0.frames_drawn)
+            .context("Error preparing next target")
+    }
+
+    /// Submit a frame begun with [`Self::begin_frame`] and recorded with [`PendingFrame::record`], and present it.
+    pub fn submit_frame(&mut self, frame: PendingFrame) -> Result<()> {
+        let mut device = lock_device_observed(&self.0.device, self.0.observer.as_deref())
             .context("Error getting device lock")?;
         let mut queue = self
             .0
@@ -259,14 +418,172 @@ impl StatefulRenderingContext<Normal> {
             .map_err(|_| LockPoisoned::Queue)
             .context("Error getting draw queue lock")?;
 
+        let frame_index = frame.context().frame_index;
         self.0
             .target_chain
-            .do_draw_with(&mut device, &mut queue, dp, session)
-            .context("Error preparing next target")?;
+            .submit_frame(&mut device, &mut queue, frame)
+            .context("Error submitting frame")?;
+
+        self.0.frames_drawn += 1;
+        self.0.last_frame_index = Some(frame_index);
 
         Ok(())
     }
 
+    /// Give back a frame begun with [`Self::begin_frame`] without submitting it, for example because recording it failed.
+    /// If it was recorded, the draw pass should be told with [`crate::draw_passes::DrawPass::frame_cancelled`], so it can redo any uploads the frame would have done.
+    pub fn cancel_frame(&mut self, frame: PendingFrame) {
+        self.0.target_chain.cancel_frame(frame);
+    }
+
+    /// Draw one frame into an offscreen image the size of the surface instead of the swapchain, and read it back.
+    /// This waits for the device to be idle first, and blocks until the frame has been drawn and copied, so is mostly useful for tests that compare a draw pass's output to a known image.
+    /// Nothing is presented, and the swapchain isn't used. The draw pass is told it's drawing the first frame in flight.
+    ///
+    /// The image is [`READBACK_FORMAT`] rather than the swapchain's format, so [`Self::properties`] are changed to describe it while drawing.
+    /// The draw pass is rebuilt with [`DrawPass::handle_surface_change`] before and after, so its framebuffers match, which is why it's taken by value and returned.
+    /// Like `handle_surface_change`, if this returns an error the draw pass is lost.
+    /// The context still needs a window and surface, which decide the size of the image.
+    pub fn draw_to_image<DP: DrawPass<Singular>>(
+        &mut self,
+        session: &Session,
+        dp: DP,
+    ) -> Result<(RgbaImage, DP)> {
+        self.lock_device()?
+            .wait_idle()
+            .context("Error waiting for device to be idle")?;
+
+        // Have the draw pass make its framebuffers for our image instead of the swapchain's
+        let surface_properties = self.0.properties.clone();
+        self.0.properties.color_format = READBACK_FORMAT;
+        self.0.properties.color_usage = Usage::COLOR_ATTACHMENT | Usage::TRANSFER_SRC;
+        let mut dp = match dp.handle_surface_change(session, self) {
+            Ok(dp) => dp,
+            Err(e) => {
+                self.0.properties = surface_properties;
+                return Err(e).context("Error preparing draw pass for offscreen image");
+            }
+        };
+
+        let image = self.draw_offscreen(session, &mut dp);
+
+        self.0.properties = surface_properties;
+        let dp = dp
+            .handle_surface_change(session, self)
+            .context("Error returning draw pass to the surface")?;
+
+        Ok((image?, dp))
+    }
+
+    /// Draw one frame into a new image with the current properties, and read it back. See [`Self::draw_to_image`].
+    fn draw_offscreen<DP: DrawPass<Singular>>(
+        &mut self,
+        session: &Session,
+        dp: &mut DP,
+    ) -> Result<RgbaImage> {
+        let props = self.properties().clone();
+        let (width, height) = (props.extent.width, props.extent.height);
+        let target = BoundImageView::<RenderTargetPool>::from_context(
+            self,
+            &ImageSpec {
+                width,
+                height,
+                format: props.color_format,
+                usage: props.color_usage,
+                resources: COLOR_RESOURCES,
+            },
+        )
+        .context("Error creating offscreen image")?;
+        let mut readback = match StagingBuffer::<StagingPool>::readback_from_context(
+            self,
+            width as u64 * height as u64 * 4,
+        )
+        .context("Error creating readback buffer")
+        {
+            Ok(x) => x,
+            Err(e) => {
+                target.deactivate_with_context(self);
+                return Err(e);
+            }
+        };
+
+        let frame = FrameContext {
+            frame_index: 0,
+            frame_count: self.frames_in_flight(),
+            frame_number: self.frame_number(),
+            viewport: props.extent.rect(),
+        };
+        let drawn = self
+            .run_one_time_commands(|buf| -> Result<()> {
+                queue_draw_checked(dp.name(), || {
+                    dp.queue_draw(session, target.img_view(), buf, frame)
+                })
+                .context("Error in draw pass")?;
+
+                unsafe {
+                    buf.pipeline_barrier(
+                        PipelineStage::COLOR_ATTACHMENT_OUTPUT..PipelineStage::TRANSFER,
+                        Dependencies::empty(),
+                        once(Barrier::Image {
+                            states: (Access::COLOR_ATTACHMENT_WRITE, Singular::layout_out())
+                                ..(Access::TRANSFER_READ, Layout::TransferSrcOptimal),
+                            target: target.img(),
+                            families: None,
+                            range: COLOR_RESOURCES,
+                        }),
+                    );
+                    buf.copy_image_to_buffer(
+                        target.img(),
+                        Layout::TransferSrcOptimal,
+                        &*readback.buf(),
+                        once(BufferImageCopy {
+                            buffer_offset: 0,
+                            buffer_width: width,
+                            buffer_height: height,
+                            image_layers: SubresourceLayers {
+                                aspects: Aspects::COLOR,
+                                level: 0,
+                                layers: 0..1,
+                            },
+                            image_offset: Offset { x: 0, y: 0, z: 0 },
+                            image_extent: props.extent,
+                        }),
+                    );
+                    buf.pipeline_barrier(
+                        PipelineStage::TRANSFER..PipelineStage::HOST,
+                        Dependencies::empty(),
+                        once(Barrier::Buffer {
+                            states: BufferAccess::TRANSFER_WRITE..BufferAccess::HOST_READ,
+                            target: &*readback.buf(),
+                            families: None,
+                            range: SubRange::WHOLE,
+                        }),
+                    );
+                }
+
+                Ok(())
+            })
+            .and_then(|r| r);
+
+        let image = drawn.and_then(|_| {
+            let mut device = self.lock_device()?;
+            let size = width as usize * height as usize * 4;
+            let ptr = readback.map(&mut device, 0..size as u64)?;
+
+            // Safety: The buffer is at least `size` bytes, and the GPU is done writing to it.
+            let mut data = unsafe { std::slice::from_raw_parts(ptr, size) }.to_vec();
+            readback.unmap(&mut device)?;
+
+            RgbaImage::from_raw(width, height, data)
+                .ok_or_else(|| anyhow!("Read back data was the wrong size"))
+        });
+
+        readback.deactivate_context(self);
+        target.deactivate_with_context(self);
+
+        image
+    }
+
     /// Get the specified memory pool, lazily initialising it if it's not yet present
     pub fn memory_pool<P: MemoryPool>(&mut self) -> Result<&Arc<RwLock<P>>> {
         self.ensure_memory_pool::<P>()?;
@@ -305,6 +622,83 @@ impl StatefulRenderingContext<Normal> {
         Ok(())
     }
 
+    /// Create `count` images from the given spec, using memory from the given pool.
+    /// This is useful for intermediate render targets, for example an offscreen image that a later pass samples for post-processing.
+    /// In most cases, count should be [`Self::frames_in_flight`] and the size should be `properties().extent`.
+    pub fn create_target_images<P: MemoryPool>(
+        &mut self,
+        spec: &ImageSpec,
+        count: usize,
+    ) -> Result<TargetSpecificResources<BoundImageView<P>>> {
+        let mut images = Vec::with_capacity(count);
+        for _ in 0..count {
+            match BoundImageView::from_context(self, spec) {
+                Ok(img) => images.push(img),
+                Err(e) => {
+                    for img in images {
+                        img.deactivate_with_context(self);
+                    }
+                    return Err(e.context("Error creating target image"));
+                }
+            }
+        }
+
+        Ok(images.into())
+    }
+
+    /// Create a depth buffer for each frame in flight, the size of the surface, using memory from the given pool (usually [`crate::mem::DepthBufferPool`]).
+    /// If `sampled` is true, the images can also be sampled by later passes, using the image for the current frame's index.
+    /// This fails if the depth format doesn't support sampling, see [`ContextProperties::depth_sampleable`].
+    /// When the surface changes, deactivate them with [`Self::deactivate_target_images`] and create them again.
+    pub fn create_depth_buffers<P: MemoryPool>(
+        &mut self,
+        sampled: bool,
+    ) -> Result<TargetSpecificResources<BoundImageView<P>>> {
+        let properties = self.properties();
+        let mut usage = Usage::DEPTH_STENCIL_ATTACHMENT;
+        if sampled {
+            if !properties.depth_sampleable {
+                return Err(EnvironmentError::SampledDepthFormat.into());
+            }
+            usage |= Usage::SAMPLED;
+        }
+
+        let spec = ImageSpec {
+            width: properties.extent.width,
+            height: properties.extent.height,
+            format: properties.depth_format,
+            usage,
+            resources: DEPTH_RESOURCES,
+        };
+        let count = self.frames_in_flight();
+
+        self.create_target_images(&spec, count)
+            .context("Error creating depth buffers")
+    }
+
+    /// Deactivate the given images, and create a new set from the given spec with the same count.
+    /// This should be called from [`crate::draw_passes::DrawPass::handle_surface_change`], with a spec using the new size.
+    pub fn recreate_target_images<P: MemoryPool>(
+        &mut self,
+        images: TargetSpecificResources<BoundImageView<P>>,
+        spec: &ImageSpec,
+    ) -> Result<TargetSpecificResources<BoundImageView<P>>> {
+        let count = images.len();
+        self.deactivate_target_images(images);
+
+        self.create_target_images(spec, count)
+    }
+
+    /// Deactivate images created with [`Self::create_target_images`].
+    pub fn deactivate_target_images<P: MemoryPool>(
+        &mut self,
+        images: TargetSpecificResources<BoundImageView<P>>,
+    ) {
+        for img in images.dissolve() {
+            img.deactivate_with_context(self);
+        }
+    }
+
     /// Ensure the specified memory pool is initialised.
     #[allow(clippy::map_entry)] // We can't follow the suggestion because of a borrowing issue
     pub fn ensure_memory_pool<P: MemoryPool>(&mut self) -> Result<()> {
@@ -324,16 +718,43 @@ impl StatefulRenderingContext<Normal> {
         self.0
             .memory_pools
             .get(&TypeId::of::<P>())
-            .map(|x| x.downcast_ref().unwrap())
+            .map(|x| x.as_any().downcast_ref().unwrap())
+    }
+
+    /// Get statistics for every memory pool that has been initialised and keeps track of them, along with the name of each pool's type.
+    /// The stats can be added together to get the total memory usage.
+    pub fn memory_stats(&self) -> Vec<(&'static str, PoolStats)> {
+        self.0
+            .memory_pools
+            .values()
+            .filter_map(|x| Some((x.name(), x.stats()?)))
+            .collect()
     }
 
-    /// Deactivate all stored memory pools.
+    /// Deactivate all stored memory pools, after waiting for the device to be idle.
+    /// Everything allocated from them should be deactivated first, and nothing else can hold a reference to any of the pools.
+    /// If a pool can't be deactivated, the rest still are, and the first error is returned once they're done.
     pub fn deactivate_memory_pools(
         self,
     ) -> Result<StatefulRenderingContext<DeactivatedMemoryPools>> {
-        // TODO: Properly deactivate memory pools
+        self.lock_device()?
+            .wait_idle()
+            .context("Error waiting for device to be idle")?;
+
+        let mut ctx = StatefulRenderingContext(self.0, PhantomData);
+        let pools = std::mem::take(&mut ctx.0.memory_pools);
+        let mut result = Ok(());
+        for pool in pools.into_values() {
+            let name = pool.name();
+            let r = pool
+                .deactivate(&mut ctx)
+                .with_context(|| format!("Error deactivating memory pool {}", name));
+            if result.is_ok() {
+                result = r;
+            }
+        }
 
-        Ok(StatefulRenderingContext(self.0, PhantomData))
+        result.map(|_| ctx)
     }
 }
 
@@ -352,6 +773,56 @@ impl<S: RenderingContextState> StatefulRenderingContext<S> {
         self.0.pixels_per_point
     }
 
+    /// Set the pixels per point.
+    /// This should be called when the window's scale factor changes, for example when it's moved to a different monitor.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.0.pixels_per_point = pixels_per_point;
+    }
+
+    /// Record commands into a one-time-submit command buffer with `f`, then submit them on the draw queue and block until they're done.
+    /// This is for one-off work like uploading a lookup table when a draw pass is initialised, and shouldn't be used every frame.
+    /// The device is locked while `f` runs.
+    pub fn run_one_time_commands<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut CommandBufferT) -> R,
+    {
+        let inner = &mut *self.0;
+        let mut device = inner.device.write().map_err(|_| LockPoisoned::Device)?;
+
+        unsafe {
+            let mut buf = inner.cmd_pool.allocate_one(Level::Primary);
+            buf.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+            let result = f(&mut buf);
+            buf.finish();
+
+            let mut fence = match device.create_fence(false) {
+                Ok(f) => f,
+                Err(e) => {
+                    inner.cmd_pool.free(once(buf));
+                    return Err(e).context("Error creating fence");
+                }
+            };
+
+            let waited = inner
+                .queue
+                .write()
+                .map_err(|_| anyhow!(LockPoisoned::Queue))
+                .and_then(|mut queue| {
+                    queue.submit(once(&buf), empty(), empty(), Some(&mut fence));
+                    drop(queue);
+
+                    device
+                        .wait_for_fence(&fence, u64::MAX)
+                        .context("Error waiting for commands to finish")
+                });
+
+            device.destroy_fence(fence);
+            inner.cmd_pool.free(once(buf));
+
+            waited.map(|_| result)
+        }
+    }
+
     /// Get a new reference to the lock for the device used by this context.
     /// This can be used when instantiating code that runs in another thread.
     pub fn clone_device_lock(&self) -> Arc<RwLock<DeviceT>> {
@@ -360,7 +831,19 @@ impl<S: RenderingContextState> StatefulRenderingContext<S> {
 
     /// Lock the device used by this rendering context
     pub fn lock_device(&self) -> Result<RwLockWriteGuard<'_, DeviceT>> {
-        Ok(self.0.device.write().map_err(|_| LockPoisoned::Device)?)
+        lock_device_observed(&self.0.device, self.0.observer.as_deref())
+    }
+
+    /// Set the observer to give [`RenderEvent`]s to, or None to stop observing.
+    /// Texture loaders and other components created afterwards will also use this observer.
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn RenderObserver>>) {
+        self.0.observer = observer;
+    }
+
+    /// Get the observer events are being given to, if there is one.
+    /// Components that run on other threads should clone this when they're created.
+    pub fn observer(&self) -> Option<Arc<dyn RenderObserver>> {
+        self.0.observer.clone()
     }
 
     /// Get a reference to the rendering context's adapter.
@@ -368,6 +851,26 @@ impl<S: RenderingContextState> StatefulRenderingContext<S> {
         &self.0.adapter
     }
 
+    /// Get the name, type, and ids of the adapter being used.
+    pub fn adapter_info(&self) -> &AdapterInfo {
+        &self.0.adapter.info
+    }
+
+    /// Describe the adapter, backend, and properties being used, over a few lines.
+    /// This is useful for logging, or for users to paste into bug reports.
+    pub fn describe(&self) -> String {
+        let info = self.adapter_info();
+        format!(
+            "Adapter: {} ({:?}, vendor {:#06x}, device {:#06x})\nBackend: {}\n{}",
+            info.name,
+            info.device_type,
+            info.vendor,
+            info.device,
+            back::NAME,
+            self.properties()
+        )
+    }
+
     /// Get a shared queue from the family that was selected with T.
     /// You should already have called [`crate::queue_negotiator::QueueFamilyNegotiator::find`], otherwise this will return an error.
     pub fn get_queue<T: QueueFamilySelector>(&mut self) -> Result<SharedQueue> {
@@ -383,24 +886,76 @@ impl<S: RenderingContextState> StatefulRenderingContext<S> {
             .ok_or(anyhow!(UsageError::QueueNegotiatorMisuse))
     }
 
+    /// Get what the family selected by T can do, for example to check if it's a dedicated transfer queue.
+    /// You should already have called [`crate::queue_negotiator::QueueFamilyNegotiator::find`], otherwise this will return an error.
+    pub fn queue_family_properties<T: QueueFamilySelector>(&self) -> Result<QueueFamilyProperties> {
+        let id = self.get_queue_family::<T>()?;
+        let family = self
+            .0
+            .adapter
+            .queue_families
+            .iter()
+            .find(|x| x.id() == id)
+            .ok_or(EnvironmentError::NoSuitableFamilies)?;
+
+        Ok(QueueFamilyProperties::new(
+            family,
+            self.0
+                .physical_device_properties
+                .limits
+                .timestamp_compute_and_graphics,
+        ))
+    }
+
     /// Get a reference to the physical device's properties.
     pub fn physical_device_properties(&self) -> &PhysicalDeviceProperties {
         &self.0.physical_device_properties
     }
+    /// Get the device features that were enabled.
+    /// This is everything the draw pass asked for with [`IntoDrawPass::required_features`], along with [`Features::DEPTH_CLAMP`], [`Features::SAMPLER_ANISOTROPY`], [`Features::LINE_WIDTH`] and [`Features::POINT_SIZE`] if the device supports them.
+    pub fn enabled_features(&self) -> Features {
+        self.0.enabled_features
+    }
+
     /// Get a reference to the rendering context's properties.
     pub fn properties(&self) -> &ContextProperties {
         &self.0.properties
     }
 
+    /// Get the number of frames that can be queued at once.
+    /// Draw passes should keep this many copies of any per-frame resources, for example with [`TargetSpecificResources`].
+    pub fn frames_in_flight(&self) -> usize {
+        self.0.properties.frames_in_flight as usize
+    }
+
+    /// Get the number of frames drawn so far, which is also the [`FrameContext::frame_number`] the next frame will be given.
+    /// This only counts frames that were drawn successfully, and keeps counting when the surface is recreated.
+    pub fn frame_number(&self) -> u64 {
+        self.0.frames_drawn
+    }
+
+    /// Get the [`FrameContext::frame_index`] of the last frame drawn, which says which copy of any per-frame resources it used.
+    /// This is None if no frames have been drawn since the surface was last created.
+    pub fn last_frame_index(&self) -> Option<usize> {
+        self.0.last_frame_index
+    }
+
     /// Recreate the surface, swapchain, and other derived components.
     pub fn recreate_surface(mut self) -> Result<Self> {
         // TODO: Deactivate if this fails
+        let family = self
+            .0
+            .queue_negotiator
+            .family::<DrawQueue>()
+            .ok_or(EnvironmentError::NoSuitableFamilies)?;
+
+        notify(
+            self.0.observer.as_deref(),
+            RenderEvent::SurfaceRecreateStarted,
+        );
+
         unsafe {
-            let mut device = self
-                .0
-                .device
-                .write()
-                .map_err(|_| LockPoisoned::Device)
+            let mut device = lock_device_observed(&self.0.device, self.0.observer.as_deref())
                 .context("Error getting device lock")?;
 
             device
@@ -408,40 +963,59 @@ impl<S: RenderingContextState> StatefulRenderingContext<S> {
                 .context("Error waiting for device to become idle")?;
 
             let surface = ManuallyDrop::into_inner(read(&self.0.target_chain))
-                .deactivate_with_recyling(&mut device, &mut self.0.cmd_pool);
-
-            self.0.properties = ContextProperties::find_best(&self.0.adapter, &surface)
-                .context("Error finding best swapchain properties")?;
+                .deactivate_with_recyling(&mut device);
+
+            self.0.properties = ContextProperties::find_best_inner(
+                &self.0.adapter,
+                &surface,
+                &self.0.options.preferred_formats,
+                self.0.options.image_count,
+                self.0.options.frames_in_flight,
+                self.0.options.relaxed_properties,
+            )
+            .context("Error finding best swapchain properties")?;
 
             // TODO: This is unsound, if we return an error here `self.0.TargetChain` may be accessed again.
             self.0.target_chain = ManuallyDrop::new(
-                TargetChain::new(
-                    &mut device,
-                    surface,
-                    &mut self.0.cmd_pool,
-                    &self.0.properties,
-                )
-                .context("Error creating target chain")?,
+                TargetChain::new(&mut device, surface, family, &self.0.properties)
+                    .context("Error creating target chain")?,
             );
+            self.0.last_frame_index = None;
         }
 
+        notify(
+            self.0.observer.as_deref(),
+            RenderEvent::SurfaceRecreateFinished,
+        );
+
         Ok(StatefulRenderingContext(self.0, PhantomData))
     }
 }
 
 // Methods only implemented after we start deactivating
 impl StatefulRenderingContext<DeactivatedMemoryPools> {
+    /// Destroy the context, after waiting for the device to be idle.
+    /// Nothing else can hold a reference to the device (see [`Self::clone_device_lock`]) or the queue, as they need to be destroyed before the instance.
     pub fn deactivate(mut self) -> Result<()> {
         self.lock_device()?.wait_idle()?;
 
-        // TODO: The rest of the deactivation code needs updated.
+        if Arc::strong_count(&self.0.device) > 1 || Arc::strong_count(&self.0.queue) > 1 {
+            return Err(UsageError::DeviceInUse.into());
+        }
+
         unsafe {
             let mut device = self.0.device.write().map_err(|_| LockPoisoned::Device)?;
 
+            device.destroy_command_pool(ManuallyDrop::take(&mut self.0.cmd_pool));
+
             let target_chain = ManuallyDrop::take(&mut self.0.target_chain);
-            target_chain.deactivate(&mut self.0.instance, &mut device, &mut self.0.cmd_pool);
+            target_chain.deactivate(&mut self.0.instance, &mut device);
+            drop(device);
 
-            device.destroy_command_pool(ManuallyDrop::into_inner(self.0.cmd_pool));
+            // The device and queue are destroyed when the last reference to them is dropped, which must happen before the instance is.
+            let instance = ManuallyDrop::take(&mut self.0.instance);
+            drop(self);
+            drop(instance);
         }
 
         Ok(())
@@ -454,9 +1028,17 @@ pub struct ContextProperties {
     /// Format to be used by colour attachments. Used by swapchain images.
     pub color_format: Format,
 
+    /// The usage of the colour images being drawn to, which framebuffers are made for.
+    /// This is `COLOR_ATTACHMENT` for swapchain images, and also `TRANSFER_SRC` while drawing with [`RenderingContext::draw_to_image`].
+    pub color_usage: Usage,
+
     /// Recommended format to be used by depth attachments.
     pub depth_format: Format,
 
+    /// Whether images with `depth_format` can also be sampled, so later passes can read depth buffers.
+    /// Formats that support this are preferred, but it isn't guaranteed.
+    pub depth_sampleable: bool,
+
     /// The present mode being used by the context
     pub present_mode: PresentMode,
 
@@ -466,45 +1048,170 @@ pub struct ContextProperties {
     pub viewport: Viewport,
     pub extent: Extent,
 
-    /// The maximum number of frames we queue at once.
+    /// The number of images in the swapchain.
     pub image_count: u32,
+
+    /// The maximum number of frames we queue at once. This is never more than `image_count`.
+    /// Resources that are used for a single frame should have this many copies.
+    pub frames_in_flight: u32,
+}
+
+impl Display for ContextProperties {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Color format: {:?}", self.color_format)?;
+        writeln!(
+            f,
+            "Depth format: {:?} (sampleable: {})",
+            self.depth_format, self.depth_sampleable
+        )?;
+        writeln!(f, "Present mode: {:?}", self.present_mode)?;
+        writeln!(f, "Composite alpha: {:?}", self.composite_alpha_mode)?;
+        writeln!(f, "Extent: {}x{}", self.extent.width, self.extent.height)?;
+        write!(
+            f,
+            "Swapchain images: {} ({} in flight)",
+            self.image_count, self.frames_in_flight
+        )
+    }
 }
 
 impl ContextProperties {
-    /// Find the best properties for the given adapter and surface
+    /// Find the best properties for the given adapter and surface.
+    /// `desired_image_count` and `desired_frames_in_flight` are clamped to valid values, see [`RendererOptions`].
     pub fn find_best(
         adapter: &Adapter,
         surface: &SurfaceT,
+        desired_image_count: Option<u32>,
+        desired_frames_in_flight: Option<u32>,
+    ) -> Result<ContextProperties, EnvironmentError> {
+        Self::find_best_with_preference(
+            adapter,
+            surface,
+            &[],
+            desired_image_count,
+            desired_frames_in_flight,
+        )
+    }
+
+    /// Like [`Self::find_best`], but the colour format is the first of `preferred_formats` that the surface supports.
+    /// If it supports none of them, the colour format is picked as usual. Check `color_format` for the one actually used.
+    pub fn find_best_with_preference(
+        adapter: &Adapter,
+        surface: &SurfaceT,
+        preferred_formats: &[Format],
+        desired_image_count: Option<u32>,
+        desired_frames_in_flight: Option<u32>,
+    ) -> Result<ContextProperties, EnvironmentError> {
+        Self::find_best_inner(
+            adapter,
+            surface,
+            preferred_formats,
+            desired_image_count,
+            desired_frames_in_flight,
+            false,
+        )
+    }
+
+    /// Like [`Self::find_best_with_preference`], but falls back to properties that might not look or perform as well instead of failing.
+    /// This is meant for minimal or virtual GPUs, such as software renderers used in CI, and shouldn't be needed on real hardware.
+    ///
+    /// If the surface has no SRGB formats, a UNORM format is used instead. Draw passes that output linear colour then need to apply gamma themselves, see [`Self::needs_gamma_correction`].
+    /// If the surface reports none of the present modes we know, FIFO is used, as every Vulkan implementation has to support it.
+    pub fn find_best_relaxed(
+        adapter: &Adapter,
+        surface: &SurfaceT,
+        preferred_formats: &[Format],
+        desired_image_count: Option<u32>,
+        desired_frames_in_flight: Option<u32>,
+    ) -> Result<ContextProperties, EnvironmentError> {
+        Self::find_best_inner(
+            adapter,
+            surface,
+            preferred_formats,
+            desired_image_count,
+            desired_frames_in_flight,
+            true,
+        )
+    }
+
+    fn find_best_inner(
+        adapter: &Adapter,
+        surface: &SurfaceT,
+        preferred_formats: &[Format],
+        desired_image_count: Option<u32>,
+        desired_frames_in_flight: Option<u32>,
+        relaxed: bool,
     ) -> Result<ContextProperties, EnvironmentError> {
         let caps = surface.capabilities(&adapter.physical_device);
         let formats = surface.supported_formats(&adapter.physical_device);
 
-        // Use the first SRGB format our surface prefers
-        let color_format = match formats {
-            Some(formats) => formats
+        // Use the first of the caller's preferences the surface supports
+        let preferred = preferred_formats
+            .iter()
+            .find(|format| match &formats {
+                Some(formats) => formats.contains(*format),
+                None => adapter
+                    .physical_device
+                    .format_properties(Some(**format))
+                    .optimal_tiling
+                    .contains(ImageFeature::COLOR_ATTACHMENT),
+            })
+            .copied();
+
+        // Otherwise, use the first SRGB format our surface prefers
+        let color_format = match (preferred, formats) {
+            (Some(format), _) => Ok(format),
+            (None, Some(formats)) => formats
                 .iter()
                 .find(|format| format.base_format().1 == ChannelType::Srgb)
+                .or_else(|| {
+                    // Or a UNORM one, if we're allowed
+                    if !relaxed {
+                        return None;
+                    }
+                    let format = formats
+                        .iter()
+                        .find(|format| format.base_format().1 == ChannelType::Unorm)?;
+                    warn!(
+                        "No SRGB surface formats, falling back to {:?}. Gamma needs to be applied in shaders.",
+                        format
+                    );
+
+                    Some(format)
+                })
                 .copied()
                 .ok_or(EnvironmentError::ColorFormat),
-            None => Ok(Format::Rgba8Srgb),
+            (None, None) => Ok(Format::Rgba8Srgb),
         }?;
 
-        // Use the most preferable format our adapter prefers.
-        let depth_format = *[
+        // Use the most preferable format our adapter prefers, ideally one that can also be sampled.
+        let depth_formats = [
             Format::D32SfloatS8Uint,
             Format::D24UnormS8Uint,
             Format::D32Sfloat,
-        ]
-        .iter()
-        .find(|format| {
+        ];
+        let supports_depth = |format: &Format, features: ImageFeature| {
             format.is_depth()
                 && adapter
                     .physical_device
-                    .format_properties(Some(**format))
+                    .format_properties(Some(*format))
                     .optimal_tiling
-                    .contains(ImageFeature::DEPTH_STENCIL_ATTACHMENT)
-        })
-        .ok_or(EnvironmentError::DepthFormat)?;
+                    .contains(features)
+        };
+        let sampleable_features = ImageFeature::DEPTH_STENCIL_ATTACHMENT | ImageFeature::SAMPLED;
+        let (depth_format, depth_sampleable) = match depth_formats
+            .iter()
+            .find(|format| supports_depth(format, sampleable_features))
+        {
+            Some(format) => (*format, true),
+            None => (
+                *depth_formats
+                    .iter()
+                    .find(|format| supports_depth(format, ImageFeature::DEPTH_STENCIL_ATTACHMENT))
+                    .ok_or(EnvironmentError::DepthFormat)?,
+                false,
+            ),
+        };
 
         // V-Sync if possible
         let present_mode = [
@@ -516,6 +1223,15 @@ impl ContextProperties {
         .iter()
         .cloned()
         .find(|pm| caps.present_modes.contains(*pm))
+        .or_else(|| {
+            // FIFO is always supported, even if the surface doesn't say so
+            if !relaxed {
+                return None;
+            }
+            warn!("No known present modes reported, falling back to FIFO");
+
+            Some(PresentMode::FIFO)
+        })
         .ok_or(EnvironmentError::PresentMode)?;
 
         // Prefer opaque
@@ -536,25 +1252,42 @@ impl ContextProperties {
             depth: 0.0..1.0,
         };
 
+        let image_count = match desired_image_count {
+            Some(count) => count.clamp(*caps.image_count.start(), *caps.image_count.end()),
+            None if present_mode == PresentMode::MAILBOX => {
+                ((*caps.image_count.end()) - 1).min((*caps.image_count.start()).max(3))
+            }
+            None => ((*caps.image_count.end()) - 1).min((*caps.image_count.start()).max(2)),
+        };
+        let frames_in_flight = match desired_frames_in_flight {
+            Some(count) => count.clamp(1, image_count),
+            None => image_count,
+        };
+
         Ok(ContextProperties {
             color_format,
+            color_usage: Usage::COLOR_ATTACHMENT,
             depth_format,
+            depth_sampleable,
             present_mode,
             composite_alpha_mode,
             extent,
             viewport,
-            image_count: if present_mode == PresentMode::MAILBOX {
-                ((*caps.image_count.end()) - 1).min((*caps.image_count.start()).max(3))
-            } else {
-                ((*caps.image_count.end()) - 1).min((*caps.image_count.start()).max(2))
-            },
+            image_count,
+            frames_in_flight,
         })
     }
 
+    /// Check if the colour format doesn't do gamma correction, meaning draw passes that output linear colour need to apply it themselves.
+    /// This can only be true if the properties were found with [`Self::find_best_relaxed`], or a UNORM format was preferred.
+    pub fn needs_gamma_correction(&self) -> bool {
+        self.color_format.base_format().1 != ChannelType::Srgb
+    }
+
     /// Get the framebuffer attachment to use for swapchain images
     pub fn swapchain_framebuffer_attachment(&self) -> FramebufferAttachment {
         FramebufferAttachment {
-            usage: Usage::COLOR_ATTACHMENT,
+            usage: self.color_usage,
             format: self.color_format,
             view_caps: ViewCapabilities::empty(),
         }
@@ -0,0 +1,200 @@
+//! A draw pass that just clears the image.
+
+use std::{array::IntoIter, marker::PhantomData, mem::ManuallyDrop};
+
+use super::{DrawPass, FrameContext, IntoDrawPass, PassPosition, Singular};
+use crate::{
+    builders::{AttachmentSpec, RenderpassSpec},
+    context::RenderingContext,
+    draw_passes::util::{begin_render_pass, end_render_pass, TargetSpecificResources},
+    queue_negotiator::QueueFamilyNegotiator,
+    session::Session,
+    types::*,
+};
+
+use anyhow::{Context, Result};
+use hal::{
+    command::{ClearColor, ClearValue, RenderAttachmentInfo, SubpassContents},
+    image::Layout,
+    pass::{Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp},
+    pso::Rect,
+};
+
+/// Config for a [`ClearPass`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearPassConfig {
+    /// The colour to clear to, as RGBA.
+    pub color: [f32; 4],
+}
+
+impl Default for ClearPassConfig {
+    fn default() -> Self {
+        ClearPassConfig {
+            color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+/// Clears the image to a single colour, and leaves it in the right layout for `P`.
+/// This is useful to get a renderer running before writing any draw passes of your own.
+pub struct ClearPass<P: PassPosition> {
+    /// The colour to clear to, or None to keep what's already there.
+    clear_color: Option<[f32; 4]>,
+    renderpass: ManuallyDrop<RenderPassT>,
+    framebuffers: TargetSpecificResources<FramebufferT>,
+    render_area: Rect,
+    _d: PhantomData<P>,
+}
+
+impl ClearPass<Singular> {
+    /// Get the config for a clear pass with the given colour, which can be passed straight to [`crate::Renderer::new`].
+    pub fn color(color: [f32; 4]) -> ClearPassConfig {
+        ClearPassConfig { color }
+    }
+}
+
+impl<P: PassPosition> ClearPass<P> {
+    /// Create a pass that doesn't clear, and only moves the image between the layouts given by `P`.
+    /// This doesn't need a session, so it's useful to pad out a [`super::ConsDrawPass`] or in tests.
+    pub fn transition_only(context: &mut RenderingContext) -> Result<Self> {
+        Self::new(context, None)
+    }
+
+    /// Create a new pass, clearing to the given colour if there is one.
+    /// Unlike going through [`ClearPassConfig`], this doesn't need a session.
+    pub fn new(context: &mut RenderingContext, clear_color: Option<[f32; 4]>) -> Result<Self> {
+        let ops = match clear_color {
+            Some(_) => AttachmentOps::new(AttachmentLoadOp::Clear, AttachmentStoreOp::Store),
+            None => P::attachment_ops(),
+        };
+        let spec = RenderpassSpec {
+            colors: vec![AttachmentSpec {
+                attachment: Attachment {
+                    format: Some(context.properties().color_format),
+                    samples: 1,
+                    ops,
+                    stencil_ops: AttachmentOps::DONT_CARE,
+                    layouts: P::layout_as_range(),
+                },
+                used_layout: Layout::ColorAttachmentOptimal,
+            }],
+            depth: None,
+            inputs: vec![],
+            resolves: vec![],
+            preserves: vec![],
+        };
+
+        let mut device = context.lock_device()?;
+        let renderpass = spec
+            .build_renderpass(&mut device)
+            .context("Error building clear renderpass")?;
+
+        let fat = context.properties().swapchain_framebuffer_attachment();
+        let framebuffers = TargetSpecificResources::new(
+            || unsafe {
+                Ok(device.create_framebuffer(
+                    &renderpass,
+                    IntoIter::new([fat.clone()]),
+                    context.properties().extent,
+                )?)
+            },
+            context.frames_in_flight(),
+        );
+        let framebuffers = match framebuffers {
+            Ok(f) => f,
+            Err(e) => {
+                unsafe { device.destroy_render_pass(renderpass) };
+                return Err(e);
+            }
+        };
+
+        Ok(ClearPass {
+            clear_color,
+            renderpass: ManuallyDrop::new(renderpass),
+            framebuffers,
+            render_area: context.properties().extent.rect(),
+            _d: PhantomData,
+        })
+    }
+
+    /// Get the colour being cleared to, if any.
+    pub fn clear_color(&self) -> Option<[f32; 4]> {
+        self.clear_color
+    }
+
+    /// Change the colour being cleared to. This takes effect from the next frame.
+    /// This won't do anything if the pass was created without a clear colour.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        if self.clear_color.is_some() {
+            self.clear_color = Some(color);
+        }
+    }
+}
+
+impl<P: PassPosition> DrawPass<P> for ClearPass<P> {
+    fn queue_draw(
+        &mut self,
+        _session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        _frame: FrameContext,
+    ) -> Result<()> {
+        let fb = self.framebuffers.get_next();
+        unsafe {
+            begin_render_pass(
+                cmd_buffer,
+                &self.renderpass,
+                fb,
+                self.render_area,
+                IntoIter::new([RenderAttachmentInfo {
+                    image_view: img_view,
+                    clear_value: ClearValue {
+                        color: ClearColor {
+                            float32: self.clear_color.unwrap_or([0.0, 0.0, 0.0, 1.0]),
+                        },
+                    },
+                }]),
+                SubpassContents::Inline,
+            );
+            end_render_pass(cmd_buffer);
+        }
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        self,
+        _session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        let clear_color = self.clear_color;
+        DrawPass::<P>::deactivate(self, context)?;
+
+        Self::new(context, clear_color)
+    }
+
+    fn deactivate(mut self, context: &mut RenderingContext) -> Result<()> {
+        let device = context.lock_device()?;
+        unsafe {
+            for fb in self.framebuffers.dissolve() {
+                device.destroy_framebuffer(fb);
+            }
+            device.destroy_render_pass(ManuallyDrop::take(&mut self.renderpass));
+        }
+
+        Ok(())
+    }
+}
+
+impl<P: PassPosition> IntoDrawPass<ClearPass<P>, P> for ClearPassConfig {
+    fn init(self, _session: &mut Session, context: &mut RenderingContext) -> Result<ClearPass<P>> {
+        ClearPass::new(context, Some(self.color))
+    }
+
+    fn find_aux_queues(
+        _adapter: &Adapter,
+        _queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
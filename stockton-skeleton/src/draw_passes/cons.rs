@@ -1,12 +1,16 @@
 //! Code for using multiple draw passes in place of just one
 //! Note that this can be extended to an arbitrary amount of draw passes.
 
-use super::{Beginning, DrawPass, End, IntoDrawPass, Middle, Singular};
+use super::{
+    util::queue_draw_checked, Beginning, DrawPass, End, FrameContext, IntoDrawPass, Middle,
+    Singular,
+};
 use crate::{
     context::RenderingContext, queue_negotiator::QueueFamilyNegotiator, session::Session, types::*,
 };
 
 use anyhow::Result;
+use hal::Features;
 
 /// One draw pass, then another.
 pub struct ConsDrawPass<A, B> {
@@ -21,12 +25,24 @@ macro_rules! cons_shared_impl {
             session: &Session,
             img_view: &ImageViewT,
             cmd_buffer: &mut CommandBufferT,
+            frame: FrameContext,
         ) -> Result<()> {
-            self.a.queue_draw(session, img_view, cmd_buffer)?;
-            self.b.queue_draw(session, img_view, cmd_buffer)?;
+            let a = &mut self.a;
+            queue_draw_checked(a.name(), || {
+                a.queue_draw(session, img_view, cmd_buffer, frame)
+            })?;
+
+            let b = &mut self.b;
+            queue_draw_checked(b.name(), || {
+                b.queue_draw(session, img_view, cmd_buffer, frame)
+            })?;
 
             Ok(())
         }
+        fn frame_cancelled(&mut self, frame: FrameContext) {
+            self.a.frame_cancelled(frame);
+            self.b.frame_cancelled(frame);
+        }
         fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
             self.a.deactivate(context)?;
             self.b.deactivate(context)
@@ -94,6 +110,10 @@ macro_rules! into_shared_impl {
             IB::find_aux_queues(adapter, queue_negotiator)?;
             Ok(())
         }
+
+        fn required_features() -> Features {
+            IA::required_features() | IB::required_features()
+        }
     };
 }
 
@@ -0,0 +1,23 @@
+#version 450
+
+layout (push_constant) uniform PushConsts {
+	mat4 view_proj;
+	float point_size;
+} push;
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec4 color;
+
+layout (location = 0) out vec4 frag_color;
+
+out gl_PerVertex {
+	vec4 gl_Position;
+	float gl_PointSize;
+};
+
+void main()
+{
+	frag_color = color;
+	gl_PointSize = push.point_size;
+	gl_Position = push.view_proj * vec4(position, 1.0);
+}
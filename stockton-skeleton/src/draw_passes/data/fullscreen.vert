@@ -0,0 +1,14 @@
+#version 450
+
+layout (location = 0) out vec2 uv;
+
+out gl_PerVertex {
+	vec4 gl_Position;
+};
+
+// Draws a single triangle covering the whole viewport, given 3 vertices and no vertex buffers
+void main()
+{
+	uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+	gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 450
+
+// The font is always the first texture in the first block, see TextureRepo
+layout (set = 0, binding = 0) uniform texture2D tex[8];
+layout (set = 0, binding = 1) uniform sampler samp[8];
+
+layout (location = 0) in vec2 frag_uv;
+layout (location = 1) in vec4 frag_color;
+
+layout (location = 0) out vec4 color;
+
+void main()
+{
+	color = frag_color * texture(sampler2D(tex[0], samp[0]), frag_uv);
+}
@@ -0,0 +1,20 @@
+#version 450
+
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec2 uv;
+layout (location = 2) in vec4 color;
+
+layout (location = 0) out vec2 frag_uv;
+layout (location = 1) out vec4 frag_color;
+
+out gl_PerVertex {
+	vec4 gl_Position;
+};
+
+// Positions are already in clip space, see TextPass::write_queued
+void main()
+{
+	frag_uv = uv;
+	frag_color = color;
+	gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,437 @@
+//! A pass for drawing coloured lines and points over the scene, for visualising things like normals and paths.
+
+use std::{
+    array::IntoIter,
+    iter::{empty, once},
+};
+
+use super::{
+    util::{begin_render_pass, end_render_pass, SurfaceResources, TargetSpecificResources},
+    DrawPass, FrameContext, IntoDrawPass, PassPosition,
+};
+use crate::{
+    buffers::staged::StagedBuffer,
+    builders::{
+        AttachmentSpec, BlendMode, CompletePipeline, PipelineSpecBuilder, RenderpassSpec,
+        ShaderDesc, ShaderKind, VertexLayout, VertexPrimitiveAssemblerSpec,
+    },
+    context::RenderingContext,
+    error::EnvironmentError,
+    mem::{DataPool, StagingPool},
+    queue_negotiator::QueueFamilyNegotiator,
+    session::Session,
+    types::*,
+};
+
+use anyhow::{Context, Result};
+use hal::{
+    buffer::{SubRange, Usage},
+    command::{ClearColor, ClearValue, RenderAttachmentInfo, SubpassContents},
+    format::Format,
+    image::Layout,
+    pass::Attachment,
+    pso::{
+        DepthStencilDesc, Face, FrontFace, InputAssemblerDesc, PolygonMode, Primitive, Rasterizer,
+        ShaderStageFlags, State, VertexInputRate,
+    },
+    Features,
+};
+
+/// The most vertices that can be drawn in one frame. Each line takes two, and each point takes one.
+/// Lines are written first, and anything past this is left out.
+pub const MAX_DEBUG_VERTICES: u64 = 8192;
+
+/// Offset of the point size in the push constants, after the view-projection matrix
+const POINT_SIZE_OFFSET: u32 = 64;
+
+/// The vertices given to the debug shader (world-space position, colour)
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct DebugVertex(Vector3, [f32; 4]);
+
+impl VertexLayout for DebugVertex {
+    fn attributes() -> Vec<Format> {
+        vec![Format::Rgb32Sfloat, Format::Rgba32Sfloat]
+    }
+}
+
+/// Config for a [`DebugDrawPass`]
+pub struct DebugDrawPassConfig {
+    /// The view-projection matrix of the camera to draw from. This can be changed later with [`DebugDrawPass::set_view_proj`].
+    pub view_proj: Matrix4,
+
+    /// The width of lines, in pixels.
+    /// Anything other than 1.0 needs [`Features::LINE_WIDTH`], see [`RenderingContext::enabled_features`].
+    pub line_width: f32,
+
+    /// The size of points, in pixels.
+    /// Anything other than 1.0 needs [`Features::POINT_SIZE`], see [`RenderingContext::enabled_features`].
+    pub point_size: f32,
+}
+
+impl Default for DebugDrawPassConfig {
+    fn default() -> Self {
+        DebugDrawPassConfig {
+            view_proj: Matrix4::identity(),
+            line_width: 1.0,
+            point_size: 1.0,
+        }
+    }
+}
+
+/// Draws coloured lines and points in world space, alpha blended on top of whatever is already in the image.
+/// Lines and points are added with [`DebugDrawPass::add_line`] and [`DebugDrawPass::add_point`] each frame, and cleared once they're drawn.
+/// This doesn't test against depth, so everything is drawn over the scene.
+pub struct DebugDrawPass<'a> {
+    /// One vertex buffer for each frame in flight
+    vertex_buffers: Vec<StagedBuffer<'a, DebugVertex, DataPool, StagingPool>>,
+    surface_resources: SurfaceDependentResources,
+    view_proj: Matrix4,
+    line_width: f32,
+    point_size: f32,
+
+    /// Queued lines, as pairs of vertices
+    lines: Vec<DebugVertex>,
+    points: Vec<DebugVertex>,
+}
+
+impl<'a> DebugDrawPass<'a> {
+    /// Queue a line from `a` to `b` to be drawn on the next frame.
+    pub fn add_line(&mut self, a: Vector3, b: Vector3, color: [f32; 4]) {
+        self.lines.push(DebugVertex(a, color));
+        self.lines.push(DebugVertex(b, color));
+    }
+
+    /// Queue a point at `p` to be drawn on the next frame.
+    pub fn add_point(&mut self, p: Vector3, color: [f32; 4]) {
+        self.points.push(DebugVertex(p, color));
+    }
+
+    /// Get the view-projection matrix lines and points are drawn with.
+    pub fn view_proj(&self) -> Matrix4 {
+        self.view_proj
+    }
+
+    /// Set the view-projection matrix, which will be used from the next frame onwards.
+    pub fn set_view_proj(&mut self, view_proj: Matrix4) {
+        self.view_proj = view_proj;
+    }
+
+    /// Write all of the queued lines and points into the vertex buffer for the given frame, clearing the queues.
+    /// Returns the number of line vertices and point vertices written.
+    fn write_queued(&mut self, frame: &FrameContext) -> (usize, usize) {
+        let idx = frame.frame_index % self.vertex_buffers.len();
+        let buffer = &mut self.vertex_buffers[idx];
+        let max = MAX_DEBUG_VERTICES as usize;
+
+        // Only whole lines
+        let line_verts = self.lines.len().min(max) & !1;
+        for (i, vert) in self.lines.drain(..).take(line_verts).enumerate() {
+            buffer[i] = vert;
+        }
+
+        let point_verts = self.points.len().min(max - line_verts);
+        for (i, vert) in self.points.drain(..).take(point_verts).enumerate() {
+            buffer[line_verts + i] = vert;
+        }
+
+        (line_verts, point_verts)
+    }
+}
+
+impl<'a, P: PassPosition> DrawPass<P> for DebugDrawPass<'a> {
+    fn queue_draw(
+        &mut self,
+        _session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        let (line_verts, point_verts) = self.write_queued(&frame);
+        if line_verts == 0 && point_verts == 0 {
+            return Ok(());
+        }
+
+        let buffer = &mut self.vertex_buffers[frame.frame_index % self.vertex_buffers.len()];
+        buffer.record_commit_cmds(cmd_buffer)?;
+
+        let res = &mut self.surface_resources;
+        unsafe {
+            begin_render_pass(
+                cmd_buffer,
+                &res.lines.renderpass,
+                res.framebuffers.get_next(),
+                frame.viewport,
+                once(RenderAttachmentInfo {
+                    image_view: img_view,
+                    clear_value: ClearValue {
+                        color: ClearColor {
+                            float32: [0.0, 0.0, 0.0, 1.0],
+                        },
+                    },
+                }),
+                SubpassContents::Inline,
+            );
+            cmd_buffer.bind_vertex_buffers(
+                0,
+                once((
+                    buffer.get_buffer(),
+                    SubRange {
+                        offset: 0,
+                        size: None,
+                    },
+                )),
+            );
+        }
+
+        // Both pipelines use compatible renderpasses, so can be used in the same one
+        for (pipeline, verts) in [
+            (&res.lines, 0..line_verts as u32),
+            (
+                &res.points,
+                line_verts as u32..(line_verts + point_verts) as u32,
+            ),
+        ] {
+            if verts.is_empty() {
+                continue;
+            }
+
+            unsafe {
+                cmd_buffer.bind_graphics_pipeline(&pipeline.pipeline);
+            }
+            pipeline.set_viewport(cmd_buffer, frame.viewport);
+            pipeline.push_constants(cmd_buffer, ShaderStageFlags::VERTEX, 0, &self.view_proj)?;
+            pipeline.push_constants(
+                cmd_buffer,
+                ShaderStageFlags::VERTEX,
+                POINT_SIZE_OFFSET,
+                &self.point_size,
+            )?;
+
+            unsafe {
+                cmd_buffer.draw(verts, 0..1);
+            }
+        }
+
+        unsafe {
+            end_render_pass(cmd_buffer);
+        }
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        mut self,
+        _session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        match self
+            .surface_resources
+            .recreate::<P>(context, &self.line_width)
+        {
+            Ok(_) => Ok(self),
+            Err(e) => {
+                <Self as DrawPass<P>>::deactivate(self, context)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        for buffer in self.vertex_buffers {
+            buffer.deactivate(context);
+        }
+        self.surface_resources.deactivate(context)
+    }
+}
+
+impl<'a, P: PassPosition> IntoDrawPass<DebugDrawPass<'a>, P> for DebugDrawPassConfig {
+    fn init(
+        self,
+        _session: &mut Session,
+        context: &mut RenderingContext,
+    ) -> Result<DebugDrawPass<'a>> {
+        let mut required = Features::empty();
+        if self.line_width != 1.0 {
+            required |= Features::LINE_WIDTH;
+        }
+        if self.point_size != 1.0 {
+            required |= Features::POINT_SIZE;
+        }
+        if !context.enabled_features().contains(required) {
+            return Err(EnvironmentError::MissingFeatures(required).into());
+        }
+
+        let mut vertex_buffers = Vec::with_capacity(context.frames_in_flight());
+        for _ in 0..context.frames_in_flight() {
+            match StagedBuffer::from_context(context, Usage::VERTEX, MAX_DEBUG_VERTICES)
+                .context("Error creating vertex buffer")
+            {
+                Ok(x) => vertex_buffers.push(x),
+                Err(e) => {
+                    for buffer in vertex_buffers {
+                        buffer.deactivate(context);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let surface_resources =
+            match SurfaceDependentResources::create::<P>(context, &self.line_width) {
+                Ok(x) => x,
+                Err(e) => {
+                    for buffer in vertex_buffers {
+                        buffer.deactivate(context);
+                    }
+                    return Err(e);
+                }
+            };
+
+        Ok(DebugDrawPass {
+            vertex_buffers,
+            surface_resources,
+            view_proj: self.view_proj,
+            line_width: self.line_width,
+            point_size: self.point_size,
+            lines: vec![],
+            points: vec![],
+        })
+    }
+
+    fn find_aux_queues(
+        _adapter: &Adapter,
+        _queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Everything that depends on the surface, so needs recreated when it changes.
+struct SurfaceDependentResources {
+    lines: CompletePipeline,
+    points: CompletePipeline,
+    framebuffers: TargetSpecificResources<FramebufferT>,
+}
+
+impl SurfaceResources for SurfaceDependentResources {
+    /// The line width
+    type Shared = f32;
+
+    fn create<P: PassPosition>(context: &mut RenderingContext, line_width: &f32) -> Result<Self> {
+        let props = context.properties().clone();
+
+        let build_spec = |primitive| {
+            PipelineSpecBuilder::default()
+                .rasterizer(Rasterizer {
+                    polygon_mode: PolygonMode::Fill,
+                    cull_face: Face::NONE,
+                    front_face: FrontFace::CounterClockwise,
+                    depth_clamping: false,
+                    depth_bias: None,
+                    conservative: false,
+                    line_width: State::Static(*line_width),
+                })
+                .depth_stencil(DepthStencilDesc {
+                    depth: None,
+                    depth_bounds: false,
+                    stencil: None,
+                })
+                .blender(BlendMode::AlphaBlend)
+                .primitive_assembler(VertexPrimitiveAssemblerSpec::with_buffers(
+                    InputAssemblerDesc::new(primitive),
+                    vec![DebugVertex::buffer_spec(VertexInputRate::Vertex)],
+                ))
+                .shader_vertex(ShaderDesc {
+                    source: include_str!("./data/debug.vert").to_string(),
+                    entry: "main".to_string(),
+                    kind: ShaderKind::Vertex,
+                })
+                .shader_fragment(ShaderDesc {
+                    source: include_str!("./data/debug.frag").to_string(),
+                    entry: "main".to_string(),
+                    kind: ShaderKind::Fragment,
+                })
+                .push_constants_of::<Matrix4>(ShaderStageFlags::VERTEX, 0)
+                .push_constants_of::<f32>(ShaderStageFlags::VERTEX, POINT_SIZE_OFFSET)
+                .dynamic_viewport(true)
+                .dynamic_scissor(true)
+                .renderpass(RenderpassSpec {
+                    colors: vec![AttachmentSpec {
+                        attachment: Attachment {
+                            format: Some(props.color_format),
+                            samples: 1,
+                            ops: P::attachment_ops(),
+                            stencil_ops: P::attachment_ops(),
+                            layouts: P::layout_as_range(),
+                        },
+                        used_layout: Layout::ColorAttachmentOptimal,
+                    }],
+                    depth: None,
+                    inputs: vec![],
+                    resolves: vec![],
+                    preserves: vec![],
+                })
+                .build()
+                .context("Error building debug draw pipeline")
+        };
+        let lines_spec = build_spec(Primitive::LineList)?;
+        let points_spec = build_spec(Primitive::PointList)?;
+
+        let mut device = context.lock_device()?;
+        let limits = &context.physical_device_properties().limits;
+        let lines = lines_spec
+            .build(&mut device, props.extent, limits, empty())
+            .context("Error building debug line pipeline")?;
+        let points = match points_spec
+            .build(&mut device, props.extent, limits, empty())
+            .context("Error building debug point pipeline")
+        {
+            Ok(p) => p,
+            Err(e) => {
+                lines.deactivate(&mut device);
+                return Err(e);
+            }
+        };
+
+        let fat = props.swapchain_framebuffer_attachment();
+        let framebuffers = TargetSpecificResources::new(
+            || unsafe {
+                Ok(device.create_framebuffer(
+                    &lines.renderpass,
+                    IntoIter::new([fat.clone()]),
+                    props.extent,
+                )?)
+            },
+            context.frames_in_flight(),
+        );
+        let framebuffers = match framebuffers {
+            Ok(f) => f,
+            Err(e) => {
+                lines.deactivate(&mut device);
+                points.deactivate(&mut device);
+                return Err(e);
+            }
+        };
+
+        Ok(SurfaceDependentResources {
+            lines,
+            points,
+            framebuffers,
+        })
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        unsafe {
+            let mut device = context.lock_device()?;
+            for fb in self.framebuffers.dissolve() {
+                device.destroy_framebuffer(fb);
+            }
+
+            self.lines.deactivate(&mut device);
+            self.points.deactivate(&mut device);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,638 @@
+//! Draw passes that say which images they use, and have the layout transitions between them worked out automatically.
+//!
+//! Instead of picking a [`super::PassPosition`] for each pass, every pass in a [`RenderGraph`] is a [`DrawPass<Middle>`] that lists the images it reads and writes with [`GraphPass::uses`].
+//! The graph clears the swapchain image before the first pass and gets it ready for presenting after the last, like [`super::DrawPassList`].
+//! Before each pass, it records a pipeline barrier moving every other image it uses into the layout given by its [`ResourceUsage`].
+//! Renderpasses for graph passes should start and end each image in that layout, since the graph takes care of moving between them.
+//! [`super::ConsDrawPass`] can still be used to pick positions by hand where more control is needed.
+
+use super::{
+    clear::ClearPass,
+    list::{deactivate_all, BoxedDrawPass},
+    util::queue_draw_checked,
+    Beginning, DrawPass, End, FrameContext, IntoDrawPass, Middle, Singular,
+};
+use crate::{
+    buffers::image::{COLOR_RESOURCES, DEPTH_RESOURCES},
+    context::RenderingContext,
+    error::UsageError,
+    queue_negotiator::QueueFamilyNegotiator,
+    session::Session,
+    types::*,
+};
+
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+use hal::{
+    image::{Access, Layout},
+    memory::{Barrier, Dependencies},
+    pso::PipelineStage,
+};
+
+/// The name of the image being drawn to, which every pass in a graph draws to as a colour attachment.
+/// It doesn't need to be listed in [`GraphPass::uses`].
+pub const SWAPCHAIN: &str = "swapchain";
+
+/// How a pass uses an image, which decides the layout it needs to be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceUsage {
+    ColorAttachment,
+    DepthAttachment,
+
+    /// Sampled or read from a shader, in either the vertex or fragment stage
+    ShaderRead,
+
+    TransferSrc,
+    TransferDst,
+}
+
+impl ResourceUsage {
+    /// The layout the image needs to be in.
+    pub fn layout(&self) -> Layout {
+        match self {
+            ResourceUsage::ColorAttachment => Layout::ColorAttachmentOptimal,
+            ResourceUsage::DepthAttachment => Layout::DepthStencilAttachmentOptimal,
+            ResourceUsage::ShaderRead => Layout::ShaderReadOnlyOptimal,
+            ResourceUsage::TransferSrc => Layout::TransferSrcOptimal,
+            ResourceUsage::TransferDst => Layout::TransferDstOptimal,
+        }
+    }
+
+    /// The ways the image might be accessed.
+    pub fn access(&self) -> Access {
+        match self {
+            ResourceUsage::ColorAttachment => {
+                Access::COLOR_ATTACHMENT_READ | Access::COLOR_ATTACHMENT_WRITE
+            }
+            ResourceUsage::DepthAttachment => {
+                Access::DEPTH_STENCIL_ATTACHMENT_READ | Access::DEPTH_STENCIL_ATTACHMENT_WRITE
+            }
+            ResourceUsage::ShaderRead => Access::SHADER_READ,
+            ResourceUsage::TransferSrc => Access::TRANSFER_READ,
+            ResourceUsage::TransferDst => Access::TRANSFER_WRITE,
+        }
+    }
+
+    /// The pipeline stages the image might be accessed in.
+    pub fn stages(&self) -> PipelineStage {
+        match self {
+            ResourceUsage::ColorAttachment => PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+            ResourceUsage::DepthAttachment => {
+                PipelineStage::EARLY_FRAGMENT_TESTS | PipelineStage::LATE_FRAGMENT_TESTS
+            }
+            ResourceUsage::ShaderRead => {
+                PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER
+            }
+            ResourceUsage::TransferSrc | ResourceUsage::TransferDst => PipelineStage::TRANSFER,
+        }
+    }
+
+    /// Whether the image might be written to.
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            ResourceUsage::ColorAttachment
+                | ResourceUsage::DepthAttachment
+                | ResourceUsage::TransferDst
+        )
+    }
+}
+
+/// A change to an image's layout and access, needed before a pass can use it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// The name of the image
+    pub resource: &'static str,
+
+    /// How the image was last used this frame, or None if this is the first use.
+    pub from: Option<ResourceUsage>,
+
+    /// How the pass is going to use the image
+    pub to: ResourceUsage,
+}
+
+impl Transition {
+    /// The layout the image is in before the transition, and the layout it's in after.
+    /// Images start each frame in [`Layout::Undefined`], so their contents aren't kept between frames.
+    pub fn layouts(&self) -> Range<Layout> {
+        self.from.map_or(Layout::Undefined, |u| u.layout())..self.to.layout()
+    }
+
+    /// The image state to give a pipeline barrier.
+    fn states(&self) -> Range<(Access, Layout)> {
+        let from = match self.from {
+            Some(u) => (u.access(), u.layout()),
+            None => (Access::empty(), Layout::Undefined),
+        };
+
+        from..(self.to.access(), self.to.layout())
+    }
+
+    /// The stages to give a pipeline barrier.
+    fn stages(&self) -> Range<PipelineStage> {
+        self.from.map_or(PipelineStage::TOP_OF_PIPE, |u| u.stages())..self.to.stages()
+    }
+}
+
+/// Work out the transitions each pass needs before it's drawn, given the name of each pass and the images it uses, in the order they're drawn.
+/// Images other than [`SWAPCHAIN`] must be written by an earlier pass, or by the same pass, before they're read.
+/// Nothing is transitioned between two passes that only read an image in the same way.
+pub fn resolve_transitions(
+    passes: &[(&'static str, Vec<(&'static str, ResourceUsage)>)],
+) -> Result<Vec<Vec<Transition>>> {
+    let mut last_use: Vec<(&'static str, ResourceUsage)> = Vec::new();
+    let mut transitions = Vec::with_capacity(passes.len());
+    for &(pass, ref uses) in passes.iter() {
+        let mut seen: Vec<(&'static str, ResourceUsage)> = Vec::with_capacity(uses.len());
+        let mut pass_transitions = Vec::with_capacity(uses.len());
+        for &(resource, usage) in uses.iter() {
+            if resource == SWAPCHAIN {
+                if usage != ResourceUsage::ColorAttachment {
+                    return Err(UsageError::GraphSwapchainUsage { pass }.into());
+                }
+                continue;
+            }
+
+            match seen.iter().find(|(r, _)| *r == resource) {
+                Some((_, u)) if *u != usage => {
+                    return Err(UsageError::GraphConflictingUsage { pass, resource }.into())
+                }
+                Some(_) => continue,
+                None => seen.push((resource, usage)),
+            }
+
+            let from = match last_use.iter_mut().find(|(r, _)| *r == resource) {
+                Some((_, last)) => Some(std::mem::replace(last, usage)),
+                None if usage.is_write() => {
+                    last_use.push((resource, usage));
+                    None
+                }
+                None => return Err(UsageError::GraphReadBeforeWrite { pass, resource }.into()),
+            };
+
+            if from != Some(usage) || usage.is_write() {
+                pass_transitions.push(Transition {
+                    resource,
+                    from,
+                    to: usage,
+                });
+            }
+        }
+        transitions.push(pass_transitions);
+    }
+
+    Ok(transitions)
+}
+
+/// A draw pass that can be put in a [`RenderGraph`].
+/// As well as your own passes, this is implemented for [`super::ShadowMapPass`] and [`super::PostProcessPass`].
+pub trait GraphPass: DrawPass<Middle> {
+    /// The images this pass uses and how, other than drawing to the swapchain image.
+    /// This is checked when the graph is created, and again whenever the surface changes.
+    fn uses(&self) -> Vec<(&'static str, ResourceUsage)>;
+
+    /// Get the image with the given name to be used for the given frame, if this pass owns it.
+    /// Every image used in the graph needs to be owned by exactly one of its passes.
+    fn graph_image(&self, _name: &'static str, _frame: FrameContext) -> Option<&ImageT> {
+        None
+    }
+}
+
+/// A [`GraphPass`] that can be stored as a trait object.
+/// Drawing and deactivating go through [`BoxedDrawPass`], the same as in a [`super::DrawPassList`].
+trait BoxedGraphPass: BoxedDrawPass<Middle> {
+    fn uses(&self) -> Vec<(&'static str, ResourceUsage)>;
+    fn graph_image(&self, name: &'static str, frame: FrameContext) -> Option<&ImageT>;
+    fn handle_surface_change_graph(
+        self: Box<Self>,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Box<dyn BoxedGraphPass>>;
+}
+
+impl<T: GraphPass + 'static> BoxedGraphPass for T {
+    fn uses(&self) -> Vec<(&'static str, ResourceUsage)> {
+        GraphPass::uses(self)
+    }
+
+    fn graph_image(&self, name: &'static str, frame: FrameContext) -> Option<&ImageT> {
+        GraphPass::graph_image(self, name, frame)
+    }
+
+    fn handle_surface_change_graph(
+        self: Box<Self>,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Box<dyn BoxedGraphPass>> {
+        Ok(Box::new((*self).handle_surface_change(session, context)?))
+    }
+}
+
+/// An image used in the graph, and the index of the pass that owns it.
+struct GraphResource {
+    name: &'static str,
+    owner: usize,
+    is_depth: bool,
+}
+
+/// A list of passes, with the transitions between them worked out from the images they use.
+pub struct RenderGraph {
+    /// Clears the image and gets it ready for the passes in the graph
+    begin: ClearPass<Beginning>,
+
+    /// The passes in the graph, in the order they're drawn
+    passes: Vec<Box<dyn BoxedGraphPass>>,
+
+    /// Gets the image ready for presenting
+    end: ClearPass<End>,
+
+    /// The images used by passes, other than the swapchain image
+    resources: Vec<GraphResource>,
+
+    /// The transitions needed before each pass
+    transitions: Vec<Vec<Transition>>,
+}
+
+impl RenderGraph {
+    /// Work out the transitions between passes and who owns each image, from what each pass says it uses.
+    fn resolve(
+        passes: &[Box<dyn BoxedGraphPass>],
+        frame: FrameContext,
+    ) -> Result<(Vec<GraphResource>, Vec<Vec<Transition>>)> {
+        let uses: Vec<_> = passes.iter().map(|p| (p.name(), p.uses())).collect();
+        let transitions = resolve_transitions(&uses)?;
+
+        let mut resources: Vec<GraphResource> = Vec::new();
+        for &(resource, usage) in uses.iter().flat_map(|(_, u)| u.iter()) {
+            if resource == SWAPCHAIN {
+                continue;
+            }
+            let is_depth = usage == ResourceUsage::DepthAttachment;
+            if let Some(r) = resources.iter_mut().find(|r| r.name == resource) {
+                r.is_depth |= is_depth;
+                continue;
+            }
+
+            let owner = passes
+                .iter()
+                .position(|p| p.graph_image(resource, frame).is_some())
+                .ok_or(UsageError::GraphResourceMissing(resource))?;
+            resources.push(GraphResource {
+                name: resource,
+                owner,
+                is_depth,
+            });
+        }
+
+        Ok((resources, transitions))
+    }
+
+    /// Record the barriers needed before the pass at the given index.
+    fn record_transitions(
+        &self,
+        idx: usize,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        let transitions = &self.transitions[idx];
+        if transitions.is_empty() {
+            return Ok(());
+        }
+
+        let mut stages = PipelineStage::empty()..PipelineStage::empty();
+        let mut barriers = Vec::with_capacity(transitions.len());
+        for t in transitions.iter() {
+            let resource = self
+                .resources
+                .iter()
+                .find(|r| r.name == t.resource)
+                .ok_or(UsageError::GraphResourceMissing(t.resource))?;
+            let img = self.passes[resource.owner]
+                .graph_image(t.resource, frame)
+                .ok_or(UsageError::GraphResourceMissing(t.resource))?;
+
+            let s = t.stages();
+            stages.start |= s.start;
+            stages.end |= s.end;
+            barriers.push(Barrier::Image {
+                states: t.states(),
+                target: img,
+                families: None,
+                range: match resource.is_depth {
+                    true => DEPTH_RESOURCES,
+                    false => COLOR_RESOURCES,
+                },
+            });
+        }
+
+        unsafe {
+            cmd_buffer.pipeline_barrier(stages, Dependencies::empty(), barriers.into_iter());
+        }
+
+        Ok(())
+    }
+
+    /// The transitions recorded before each pass, in the order the passes are drawn.
+    pub fn transitions(&self) -> &[Vec<Transition>] {
+        &self.transitions
+    }
+
+    /// The number of passes in the graph.
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Check if there are no passes in the graph.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Set the colour the image is cleared to before any passes are drawn.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.begin.set_clear_color(color);
+    }
+}
+
+impl DrawPass<Singular> for RenderGraph {
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        self.begin
+            .queue_draw(session, img_view, cmd_buffer, frame)?;
+        for idx in 0..self.passes.len() {
+            self.record_transitions(idx, cmd_buffer, frame)?;
+
+            let pass = &mut self.passes[idx];
+            queue_draw_checked(pass.name(), || {
+                pass.queue_draw(session, img_view, cmd_buffer, frame)
+            })?;
+        }
+        self.end.queue_draw(session, img_view, cmd_buffer, frame)?;
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        self,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        let begin = self.begin.handle_surface_change(session, context);
+        let end = self.end.handle_surface_change(session, context);
+        let (begin, end) = match (begin, end) {
+            (Ok(begin), Ok(end)) => (begin, end),
+            (Ok(p), Err(e)) => {
+                let _ = p.deactivate(context);
+                deactivate_all(self.passes.into_iter(), context);
+                return Err(e);
+            }
+            (Err(e), Ok(p)) => {
+                let _ = p.deactivate(context);
+                deactivate_all(self.passes.into_iter(), context);
+                return Err(e);
+            }
+            (Err(e), Err(_)) => {
+                deactivate_all(self.passes.into_iter(), context);
+                return Err(e);
+            }
+        };
+
+        let mut passes = Vec::with_capacity(self.passes.len());
+        let mut old = self.passes.into_iter();
+        while let Some(pass) = old.next() {
+            match pass.handle_surface_change_graph(session, context) {
+                Ok(pass) => passes.push(pass),
+                Err(e) => {
+                    deactivate_all(passes.into_iter().chain(old), context);
+                    let _ = begin.deactivate(context);
+                    let _ = end.deactivate(context);
+                    return Err(e);
+                }
+            }
+        }
+
+        finish_graph(begin, passes, end, context)
+    }
+
+    fn frame_cancelled(&mut self, frame: FrameContext) {
+        for pass in self.passes.iter_mut() {
+            pass.frame_cancelled(frame);
+        }
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        self.begin.deactivate(context)?;
+        self.end.deactivate(context)?;
+        for pass in self.passes {
+            pass.deactivate_boxed(context)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve the graph's transitions and put it together, deactivating everything if that fails.
+fn finish_graph(
+    begin: ClearPass<Beginning>,
+    passes: Vec<Box<dyn BoxedGraphPass>>,
+    end: ClearPass<End>,
+    context: &mut RenderingContext,
+) -> Result<RenderGraph> {
+    let frame = FrameContext {
+        frame_index: 0,
+        frame_count: context.frames_in_flight(),
+        frame_number: context.frame_number(),
+        viewport: context.properties().extent.rect(),
+    };
+
+    match RenderGraph::resolve(&passes, frame).context("Error resolving render graph") {
+        Ok((resources, transitions)) => Ok(RenderGraph {
+            begin,
+            passes,
+            end,
+            resources,
+            transitions,
+        }),
+        Err(e) => {
+            deactivate_all(passes.into_iter(), context);
+            let _ = begin.deactivate(context);
+            let _ = end.deactivate(context);
+            Err(e)
+        }
+    }
+}
+
+/// A function that initialises a pass to be put in a [`RenderGraph`]
+type GraphPassInitialiser =
+    Box<dyn FnOnce(&mut Session, &mut RenderingContext) -> Result<Box<dyn BoxedGraphPass>>>;
+
+/// Config for a [`RenderGraph`], listing its passes in the order they're drawn.
+/// Note that the passes in the graph can't ask for auxiliary queues with [`IntoDrawPass::find_aux_queues`].
+pub struct RenderGraphConfig {
+    initialisers: Vec<GraphPassInitialiser>,
+    clear_color: [f32; 4],
+}
+
+impl Default for RenderGraphConfig {
+    fn default() -> Self {
+        RenderGraphConfig {
+            initialisers: vec![],
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl RenderGraphConfig {
+    /// Set the colour the image is cleared to before any passes are drawn. This defaults to black.
+    pub fn with_clear_color(mut self, color: [f32; 4]) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    /// Add a pass to the end of the graph.
+    pub fn with_pass<IDP, T>(mut self, idp: IDP) -> Self
+    where
+        IDP: IntoDrawPass<T, Middle> + 'static,
+        T: GraphPass + 'static,
+    {
+        self.initialisers.push(Box::new(move |session, context| {
+            Ok(Box::new(idp.init(session, context)?))
+        }));
+
+        self
+    }
+}
+
+impl IntoDrawPass<RenderGraph, Singular> for RenderGraphConfig {
+    fn init(self, session: &mut Session, context: &mut RenderingContext) -> Result<RenderGraph> {
+        let begin = ClearPass::new(context, Some(self.clear_color))?;
+        let end = match ClearPass::transition_only(context) {
+            Ok(p) => p,
+            Err(e) => {
+                let _ = begin.deactivate(context);
+                return Err(e);
+            }
+        };
+
+        let mut passes = Vec::with_capacity(self.initialisers.len());
+        for initialiser in self.initialisers {
+            match initialiser(session, context).context("Error initialising pass in graph") {
+                Ok(pass) => passes.push(pass),
+                Err(e) => {
+                    deactivate_all(passes.into_iter(), context);
+                    let _ = begin.deactivate(context);
+                    let _ = end.deactivate(context);
+                    return Err(e);
+                }
+            }
+        }
+
+        finish_graph(begin, passes, end, context)
+    }
+
+    fn find_aux_queues(
+        _adapter: &Adapter,
+        _queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage_error(passes: &[(&'static str, Vec<(&'static str, ResourceUsage)>)]) -> UsageError {
+        resolve_transitions(passes)
+            .unwrap_err()
+            .downcast::<UsageError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn transitions_between_passes() {
+        let transitions = resolve_transitions(&[
+            (
+                "shadow",
+                vec![("shadow_map", ResourceUsage::DepthAttachment)],
+            ),
+            (
+                "lit",
+                vec![
+                    (SWAPCHAIN, ResourceUsage::ColorAttachment),
+                    ("shadow_map", ResourceUsage::ShaderRead),
+                ],
+            ),
+            ("debug", vec![("shadow_map", ResourceUsage::ShaderRead)]),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            transitions,
+            vec![
+                vec![Transition {
+                    resource: "shadow_map",
+                    from: None,
+                    to: ResourceUsage::DepthAttachment,
+                }],
+                vec![Transition {
+                    resource: "shadow_map",
+                    from: Some(ResourceUsage::DepthAttachment),
+                    to: ResourceUsage::ShaderRead,
+                }],
+                vec![],
+            ]
+        );
+        assert_eq!(
+            transitions[0][0].layouts(),
+            Layout::Undefined..Layout::DepthStencilAttachmentOptimal
+        );
+    }
+
+    #[test]
+    fn swapchain_must_be_a_colour_attachment() {
+        let err = usage_error(&[("a", vec![(SWAPCHAIN, ResourceUsage::ShaderRead)])]);
+
+        assert!(matches!(err, UsageError::GraphSwapchainUsage { pass: "a" }));
+    }
+
+    #[test]
+    fn image_used_two_ways_in_one_pass() {
+        let err = usage_error(&[(
+            "a",
+            vec![
+                ("target", ResourceUsage::ColorAttachment),
+                ("target", ResourceUsage::ShaderRead),
+            ],
+        )]);
+
+        assert!(matches!(
+            err,
+            UsageError::GraphConflictingUsage {
+                pass: "a",
+                resource: "target"
+            }
+        ));
+    }
+
+    #[test]
+    fn image_read_before_written() {
+        let err = usage_error(&[
+            ("a", vec![("target", ResourceUsage::ShaderRead)]),
+            ("b", vec![("target", ResourceUsage::ColorAttachment)]),
+        ]);
+
+        assert!(matches!(
+            err,
+            UsageError::GraphReadBeforeWrite {
+                pass: "a",
+                resource: "target"
+            }
+        ));
+    }
+}
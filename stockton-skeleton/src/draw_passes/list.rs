@@ -0,0 +1,312 @@
+//! A list of draw passes that can be changed at runtime.
+//!
+//! Unlike [`super::ConsDrawPass`], the set of passes isn't part of the type, so passes can be added or toggled while running.
+//! Every pass in the list is a [`DrawPass<Middle>`], and the list itself takes care of clearing the image beforehand and transitioning it for presenting afterwards.
+
+use super::{
+    clear::ClearPass, util::queue_draw_checked, Beginning, DrawPass, End, FrameContext,
+    IntoDrawPass, Middle, PassPosition, Singular,
+};
+use crate::{
+    context::RenderingContext, queue_negotiator::QueueFamilyNegotiator, session::Session, types::*,
+};
+
+use anyhow::{Context, Result};
+
+/// A [`DrawPass`] that can be stored as a trait object.
+/// This is implemented for every sized draw pass, so you shouldn't need to implement it yourself.
+pub trait BoxedDrawPass<P: PassPosition> {
+    /// See [`DrawPass::queue_draw`]
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()>;
+
+    /// See [`DrawPass::handle_surface_change`]
+    fn handle_surface_change_boxed(
+        self: Box<Self>,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Box<dyn BoxedDrawPass<P>>>;
+
+    /// See [`DrawPass::deactivate`]
+    fn deactivate_boxed(self: Box<Self>, context: &mut RenderingContext) -> Result<()>;
+
+    /// See [`DrawPass::frame_cancelled`]
+    fn frame_cancelled(&mut self, frame: FrameContext);
+
+    /// See [`DrawPass::name`]
+    fn name(&self) -> &'static str;
+}
+
+impl<P: PassPosition, T: DrawPass<P> + 'static> BoxedDrawPass<P> for T {
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        DrawPass::queue_draw(self, session, img_view, cmd_buffer, frame)
+    }
+
+    fn handle_surface_change_boxed(
+        self: Box<Self>,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Box<dyn BoxedDrawPass<P>>> {
+        Ok(Box::new((*self).handle_surface_change(session, context)?))
+    }
+
+    fn deactivate_boxed(self: Box<Self>, context: &mut RenderingContext) -> Result<()> {
+        (*self).deactivate(context)
+    }
+
+    fn frame_cancelled(&mut self, frame: FrameContext) {
+        DrawPass::frame_cancelled(self, frame)
+    }
+
+    fn name(&self) -> &'static str {
+        DrawPass::name(self)
+    }
+}
+
+/// A pass in a [`DrawPassList`], and whether it's currently being drawn.
+struct ListEntry {
+    pass: Box<dyn BoxedDrawPass<Middle>>,
+    enabled: bool,
+}
+
+/// A list of draw passes, which can be added to or toggled at runtime.
+/// Passes are drawn in the order they were added.
+pub struct DrawPassList {
+    /// Clears the image and gets it ready for the passes in the list
+    begin: ClearPass<Beginning>,
+
+    /// The passes in the list
+    passes: Vec<ListEntry>,
+
+    /// Gets the image ready for presenting
+    end: ClearPass<End>,
+}
+
+impl DrawPassList {
+    /// Add an already initialised pass to the end of the list, returning its index.
+    /// New passes start off enabled.
+    pub fn push<T: DrawPass<Middle> + 'static>(&mut self, pass: T) -> usize {
+        self.passes.push(ListEntry {
+            pass: Box::new(pass),
+            enabled: true,
+        });
+
+        self.passes.len() - 1
+    }
+
+    /// Remove the pass at the given index and deactivate it.
+    /// This will shift the indices of any passes after it.
+    pub fn remove(&mut self, idx: usize, context: &mut RenderingContext) -> Result<()> {
+        self.passes.remove(idx).pass.deactivate_boxed(context)
+    }
+
+    /// Set whether the pass at the given index should be drawn.
+    /// Disabled passes still have their surface changes handled, so they can be turned back on at any time.
+    pub fn set_enabled(&mut self, idx: usize, enabled: bool) {
+        self.passes[idx].enabled = enabled;
+    }
+
+    /// Check if the pass at the given index is being drawn.
+    pub fn is_enabled(&self, idx: usize) -> bool {
+        self.passes[idx].enabled
+    }
+
+    /// The number of passes in the list, including disabled ones.
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Check if there are no passes in the list.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Set the colour the image is cleared to before any passes are drawn.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.begin.set_clear_color(color);
+    }
+}
+
+/// Deactivate all of the given passes, ignoring any errors as we're most likely already handling one.
+/// This is shared with [`super::RenderGraph`], whose boxed passes are also [`BoxedDrawPass`]es.
+pub(super) fn deactivate_all<P: PassPosition, T: BoxedDrawPass<P> + ?Sized>(
+    passes: impl Iterator<Item = Box<T>>,
+    context: &mut RenderingContext,
+) {
+    for pass in passes {
+        let _ = pass.deactivate_boxed(context);
+    }
+}
+
+impl DrawPass<Singular> for DrawPassList {
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        self.begin
+            .queue_draw(session, img_view, cmd_buffer, frame)?;
+        for entry in self.passes.iter_mut().filter(|e| e.enabled) {
+            let pass = &mut entry.pass;
+            queue_draw_checked(pass.name(), || {
+                pass.queue_draw(session, img_view, cmd_buffer, frame)
+            })?;
+        }
+        self.end.queue_draw(session, img_view, cmd_buffer, frame)?;
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        self,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        let begin = self.begin.handle_surface_change(session, context);
+        let end = self.end.handle_surface_change(session, context);
+        let (begin, end) = match (begin, end) {
+            (Ok(begin), Ok(end)) => (begin, end),
+            (Ok(p), Err(e)) => {
+                let _ = p.deactivate(context);
+                deactivate_all(self.passes.into_iter().map(|e| e.pass), context);
+                return Err(e);
+            }
+            (Err(e), Ok(p)) => {
+                let _ = p.deactivate(context);
+                deactivate_all(self.passes.into_iter().map(|e| e.pass), context);
+                return Err(e);
+            }
+            (Err(e), Err(_)) => {
+                deactivate_all(self.passes.into_iter().map(|e| e.pass), context);
+                return Err(e);
+            }
+        };
+
+        let mut passes = Vec::with_capacity(self.passes.len());
+        let mut old = self.passes.into_iter();
+        while let Some(entry) = old.next() {
+            match entry.pass.handle_surface_change_boxed(session, context) {
+                Ok(pass) => passes.push(ListEntry {
+                    pass,
+                    enabled: entry.enabled,
+                }),
+                Err(e) => {
+                    deactivate_all(passes.into_iter().chain(old).map(|e| e.pass), context);
+                    let _ = begin.deactivate(context);
+                    let _ = end.deactivate(context);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(DrawPassList { begin, passes, end })
+    }
+
+    fn frame_cancelled(&mut self, frame: FrameContext) {
+        for entry in self.passes.iter_mut() {
+            entry.pass.frame_cancelled(frame);
+        }
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        self.begin.deactivate(context)?;
+        self.end.deactivate(context)?;
+        for entry in self.passes {
+            entry.pass.deactivate_boxed(context)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A function that initialises a pass to be put in a [`DrawPassList`]
+type PassInitialiser =
+    Box<dyn FnOnce(&mut Session, &mut RenderingContext) -> Result<Box<dyn BoxedDrawPass<Middle>>>>;
+
+/// Config for a [`DrawPassList`], listing the passes it should start off with.
+/// Note that the passes in the list can't ask for auxiliary queues with [`IntoDrawPass::find_aux_queues`].
+pub struct DrawPassListConfig {
+    initialisers: Vec<PassInitialiser>,
+    clear_color: [f32; 4],
+}
+
+impl Default for DrawPassListConfig {
+    fn default() -> Self {
+        DrawPassListConfig {
+            initialisers: vec![],
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl DrawPassListConfig {
+    /// Set the colour the image is cleared to before any passes are drawn. This defaults to black.
+    pub fn with_clear_color(mut self, color: [f32; 4]) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    /// Add a pass to the end of the list.
+    pub fn with_pass<IDP, T>(mut self, idp: IDP) -> Self
+    where
+        IDP: IntoDrawPass<T, Middle> + 'static,
+        T: DrawPass<Middle> + 'static,
+    {
+        self.initialisers.push(Box::new(move |session, context| {
+            Ok(Box::new(idp.init(session, context)?))
+        }));
+
+        self
+    }
+}
+
+impl IntoDrawPass<DrawPassList, Singular> for DrawPassListConfig {
+    fn init(self, session: &mut Session, context: &mut RenderingContext) -> Result<DrawPassList> {
+        let begin = ClearPass::new(context, Some(self.clear_color))?;
+        let end = match ClearPass::transition_only(context) {
+            Ok(p) => p,
+            Err(e) => {
+                let _ = begin.deactivate(context);
+                return Err(e);
+            }
+        };
+
+        let mut passes = Vec::with_capacity(self.initialisers.len());
+        for initialiser in self.initialisers {
+            match initialiser(session, context).context("Error initialising pass in list") {
+                Ok(pass) => passes.push(ListEntry {
+                    pass,
+                    enabled: true,
+                }),
+                Err(e) => {
+                    deactivate_all(passes.into_iter().map(|e| e.pass), context);
+                    let _ = begin.deactivate(context);
+                    let _ = end.deactivate(context);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(DrawPassList { begin, passes, end })
+    }
+
+    fn find_aux_queues(
+        _adapter: &Adapter,
+        _queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 //! Traits and common draw passes.
-use std::ops::Range;
+use std::{any::type_name, ops::Range};
 
 use crate::{
     context::RenderingContext, queue_negotiator::QueueFamilyNegotiator, session::Session, types::*,
@@ -7,29 +7,85 @@ use crate::{
 use hal::{
     image::Layout,
     pass::{AttachmentLoadOp, AttachmentOps, AttachmentStoreOp},
+    pso::Rect,
+    Features,
 };
 
 use anyhow::Result;
 
+mod clear;
 mod cons;
+mod debug;
+mod graph;
+mod list;
+mod post;
+mod shadow;
+mod split;
+#[cfg(feature = "text")]
+mod text;
 pub mod util;
 
+pub use clear::{ClearPass, ClearPassConfig};
 pub use cons::ConsDrawPass;
+pub use debug::{DebugDrawPass, DebugDrawPassConfig, MAX_DEBUG_VERTICES};
+pub use graph::{
+    resolve_transitions, GraphPass, RenderGraph, RenderGraphConfig, ResourceUsage, Transition,
+    SWAPCHAIN,
+};
+pub use list::{BoxedDrawPass, DrawPassList, DrawPassListConfig};
+pub use post::{PostProcessPass, PostProcessPassConfig};
+pub use shadow::{ShadowCasters, ShadowMapPass, ShadowMapPassConfig, DEFAULT_SHADOW_DEPTH_BIAS};
+pub use split::{SplitScreenPass, SplitScreenPassConfig, ViewportRegion};
+#[cfg(feature = "text")]
+pub use text::{GlyphGrid, TextPass, TextPassConfig, TextSampling, MAX_GLYPHS};
+
+/// Information about the frame currently being drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameContext {
+    /// The index of the frame currently being drawn, in the range `0..frame_count`.
+    /// This can be used to pick which per-frame resources (eg uniform buffers) to use.
+    pub frame_index: usize,
+
+    /// The number of frames that can be in flight at once.
+    pub frame_count: usize,
+
+    /// The number of frames drawn before this one. This goes up by one every frame, and isn't reset when the surface changes,
+    /// so can be used to drive temporal effects such as jittering the projection for anti-aliasing.
+    pub frame_number: u64,
+
+    /// The region of the image this pass should draw into.
+    /// This is the whole image, unless the pass is inside a [`SplitScreenPass`].
+    pub viewport: Rect,
+}
+
+impl FrameContext {
+    /// Get the aspect ratio of the region being drawn into, for use in projection matrices.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.viewport.w as f32 / self.viewport.h as f32
+    }
+}
 
 /// One of several 'passes' that draw on each frame.
 pub trait DrawPass<P: PassPosition> {
     /// Queue any necessary draw commands to cmd_buffer
     /// This should assume the command buffer isn't in the middle of a renderpass, and should leave it as such.
+    /// Renderpasses should be begun and ended with [`util::begin_render_pass`] and [`util::end_render_pass`], so debug builds can check this.
+    /// Only renderpasses begun that way are checked, so ones begun on `cmd_buffer` directly can be left open without anything noticing.
+    /// `frame` says which of the frames in flight is being drawn, and should be used to pick any per-frame resources.
+    /// Passes should only draw inside `frame.viewport`, for example by using a dynamic viewport & scissor.
     fn queue_draw(
         &mut self,
         session: &Session,
         img_view: &ImageViewT,
         cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
     ) -> Result<()>;
 
     /// Called just after the surface changes (probably a resize).
     /// This takes ownership and returns itself to ensure that the `DrawPass` is not called again if it fails.
     /// This means you should deactivate as much as possible in case of an error.
+    /// Anything not rebuilt here, such as texture repos or frame counters, is kept as the pass is returned.
+    /// Passes with resources that depend on the surface can keep them in one [`util::SurfaceResources`] field, so this only needs to recreate that.
     fn handle_surface_change(
         self,
         session: &Session,
@@ -40,6 +96,17 @@ pub trait DrawPass<P: PassPosition> {
 
     /// Deactivate any vulkan parts that need to be deactivated
     fn deactivate(self, context: &mut RenderingContext) -> Result<()>;
+
+    /// Called when a frame this pass was asked to draw won't be submitted, for example because recording it failed or the surface is being recreated.
+    /// Nothing recorded for `frame` will run, so anything that was only being uploaded by those commands, such as changes committed from a [`crate::buffers::staged::StagedBuffer`], needs to be uploaded again.
+    /// This defaults to doing nothing, which is fine for passes that write all of their data every frame.
+    fn frame_cancelled(&mut self, _frame: FrameContext) {}
+
+    /// A name for this pass, used to say which pass was being drawn in logs.
+    /// This defaults to the name of the type.
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
 }
 
 /// A type that can be made into a specific draw pass type.
@@ -53,6 +120,13 @@ pub trait IntoDrawPass<T: DrawPass<P>, P: PassPosition> {
         adapter: &Adapter,
         queue_negotiator: &mut QueueFamilyNegotiator,
     ) -> Result<()>;
+
+    /// The device features this draw pass can't work without, which are enabled when the context is created.
+    /// If the device doesn't support all of them, creating the context fails with [`crate::error::EnvironmentError::MissingFeatures`].
+    /// Features that are only sometimes needed should be checked in `init` with [`RenderingContext::enabled_features`] instead.
+    fn required_features() -> Features {
+        Features::empty()
+    }
 }
 
 /// Used so that draw passes can determine what state shared resources are in and how they should be left.
@@ -0,0 +1,537 @@
+//! A pass that runs a fragment shader over the output of another pass, for post-processing effects like tone-mapping.
+
+use std::{
+    array::IntoIter,
+    iter::{empty, once},
+};
+
+use super::{
+    util::{begin_render_pass, end_render_pass, queue_draw_checked, TargetSpecificResources},
+    DrawPass, FrameContext, GraphPass, IntoDrawPass, Middle, PassPosition, ResourceUsage,
+};
+use crate::{
+    buffers::image::{BoundImageView, ImageSpec, COLOR_RESOURCES},
+    builders::{
+        AttachmentSpec, BlendMode, CompletePipeline, PipelineSpecBuilder, RenderpassSpec,
+        ShaderDesc, ShaderKind, VertexPrimitiveAssemblerSpec,
+    },
+    context::{ContextProperties, RenderingContext},
+    mem::RenderTargetPool,
+    queue_negotiator::QueueFamilyNegotiator,
+    session::Session,
+    types::*,
+};
+
+use anyhow::{Context, Result};
+use hal::{
+    command::{ClearColor, ClearValue, RenderAttachmentInfo, SubpassContents},
+    format::Swizzle,
+    image::{Access, Filter, Layout, SamplerDesc, Usage, ViewKind, WrapMode},
+    memory::{Barrier, Dependencies},
+    pass::Attachment,
+    pso::{
+        DepthStencilDesc, Descriptor, DescriptorSetLayoutBinding, DescriptorSetWrite,
+        DescriptorType, Face, FrontFace, ImageDescriptorType, InputAssemblerDesc, PipelineStage,
+        PolygonMode, Primitive, Rasterizer, ShaderStageFlags, State,
+    },
+    Features,
+};
+use rendy_descriptor::DescriptorRanges;
+
+/// The bindings of the descriptor set given to the post-processing shader
+const BINDINGS: [DescriptorSetLayoutBinding; 2] = [
+    DescriptorSetLayoutBinding {
+        binding: 0,
+        ty: DescriptorType::Image {
+            ty: ImageDescriptorType::Sampled {
+                with_sampler: false,
+            },
+        },
+        count: 1,
+        stage_flags: ShaderStageFlags::FRAGMENT,
+        immutable_samplers: false,
+    },
+    DescriptorSetLayoutBinding {
+        binding: 1,
+        ty: DescriptorType::Sampler,
+        count: 1,
+        stage_flags: ShaderStageFlags::FRAGMENT,
+        immutable_samplers: false,
+    },
+];
+
+/// Config for a [`PostProcessPass`]
+pub struct PostProcessPassConfig<I> {
+    /// The config for the pass whose output will be processed
+    pub inner: I,
+
+    /// The fragment shader to run over every pixel.
+    /// The inner pass' output is given as `layout(set = 0, binding = 0) uniform texture2D`, with a sampler at `layout(set = 0, binding = 1) uniform sampler`.
+    /// The texture coordinates to sample at are given as `layout(location = 0) in vec2`.
+    pub shader: ShaderDesc,
+}
+
+/// Draws the inner pass to an offscreen image, then runs a fragment shader sampling that image over the whole viewport.
+/// The offscreen image is cleared to transparent black each frame, and the framework takes care of moving it from
+/// `ColorAttachmentOptimal` to `ShaderReadOnlyOptimal` between the two.
+/// The shader's output is alpha blended onto whatever is already in the image.
+pub struct PostProcessPass<T> {
+    inner: T,
+    shader: ShaderDesc,
+    resources: PostProcessResources,
+}
+
+impl<T> PostProcessPass<T> {
+    /// Get a mutable reference to the pass whose output is being processed.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: DrawPass<Middle>> DrawPass<Middle> for PostProcessPass<T> {
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        let res = &mut self.resources;
+        let idx = frame.frame_index % res.targets.len();
+        let target = &res.targets[idx];
+
+        // Clear the offscreen image and get it ready to be drawn to
+        unsafe {
+            cmd_buffer.pipeline_barrier(
+                PipelineStage::TOP_OF_PIPE..PipelineStage::TRANSFER,
+                Dependencies::empty(),
+                once(Barrier::Image {
+                    states: (Access::empty(), Layout::Undefined)
+                        ..(Access::TRANSFER_WRITE, Layout::TransferDstOptimal),
+                    target: target.image.img(),
+                    families: None,
+                    range: COLOR_RESOURCES,
+                }),
+            );
+            cmd_buffer.clear_image(
+                target.image.img(),
+                Layout::TransferDstOptimal,
+                ClearValue {
+                    color: ClearColor {
+                        float32: [0.0, 0.0, 0.0, 0.0],
+                    },
+                },
+                once(COLOR_RESOURCES),
+            );
+            cmd_buffer.pipeline_barrier(
+                PipelineStage::TRANSFER..PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+                Dependencies::empty(),
+                once(Barrier::Image {
+                    states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+                        ..(
+                            Access::COLOR_ATTACHMENT_READ | Access::COLOR_ATTACHMENT_WRITE,
+                            Layout::ColorAttachmentOptimal,
+                        ),
+                    target: target.image.img(),
+                    families: None,
+                    range: COLOR_RESOURCES,
+                }),
+            );
+        }
+
+        let inner = &mut self.inner;
+        queue_draw_checked(inner.name(), || {
+            inner.queue_draw(session, &target.attachment_view, cmd_buffer, frame)
+        })?;
+
+        // Wait for the inner pass to finish, then get the image ready to be sampled
+        unsafe {
+            cmd_buffer.pipeline_barrier(
+                PipelineStage::COLOR_ATTACHMENT_OUTPUT..PipelineStage::FRAGMENT_SHADER,
+                Dependencies::empty(),
+                once(Barrier::Image {
+                    states: (
+                        Access::COLOR_ATTACHMENT_WRITE,
+                        Layout::ColorAttachmentOptimal,
+                    )
+                        ..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+                    target: target.image.img(),
+                    families: None,
+                    range: COLOR_RESOURCES,
+                }),
+            );
+
+            begin_render_pass(
+                cmd_buffer,
+                &res.pipeline.renderpass,
+                res.framebuffers.get_next(),
+                frame.viewport,
+                once(RenderAttachmentInfo {
+                    image_view: img_view,
+                    clear_value: ClearValue {
+                        color: ClearColor {
+                            float32: [0.0, 0.0, 0.0, 1.0],
+                        },
+                    },
+                }),
+                SubpassContents::Inline,
+            );
+            cmd_buffer.bind_graphics_pipeline(&res.pipeline.pipeline);
+            res.pipeline.set_viewport(cmd_buffer, frame.viewport);
+            cmd_buffer.bind_graphics_descriptor_sets(
+                &res.pipeline.pipeline_layout,
+                0,
+                once(res.descriptor_sets[idx].raw()),
+                empty(),
+            );
+
+            // A single triangle covering the viewport, see fullscreen.vert
+            cmd_buffer.draw(0..3, 0..1);
+
+            end_render_pass(cmd_buffer);
+        }
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        self,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        self.resources.deactivate(context);
+
+        let inner = self.inner.handle_surface_change(session, context)?;
+        let resources = match PostProcessResources::new(context, &self.shader) {
+            Ok(r) => r,
+            Err(e) => {
+                let _ = inner.deactivate(context);
+                return Err(e);
+            }
+        };
+
+        Ok(PostProcessPass {
+            inner,
+            shader: self.shader,
+            resources,
+        })
+    }
+
+    fn frame_cancelled(&mut self, frame: FrameContext) {
+        self.inner.frame_cancelled(frame);
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        self.resources.deactivate(context);
+
+        self.inner.deactivate(context)
+    }
+}
+
+/// The inner pass draws to the offscreen image instead of the swapchain image, but uses any other images in the graph the same way.
+impl<T: GraphPass> GraphPass for PostProcessPass<T> {
+    fn uses(&self) -> Vec<(&'static str, ResourceUsage)> {
+        self.inner.uses()
+    }
+
+    fn graph_image(&self, name: &'static str, frame: FrameContext) -> Option<&ImageT> {
+        self.inner.graph_image(name, frame)
+    }
+}
+
+impl<I, T> IntoDrawPass<PostProcessPass<T>, Middle> for PostProcessPassConfig<I>
+where
+    I: IntoDrawPass<T, Middle>,
+    T: DrawPass<Middle>,
+{
+    fn init(
+        self,
+        session: &mut Session,
+        context: &mut RenderingContext,
+    ) -> Result<PostProcessPass<T>> {
+        let inner = self.inner.init(session, context)?;
+        let resources = match PostProcessResources::new(context, &self.shader) {
+            Ok(r) => r,
+            Err(e) => {
+                let _ = inner.deactivate(context);
+                return Err(e);
+            }
+        };
+
+        Ok(PostProcessPass {
+            inner,
+            shader: self.shader,
+            resources,
+        })
+    }
+
+    fn find_aux_queues(
+        adapter: &Adapter,
+        queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        I::find_aux_queues(adapter, queue_negotiator)
+    }
+
+    fn required_features() -> Features {
+        I::required_features()
+    }
+}
+
+/// An offscreen image the inner pass draws to.
+struct OffscreenTarget {
+    /// The image, with a view that can be sampled from
+    image: BoundImageView<RenderTargetPool>,
+
+    /// A view of the image that's compatible with [`crate::context::ContextProperties::swapchain_framebuffer_attachment`],
+    /// so the inner pass can use it exactly like a swapchain image.
+    attachment_view: ImageViewT,
+}
+
+/// Everything that depends on the surface, so needs recreated when it changes.
+struct PostProcessResources {
+    pipeline: CompletePipeline,
+    framebuffers: TargetSpecificResources<FramebufferT>,
+    ds_layout: DescriptorSetLayoutT,
+    sampler: SamplerT,
+    descriptor_allocator: DescriptorAllocator,
+    descriptor_sets: Vec<RDescriptorSet>,
+    targets: Vec<OffscreenTarget>,
+}
+
+impl PostProcessResources {
+    fn new(context: &mut RenderingContext, shader: &ShaderDesc) -> Result<Self> {
+        let props = context.properties().clone();
+
+        let pipeline_spec = PipelineSpecBuilder::default()
+            .rasterizer(Rasterizer {
+                polygon_mode: PolygonMode::Fill,
+                cull_face: Face::NONE,
+                front_face: FrontFace::CounterClockwise,
+                depth_clamping: false,
+                depth_bias: None,
+                conservative: false,
+                line_width: State::Static(1.0),
+            })
+            .depth_stencil(DepthStencilDesc {
+                depth: None,
+                depth_bounds: false,
+                stencil: None,
+            })
+            .blender(BlendMode::AlphaBlend)
+            .primitive_assembler(VertexPrimitiveAssemblerSpec::with_buffers(
+                InputAssemblerDesc::new(Primitive::TriangleList),
+                vec![],
+            ))
+            .shader_vertex(ShaderDesc {
+                source: include_str!("./data/fullscreen.vert").to_string(),
+                entry: "main".to_string(),
+                kind: ShaderKind::Vertex,
+            })
+            .shader_fragment(shader.clone())
+            .dynamic_viewport(true)
+            .dynamic_scissor(true)
+            .renderpass(RenderpassSpec {
+                colors: vec![AttachmentSpec {
+                    attachment: Attachment {
+                        format: Some(props.color_format),
+                        samples: 1,
+                        ops: Middle::attachment_ops(),
+                        stencil_ops: Middle::attachment_ops(),
+                        layouts: Middle::layout_as_range(),
+                    },
+                    used_layout: Layout::ColorAttachmentOptimal,
+                }],
+                depth: None,
+                inputs: vec![],
+                resolves: vec![],
+                preserves: vec![],
+            })
+            .build()
+            .context("Error building post-processing pipeline")?;
+
+        let (pipeline, framebuffers, ds_layout, sampler) = {
+            let mut device = context.lock_device()?;
+
+            let ds_layout =
+                unsafe { device.create_descriptor_set_layout(IntoIter::new(BINDINGS), empty()) }
+                    .context("Error creating descriptor set layout")?;
+
+            let sampler = match unsafe {
+                device.create_sampler(&SamplerDesc::new(Filter::Linear, WrapMode::Clamp))
+            } {
+                Ok(s) => s,
+                Err(e) => {
+                    unsafe { device.destroy_descriptor_set_layout(ds_layout) };
+                    return Err(e).context("Error creating sampler");
+                }
+            };
+
+            let pipeline = match pipeline_spec.build(
+                &mut device,
+                props.extent,
+                &context.physical_device_properties().limits,
+                once(&ds_layout),
+            ) {
+                Ok(p) => p,
+                Err(e) => {
+                    unsafe {
+                        device.destroy_sampler(sampler);
+                        device.destroy_descriptor_set_layout(ds_layout);
+                    }
+                    return Err(e.context("Error building post-processing pipeline"));
+                }
+            };
+
+            let fat = props.swapchain_framebuffer_attachment();
+            let framebuffers = TargetSpecificResources::new(
+                || unsafe {
+                    Ok(device.create_framebuffer(
+                        &pipeline.renderpass,
+                        IntoIter::new([fat.clone()]),
+                        props.extent,
+                    )?)
+                },
+                props.frames_in_flight as usize,
+            );
+            let framebuffers = match framebuffers {
+                Ok(f) => f,
+                Err(e) => {
+                    unsafe {
+                        pipeline.deactivate(&mut device);
+                        device.destroy_sampler(sampler);
+                        device.destroy_descriptor_set_layout(ds_layout);
+                    }
+                    return Err(e);
+                }
+            };
+
+            (pipeline, framebuffers, ds_layout, sampler)
+        };
+
+        let mut this = PostProcessResources {
+            pipeline,
+            framebuffers,
+            ds_layout,
+            sampler,
+            descriptor_allocator: DescriptorAllocator::new(),
+            descriptor_sets: vec![],
+            targets: vec![],
+        };
+
+        match this.create_targets(context, &props) {
+            Ok(()) => Ok(this),
+            Err(e) => {
+                this.deactivate(context);
+                Err(e)
+            }
+        }
+    }
+
+    /// Create an offscreen image and descriptor set for each frame in flight.
+    fn create_targets(
+        &mut self,
+        context: &mut RenderingContext,
+        props: &ContextProperties,
+    ) -> Result<()> {
+        let images = context
+            .create_target_images::<RenderTargetPool>(
+                &ImageSpec {
+                    width: props.extent.width,
+                    height: props.extent.height,
+                    format: props.color_format,
+                    usage: Usage::COLOR_ATTACHMENT | Usage::SAMPLED | Usage::TRANSFER_DST,
+                    resources: COLOR_RESOURCES,
+                },
+                props.frames_in_flight as usize,
+            )
+            .context("Error creating offscreen images")?;
+
+        let mut device = context.lock_device()?;
+        let mut images = images.dissolve();
+        while let Some(image) = images.next() {
+            let attachment_view = unsafe {
+                device.create_image_view(
+                    image.img(),
+                    ViewKind::D2,
+                    props.color_format,
+                    Swizzle::NO,
+                    Usage::COLOR_ATTACHMENT,
+                    COLOR_RESOURCES,
+                )
+            };
+            match attachment_view {
+                Ok(attachment_view) => self.targets.push(OffscreenTarget {
+                    image,
+                    attachment_view,
+                }),
+                Err(e) => {
+                    drop(device);
+                    for image in once(image).chain(images) {
+                        image.deactivate_with_context(context);
+                    }
+                    return Err(e).context("Error creating offscreen image view");
+                }
+            }
+        }
+
+        unsafe {
+            self.descriptor_allocator
+                .allocate(
+                    &device,
+                    &self.ds_layout,
+                    DescriptorRanges::from_bindings(&BINDINGS),
+                    props.frames_in_flight,
+                    &mut self.descriptor_sets,
+                )
+                .context("Error creating descriptor sets")?;
+
+            for (set, target) in self.descriptor_sets.iter_mut().zip(self.targets.iter()) {
+                device.write_descriptor_set(DescriptorSetWrite {
+                    set: set.raw_mut(),
+                    binding: 0,
+                    array_offset: 0,
+                    descriptors: once(Descriptor::Image(
+                        target.image.img_view(),
+                        Layout::ShaderReadOnlyOptimal,
+                    )),
+                });
+                device.write_descriptor_set(DescriptorSetWrite {
+                    set: set.raw_mut(),
+                    binding: 1,
+                    array_offset: 0,
+                    descriptors: once(Descriptor::Sampler(&self.sampler)),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) {
+        {
+            let mut device = context.lock_device().unwrap();
+            unsafe {
+                let mut descriptor_allocator = self.descriptor_allocator;
+                descriptor_allocator.free(self.descriptor_sets.into_iter());
+                descriptor_allocator.dispose(&device);
+
+                for fb in self.framebuffers.dissolve() {
+                    device.destroy_framebuffer(fb);
+                }
+                self.pipeline.deactivate(&mut device);
+                device.destroy_sampler(self.sampler);
+                device.destroy_descriptor_set_layout(self.ds_layout);
+            }
+        }
+
+        for target in self.targets {
+            unsafe {
+                context
+                    .lock_device()
+                    .unwrap()
+                    .destroy_image_view(target.attachment_view);
+            }
+            target.image.deactivate_with_context(context);
+        }
+    }
+}
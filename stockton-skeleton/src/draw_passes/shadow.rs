@@ -0,0 +1,338 @@
+//! A depth-only pass that renders a shadow map from a light's point of view.
+
+use std::iter::{empty, once};
+
+use super::{
+    util::{begin_render_pass, end_render_pass},
+    DrawPass, FrameContext, GraphPass, IntoDrawPass, Middle, ResourceUsage,
+};
+use crate::{
+    buffers::image::{BoundImageView, ImageSpec, DEPTH_RESOURCES},
+    builders::{
+        AttachmentSpec, CompletePipeline, PipelineSpecBuilder, RenderpassSpec, ShaderDesc,
+        ShaderKind, VertexLayout, VertexPrimitiveAssemblerSpec,
+    },
+    context::RenderingContext,
+    error::EnvironmentError,
+    mem::DepthBufferPool,
+    queue_negotiator::QueueFamilyNegotiator,
+    session::Session,
+    types::*,
+};
+
+use anyhow::{Context, Result};
+use hal::{
+    command::{ClearDepthStencil, ClearValue, RenderAttachmentInfo, SubpassContents},
+    image::{Extent, FramebufferAttachment, Layout, Usage, ViewCapabilities},
+    pass::{Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp},
+    pso::{
+        BlendDesc, Comparison, DepthBias, DepthStencilDesc, DepthTest, Face, FrontFace,
+        InputAssemblerDesc, PolygonMode, Primitive, Rasterizer, ShaderStageFlags, State,
+        VertexInputRate,
+    },
+    Features,
+};
+
+/// The depth bias used by [`ShadowMapPassConfig`]s by default, which avoids shadow acne in most scenes.
+pub const DEFAULT_SHADOW_DEPTH_BIAS: DepthBias = DepthBias {
+    const_factor: 1.25,
+    clamp: 0.0,
+    slope_factor: 1.75,
+};
+
+/// Geometry that should be drawn into a shadow map.
+pub trait ShadowCasters {
+    /// The vertex type used. The first attribute must be the vertex's world-space position, as 3 floats.
+    type Vertex: VertexLayout;
+
+    /// Record the commands to bind vertex & index buffers and draw everything that should cast shadows.
+    /// This is called inside the shadow map's renderpass, with its pipeline already bound.
+    fn record_draws(&mut self, session: &Session, cmd_buffer: &mut CommandBufferT) -> Result<()>;
+
+    /// Deactivate any vulkan parts that need to be deactivated
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()>;
+}
+
+/// Config for a [`ShadowMapPass`]
+pub struct ShadowMapPassConfig<G> {
+    /// The geometry to draw
+    pub casters: G,
+
+    /// The width and height of the shadow map, in pixels
+    pub size: u32,
+
+    /// The light's view-projection matrix
+    pub light_vp: Matrix4,
+
+    /// The bias added to each depth value, to avoid shadow acne. This is usually [`DEFAULT_SHADOW_DEPTH_BIAS`].
+    pub depth_bias: Option<DepthBias>,
+
+    /// Whether to clamp depth values instead of clipping geometry outside the light's near and far planes,
+    /// so casters between the light and its near plane still cast shadows.
+    /// This needs [`Features::DEPTH_CLAMP`], see [`RenderingContext::enabled_features`].
+    pub depth_clamping: bool,
+
+    /// The name to give the shadow map when the pass is in a [`super::RenderGraph`], so later passes can read it with [`ResourceUsage::ShaderRead`].
+    /// When this is set, the shadow map is left in `DepthStencilAttachmentOptimal` for the graph to move on, instead of `ShaderReadOnlyOptimal`.
+    pub graph_name: Option<&'static str>,
+}
+
+/// Renders the depth of [`ShadowCasters`] from a light's point of view into its own depth image.
+/// The resulting image is left in `ShaderReadOnlyOptimal`, so a later pass can sample it using [`Self::depth_view`] and [`Self::light_vp`].
+/// This doesn't touch the swapchain image, so should always be in the middle of a list of passes.
+/// In a [`super::RenderGraph`], the image is named by [`ShadowMapPassConfig::graph_name`], and the graph moves it between layouts instead.
+pub struct ShadowMapPass<G: ShadowCasters> {
+    casters: G,
+    graph_name: Option<&'static str>,
+    light_vp: Matrix4,
+    pipeline: CompletePipeline,
+    depth_image: BoundImageView<DepthBufferPool>,
+    framebuffer: FramebufferT,
+}
+
+impl<G: ShadowCasters> ShadowMapPass<G> {
+    /// Get the view of the shadow map's depth image.
+    pub fn depth_view(&self) -> &ImageViewT {
+        self.depth_image.img_view()
+    }
+
+    /// Get the light's view-projection matrix.
+    pub fn light_vp(&self) -> Matrix4 {
+        self.light_vp
+    }
+
+    /// Set the light's view-projection matrix, which will be used from the next frame onwards.
+    pub fn set_light_vp(&mut self, light_vp: Matrix4) {
+        self.light_vp = light_vp;
+    }
+
+    /// Get a mutable reference to the geometry being drawn.
+    pub fn casters_mut(&mut self) -> &mut G {
+        &mut self.casters
+    }
+}
+
+impl<G: ShadowCasters> DrawPass<Middle> for ShadowMapPass<G> {
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        _img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        _frame: FrameContext,
+    ) -> Result<()> {
+        unsafe {
+            begin_render_pass(
+                cmd_buffer,
+                &self.pipeline.renderpass,
+                &self.framebuffer,
+                self.pipeline.render_area,
+                once(RenderAttachmentInfo {
+                    image_view: self.depth_image.img_view(),
+                    clear_value: ClearValue {
+                        depth_stencil: ClearDepthStencil {
+                            depth: 1.0,
+                            stencil: 0,
+                        },
+                    },
+                }),
+                SubpassContents::Inline,
+            );
+            cmd_buffer.bind_graphics_pipeline(&self.pipeline.pipeline);
+        }
+
+        self.pipeline
+            .push_constants(cmd_buffer, ShaderStageFlags::VERTEX, 0, &self.light_vp)?;
+        self.casters.record_draws(session, cmd_buffer)?;
+
+        unsafe {
+            end_render_pass(cmd_buffer);
+        }
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        self,
+        _session: &Session,
+        _context: &mut RenderingContext,
+    ) -> Result<Self> {
+        // The shadow map doesn't depend on the surface at all
+        Ok(self)
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        unsafe {
+            let mut device = context.lock_device()?;
+            device.destroy_framebuffer(self.framebuffer);
+            self.pipeline.deactivate(&mut device);
+        }
+        self.depth_image.deactivate_with_context(context);
+
+        self.casters.deactivate(context)
+    }
+}
+
+impl<G: ShadowCasters> GraphPass for ShadowMapPass<G> {
+    fn uses(&self) -> Vec<(&'static str, ResourceUsage)> {
+        self.graph_name
+            .map(|name| vec![(name, ResourceUsage::DepthAttachment)])
+            .unwrap_or_default()
+    }
+
+    fn graph_image(&self, name: &'static str, _frame: FrameContext) -> Option<&ImageT> {
+        match self.graph_name {
+            Some(n) if n == name => Some(self.depth_image.img()),
+            _ => None,
+        }
+    }
+}
+
+impl<G: ShadowCasters> IntoDrawPass<ShadowMapPass<G>, Middle> for ShadowMapPassConfig<G> {
+    fn init(
+        self,
+        _session: &mut Session,
+        context: &mut RenderingContext,
+    ) -> Result<ShadowMapPass<G>> {
+        if self.depth_clamping && !context.enabled_features().contains(Features::DEPTH_CLAMP) {
+            return Err(EnvironmentError::MissingFeatures(Features::DEPTH_CLAMP).into());
+        }
+        if !context.properties().depth_sampleable {
+            return Err(EnvironmentError::SampledDepthFormat.into());
+        }
+
+        let depth_format = context.properties().depth_format;
+        let depth_usage = Usage::DEPTH_STENCIL_ATTACHMENT | Usage::SAMPLED;
+        let extent = Extent {
+            width: self.size,
+            height: self.size,
+            depth: 1,
+        };
+
+        // Only a depth attachment, which is left ready to be sampled by later passes, unless a render graph is going to do that
+        let final_layout = match self.graph_name {
+            Some(_) => Layout::DepthStencilAttachmentOptimal,
+            None => Layout::ShaderReadOnlyOptimal,
+        };
+        let renderpass = RenderpassSpec {
+            colors: vec![],
+            depth: Some(AttachmentSpec {
+                attachment: Attachment {
+                    format: Some(depth_format),
+                    samples: 1,
+                    ops: AttachmentOps::new(AttachmentLoadOp::Clear, AttachmentStoreOp::Store),
+                    stencil_ops: AttachmentOps::DONT_CARE,
+                    layouts: Layout::Undefined..final_layout,
+                },
+                used_layout: Layout::DepthStencilAttachmentOptimal,
+            }),
+            inputs: vec![],
+            resolves: vec![],
+            preserves: vec![],
+        };
+
+        let pipeline_spec = PipelineSpecBuilder::default()
+            .rasterizer(Rasterizer {
+                polygon_mode: PolygonMode::Fill,
+                cull_face: Face::BACK,
+                front_face: FrontFace::CounterClockwise,
+                depth_clamping: self.depth_clamping,
+                depth_bias: self.depth_bias.map(State::Static),
+                conservative: false,
+                line_width: State::Static(1.0),
+            })
+            .depth_stencil(DepthStencilDesc {
+                depth: Some(DepthTest {
+                    fun: Comparison::Less,
+                    write: true,
+                }),
+                depth_bounds: false,
+                stencil: None,
+            })
+            // There are no colour attachments to blend
+            .blender(BlendDesc {
+                logic_op: None,
+                targets: vec![],
+            })
+            .primitive_assembler(VertexPrimitiveAssemblerSpec::with_buffers(
+                InputAssemblerDesc::new(Primitive::TriangleList),
+                vec![G::Vertex::buffer_spec(VertexInputRate::Vertex)],
+            ))
+            .shader_vertex(ShaderDesc {
+                source: include_str!("./data/shadow.vert").to_string(),
+                entry: "main".to_string(),
+                kind: ShaderKind::Vertex,
+            })
+            .push_constants_of::<Matrix4>(ShaderStageFlags::VERTEX, 0)
+            .renderpass(renderpass)
+            .build()
+            .context("Error building shadow map pipeline")?;
+
+        let depth_image = BoundImageView::from_context(
+            context,
+            &ImageSpec {
+                width: self.size,
+                height: self.size,
+                format: depth_format,
+                usage: depth_usage,
+                resources: DEPTH_RESOURCES,
+            },
+        )
+        .context("Error creating shadow map image")?;
+
+        let (pipeline, framebuffer) = {
+            let mut device = context.lock_device()?;
+            let pipeline = match pipeline_spec.build(
+                &mut device,
+                extent,
+                &context.physical_device_properties().limits,
+                empty(),
+            ) {
+                Ok(p) => p,
+                Err(e) => {
+                    drop(device);
+                    depth_image.deactivate_with_context(context);
+                    return Err(e.context("Error building shadow map pipeline"));
+                }
+            };
+
+            let framebuffer = unsafe {
+                device.create_framebuffer(
+                    &pipeline.renderpass,
+                    once(FramebufferAttachment {
+                        usage: depth_usage,
+                        view_caps: ViewCapabilities::empty(),
+                        format: depth_format,
+                    }),
+                    extent,
+                )
+            };
+            let framebuffer = match framebuffer {
+                Ok(f) => f,
+                Err(e) => {
+                    pipeline.deactivate(&mut device);
+                    drop(device);
+                    depth_image.deactivate_with_context(context);
+                    return Err(e).context("Error creating shadow map framebuffer");
+                }
+            };
+
+            (pipeline, framebuffer)
+        };
+
+        Ok(ShadowMapPass {
+            casters: self.casters,
+            graph_name: self.graph_name,
+            light_vp: self.light_vp,
+            pipeline,
+            depth_image,
+            framebuffer,
+        })
+    }
+
+    fn find_aux_queues(
+        _adapter: &Adapter,
+        _queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
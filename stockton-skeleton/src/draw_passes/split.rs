@@ -0,0 +1,207 @@
+//! Drawing several copies of a pass into different regions of the image, for split-screen.
+
+use super::{util::queue_draw_checked, DrawPass, FrameContext, IntoDrawPass, Middle};
+use crate::{
+    context::RenderingContext, queue_negotiator::QueueFamilyNegotiator, session::Session, types::*,
+};
+
+use anyhow::Result;
+use hal::{pso::Rect, Features};
+
+/// A region of the area being drawn into, with each component as a fraction of its width or height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportRegion {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl ViewportRegion {
+    /// The whole area
+    pub const FULL: ViewportRegion = ViewportRegion {
+        x: 0.0,
+        y: 0.0,
+        w: 1.0,
+        h: 1.0,
+    };
+
+    /// Split the area into `n` equal columns, from left to right.
+    pub fn columns(n: usize) -> Vec<ViewportRegion> {
+        let w = 1.0 / n as f32;
+        (0..n)
+            .map(|i| ViewportRegion {
+                x: i as f32 * w,
+                w,
+                ..Self::FULL
+            })
+            .collect()
+    }
+
+    /// Split the area into `n` equal rows, from top to bottom.
+    pub fn rows(n: usize) -> Vec<ViewportRegion> {
+        let h = 1.0 / n as f32;
+        (0..n)
+            .map(|i| ViewportRegion {
+                y: i as f32 * h,
+                h,
+                ..Self::FULL
+            })
+            .collect()
+    }
+
+    /// Get the rect in pixels that this region covers inside of `outer`.
+    pub fn within(&self, outer: Rect) -> Rect {
+        let x = outer.x + (self.x * outer.w as f32).round() as i16;
+        let y = outer.y + (self.y * outer.h as f32).round() as i16;
+        let right = outer.x + ((self.x + self.w) * outer.w as f32).round() as i16;
+        let bottom = outer.y + ((self.y + self.h) * outer.h as f32).round() as i16;
+
+        Rect {
+            x,
+            y,
+            w: right - x,
+            h: bottom - y,
+        }
+    }
+}
+
+/// Draws each of its passes into its own region of the image, for example to render from several cameras at once.
+/// Each pass gets its region as [`FrameContext::viewport`], so should take which camera to use from its own config.
+pub struct SplitScreenPass<T> {
+    views: Vec<(ViewportRegion, T)>,
+}
+
+impl<T> SplitScreenPass<T> {
+    /// The number of views being drawn.
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    /// Check if there are no views being drawn.
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// Get a mutable reference to the pass for the view at the given index.
+    pub fn pass_mut(&mut self, idx: usize) -> &mut T {
+        &mut self.views[idx].1
+    }
+
+    /// Change the region the view at the given index is drawn into. This takes effect from the next frame.
+    pub fn set_region(&mut self, idx: usize, region: ViewportRegion) {
+        self.views[idx].0 = region;
+    }
+}
+
+impl<T: DrawPass<Middle>> SplitScreenPass<T> {
+    /// Deactivate all of the given passes, ignoring any errors as we're most likely already handling one.
+    fn deactivate_all(
+        views: impl Iterator<Item = (ViewportRegion, T)>,
+        context: &mut RenderingContext,
+    ) {
+        for (_, pass) in views {
+            let _ = pass.deactivate(context);
+        }
+    }
+}
+
+impl<T: DrawPass<Middle>> DrawPass<Middle> for SplitScreenPass<T> {
+    fn queue_draw(
+        &mut self,
+        session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        for (region, pass) in self.views.iter_mut() {
+            let frame = FrameContext {
+                viewport: region.within(frame.viewport),
+                ..frame
+            };
+            queue_draw_checked(pass.name(), || {
+                pass.queue_draw(session, img_view, cmd_buffer, frame)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        self,
+        session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        let mut views = Vec::with_capacity(self.views.len());
+        let mut old = self.views.into_iter();
+        while let Some((region, pass)) = old.next() {
+            match pass.handle_surface_change(session, context) {
+                Ok(pass) => views.push((region, pass)),
+                Err(e) => {
+                    Self::deactivate_all(views.into_iter().chain(old), context);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(SplitScreenPass { views })
+    }
+
+    fn frame_cancelled(&mut self, frame: FrameContext) {
+        for (region, pass) in self.views.iter_mut() {
+            pass.frame_cancelled(FrameContext {
+                viewport: region.within(frame.viewport),
+                ..frame
+            });
+        }
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        for (_, pass) in self.views {
+            pass.deactivate(context)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Config for a [`SplitScreenPass`], giving the config for each view's pass and the region it should draw into.
+pub struct SplitScreenPassConfig<I> {
+    pub views: Vec<(ViewportRegion, I)>,
+}
+
+impl<I, T> IntoDrawPass<SplitScreenPass<T>, Middle> for SplitScreenPassConfig<I>
+where
+    I: IntoDrawPass<T, Middle>,
+    T: DrawPass<Middle>,
+{
+    fn init(
+        self,
+        session: &mut Session,
+        context: &mut RenderingContext,
+    ) -> Result<SplitScreenPass<T>> {
+        let mut views = Vec::with_capacity(self.views.len());
+        for (region, idp) in self.views {
+            match idp.init(session, context) {
+                Ok(pass) => views.push((region, pass)),
+                Err(e) => {
+                    SplitScreenPass::deactivate_all(views.into_iter(), context);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(SplitScreenPass { views })
+    }
+
+    fn find_aux_queues(
+        adapter: &Adapter,
+        queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        I::find_aux_queues(adapter, queue_negotiator)
+    }
+
+    fn required_features() -> Features {
+        I::required_features()
+    }
+}
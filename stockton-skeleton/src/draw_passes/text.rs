@@ -0,0 +1,530 @@
+//! A lightweight pass for drawing text from a bitmap font, for things like FPS counters and debug text.
+
+use std::{
+    array::IntoIter,
+    iter::{empty, once},
+};
+
+use super::{
+    util::{begin_render_pass, end_render_pass, SurfaceResources, TargetSpecificResources},
+    DrawPass, FrameContext, IntoDrawPass, Middle, PassPosition,
+};
+use crate::{
+    buffers::draw::{DrawBuffers, INITIAL_INDEX_SIZE, INITIAL_VERT_SIZE},
+    builders::{
+        AttachmentSpec, BlendMode, CompletePipeline, PipelineSpecBuilder, RenderpassSpec,
+        ShaderDesc, ShaderKind, VertexLayout, VertexPrimitiveAssemblerSpec,
+    },
+    context::RenderingContext,
+    mem::{DataPool, StagingPool, TexturesPool},
+    queue_negotiator::QueueFamilyNegotiator,
+    session::Session,
+    texture::{
+        LoadableImage, TexLoadQueue, TextureLoadConfig, TextureQuality, TextureRepo,
+        TextureResolver,
+    },
+    types::*,
+};
+
+use anyhow::{Context, Result};
+use hal::{
+    buffer::SubRange,
+    command::{ClearColor, ClearValue, RenderAttachmentInfo, SubpassContents},
+    format::Format,
+    image::{Layout, WrapMode},
+    pass::Attachment,
+    pso::{
+        DepthStencilDesc, Face, FrontFace, InputAssemblerDesc, PolygonMode, Primitive, Rasterizer,
+        State, VertexInputRate,
+    },
+};
+
+/// The most glyphs that can be drawn in one frame. Any text past this is left out.
+pub const MAX_GLYPHS: usize = min(
+    INITIAL_VERT_SIZE as usize / 4,
+    INITIAL_INDEX_SIZE as usize / 2,
+);
+
+const fn min(a: usize, b: usize) -> usize {
+    if a < b {
+        a
+    } else {
+        b
+    }
+}
+
+/// The vertices given to the text shader (position in clip space, UV, colour)
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct TextVertex(Vector2, Vector2, [f32; 4]);
+
+impl VertexLayout for TextVertex {
+    fn attributes() -> Vec<Format> {
+        vec![Format::Rg32Sfloat, Format::Rg32Sfloat, Format::Rgba32Sfloat]
+    }
+}
+
+/// Describes how glyphs are laid out in a font image.
+/// Glyphs are in a grid of equally sized cells, in character order from left to right then top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphGrid {
+    /// The number of glyphs in each row
+    pub columns: u32,
+
+    /// The number of rows of glyphs
+    pub rows: u32,
+
+    /// The character in the top left cell. Usually this is `' '` for fonts covering printable ASCII.
+    pub first_char: char,
+}
+
+impl GlyphGrid {
+    /// Get the top left and bottom right UV coordinates of the glyph for the given character, or None if the font doesn't have it.
+    pub fn glyph_uv(&self, c: char) -> Option<([f32; 2], [f32; 2])> {
+        let idx = (c as u32).checked_sub(self.first_char as u32)?;
+        if idx >= self.columns * self.rows {
+            return None;
+        }
+
+        let (w, h) = (1.0 / self.columns as f32, 1.0 / self.rows as f32);
+        let (x, y) = (
+            (idx % self.columns) as f32 * w,
+            (idx / self.columns) as f32 * h,
+        );
+
+        Some(([x, y], [x + w, y + h]))
+    }
+}
+
+/// Config for a [`TextPass`]
+pub struct TextPassConfig<I> {
+    /// The image containing every glyph, laid out as described by `grid`.
+    pub font: I,
+
+    /// How glyphs are laid out in `font`
+    pub grid: GlyphGrid,
+
+    /// How many pixels on screen each pixel of the font takes up
+    pub scale: f32,
+
+    /// The colour text is drawn in, until changed with [`TextPass::set_color`]. This is multiplied with the font image.
+    pub color: [f32; 4],
+
+    /// How the font image is filtered and wrapped
+    pub sampling: TextSampling,
+}
+
+/// How a [`TextPass`] samples its font image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSampling {
+    /// [`TextureQuality::Nearest`] keeps pixel fonts crisp, while the linear options smooth out fonts drawn at non-integer scales.
+    pub quality: TextureQuality,
+
+    /// How to deal with texture coordinates outside of the font image
+    pub wrap_mode: WrapMode,
+}
+
+impl Default for TextSampling {
+    /// No filtering, and clamped to the edge of the image.
+    fn default() -> Self {
+        TextSampling {
+            quality: TextureQuality::Nearest,
+            wrap_mode: WrapMode::Clamp,
+        }
+    }
+}
+
+/// Gives out the font image as texture 0, so it can be loaded by a [`TextureRepo`]
+struct FontResolver<I>(I);
+
+impl<I: LoadableImage + Clone> TextureResolver for FontResolver<I> {
+    type Image = I;
+
+    fn resolve(&mut self, texture_id: u32) -> Option<Self::Image> {
+        match texture_id {
+            0 => Some(self.0.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Text queued to be drawn on the next frame
+struct QueuedText {
+    pos: Vector2,
+    text: String,
+    color: [f32; 4],
+}
+
+/// Draws text using a bitmap font, alpha blended on top of whatever is already in the image.
+/// Text is queued with [`TextPass::draw_text`] each frame, and cleared once it's drawn.
+/// Nothing is drawn until the font has finished loading.
+pub struct TextPass<'a> {
+    repo: TextureRepo<TexturesPool, StagingPool>,
+    draw_buffers: DrawBuffers<'a, TextVertex, DataPool, StagingPool>,
+    surface_resources: SurfaceDependentResources,
+    grid: GlyphGrid,
+
+    /// The size of each glyph on screen, in pixels
+    glyph_size: Vector2,
+    color: [f32; 4],
+    queued: Vec<QueuedText>,
+}
+
+impl<'a> TextPass<'a> {
+    /// Queue text to be drawn on the next frame, with its top left `pos` pixels from the top left of the viewport.
+    /// Newlines start a new line below `pos`, and characters the font doesn't have are left as spaces.
+    pub fn draw_text(&mut self, pos: Vector2, text: &str) {
+        self.queued.push(QueuedText {
+            pos,
+            text: text.to_string(),
+            color: self.color,
+        });
+    }
+
+    /// Set the colour of text queued from now on.
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    /// The size of each glyph on screen, in pixels. This can be used to lay out text.
+    pub fn glyph_size(&self) -> Vector2 {
+        self.glyph_size
+    }
+
+    /// Write quads for all of the queued text into the draw buffers for the given frame, clearing the queue.
+    /// Returns the number of glyphs written.
+    fn write_queued(&mut self, viewport: Vector2, frame: &FrameContext) -> usize {
+        let buffers = self.draw_buffers.for_frame(frame);
+        let mut glyphs = 0;
+        'queue: for queued in self.queued.drain(..) {
+            let mut cursor = queued.pos;
+            for c in queued.text.chars() {
+                if c == '\n' {
+                    cursor = Vector2::new(queued.pos.x, cursor.y + self.glyph_size.y);
+                    continue;
+                }
+
+                if let Some((uv_tl, uv_br)) = self.grid.glyph_uv(c) {
+                    if glyphs == MAX_GLYPHS {
+                        break 'queue;
+                    }
+
+                    // Pixels to clip space
+                    let tl = Vector2::new(
+                        cursor.x / viewport.x * 2.0 - 1.0,
+                        cursor.y / viewport.y * 2.0 - 1.0,
+                    );
+                    let br = Vector2::new(
+                        (cursor.x + self.glyph_size.x) / viewport.x * 2.0 - 1.0,
+                        (cursor.y + self.glyph_size.y) / viewport.y * 2.0 - 1.0,
+                    );
+
+                    let base = glyphs * 4;
+                    let vertices = &mut buffers.vertex_buffer;
+                    vertices[base] = TextVertex(tl, uv_tl.into(), queued.color);
+                    vertices[base + 1] = TextVertex(
+                        Vector2::new(br.x, tl.y),
+                        [uv_br[0], uv_tl[1]].into(),
+                        queued.color,
+                    );
+                    vertices[base + 2] = TextVertex(br, uv_br.into(), queued.color);
+                    vertices[base + 3] = TextVertex(
+                        Vector2::new(tl.x, br.y),
+                        [uv_tl[0], uv_br[1]].into(),
+                        queued.color,
+                    );
+
+                    let base = base as u16;
+                    let indices = &mut buffers.index_buffer;
+                    indices[glyphs * 2] = (base, base + 1, base + 2);
+                    indices[glyphs * 2 + 1] = (base, base + 2, base + 3);
+
+                    glyphs += 1;
+                }
+
+                cursor.x += self.glyph_size.x;
+            }
+        }
+
+        glyphs
+    }
+}
+
+impl<'a> DrawPass<Middle> for TextPass<'a> {
+    fn queue_draw(
+        &mut self,
+        _session: &Session,
+        img_view: &ImageViewT,
+        cmd_buffer: &mut CommandBufferT,
+        frame: FrameContext,
+    ) -> Result<()> {
+        self.repo.process_responses();
+
+        let viewport = Vector2::new(frame.viewport.w as f32, frame.viewport.h as f32);
+        let glyphs = self.write_queued(viewport, &frame);
+
+        let ds = match self.repo.attempt_get_descriptor_set(0) {
+            Some(ds) if glyphs > 0 => ds,
+            _ => return Ok(()),
+        };
+
+        let buffers = self.draw_buffers.for_frame(&frame);
+        buffers.vertex_buffer.record_commit_cmds(cmd_buffer)?;
+        buffers.index_buffer.record_commit_cmds(cmd_buffer)?;
+
+        let res = &mut self.surface_resources;
+        unsafe {
+            begin_render_pass(
+                cmd_buffer,
+                &res.pipeline.renderpass,
+                res.framebuffers.get_next(),
+                frame.viewport,
+                once(RenderAttachmentInfo {
+                    image_view: img_view,
+                    clear_value: ClearValue {
+                        color: ClearColor {
+                            float32: [0.0, 0.0, 0.0, 1.0],
+                        },
+                    },
+                }),
+                SubpassContents::Inline,
+            );
+            cmd_buffer.bind_graphics_pipeline(&res.pipeline.pipeline);
+            res.pipeline.set_viewport(cmd_buffer, frame.viewport);
+            cmd_buffer.bind_graphics_descriptor_sets(
+                &res.pipeline.pipeline_layout,
+                0,
+                once(ds),
+                empty(),
+            );
+
+            cmd_buffer.bind_vertex_buffers(
+                0,
+                once((
+                    buffers.vertex_buffer.get_buffer(),
+                    SubRange {
+                        offset: 0,
+                        size: None,
+                    },
+                )),
+            );
+            cmd_buffer.bind_index_buffer(
+                buffers.index_buffer.get_buffer(),
+                SubRange {
+                    offset: 0,
+                    size: None,
+                },
+                buffers.index_type(),
+            );
+
+            cmd_buffer.draw_indexed(0..(glyphs * 6) as u32, 0, 0..1);
+
+            end_render_pass(cmd_buffer);
+        }
+
+        Ok(())
+    }
+
+    fn handle_surface_change(
+        mut self,
+        _session: &Session,
+        context: &mut RenderingContext,
+    ) -> Result<Self> {
+        match self
+            .surface_resources
+            .recreate::<Middle>(context, &self.repo)
+        {
+            Ok(_) => Ok(self),
+            Err(e) => {
+                self.deactivate(context)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        self.draw_buffers.deactivate(context);
+        self.surface_resources.deactivate(context)?;
+        self.repo.deactivate(context);
+
+        Ok(())
+    }
+}
+
+impl<'a, I> IntoDrawPass<TextPass<'a>, Middle> for TextPassConfig<I>
+where
+    I: 'static + LoadableImage + Clone + Send + Sync,
+{
+    fn init(self, _session: &mut Session, context: &mut RenderingContext) -> Result<TextPass<'a>> {
+        let glyph_size = Vector2::new(
+            (self.font.width() / self.grid.columns) as f32 * self.scale,
+            (self.font.height() / self.grid.rows) as f32 * self.scale,
+        );
+
+        let mut repo = TextureRepo::new::<_, TexLoadQueue>(
+            context,
+            TextureLoadConfig {
+                resolver: FontResolver(self.font),
+                quality: self.sampling.quality.supported_by(context),
+                lod_bias: 0.0,
+                wrap_mode: self.sampling.wrap_mode,
+                upload_budget: Default::default(),
+            },
+        )
+        .context("Error creating texture repo")?;
+        if let Err(e) = repo.queue_load(0) {
+            repo.deactivate(context);
+            return Err(e);
+        }
+
+        let draw_buffers =
+            match DrawBuffers::from_context(context).context("Error creating draw buffers") {
+                Ok(x) => x,
+                Err(e) => {
+                    repo.deactivate(context);
+                    return Err(e);
+                }
+            };
+
+        let surface_resources = match SurfaceDependentResources::create::<Middle>(context, &repo) {
+            Ok(x) => x,
+            Err(e) => {
+                draw_buffers.deactivate(context);
+                repo.deactivate(context);
+                return Err(e);
+            }
+        };
+
+        Ok(TextPass {
+            repo,
+            draw_buffers,
+            surface_resources,
+            grid: self.grid,
+            glyph_size,
+            color: self.color,
+            queued: vec![],
+        })
+    }
+
+    fn find_aux_queues(
+        adapter: &Adapter,
+        queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        queue_negotiator.find(adapter, &TexLoadQueue, 1)
+    }
+}
+
+/// Everything that depends on the surface, so needs recreated when it changes.
+struct SurfaceDependentResources {
+    pipeline: CompletePipeline,
+    framebuffers: TargetSpecificResources<FramebufferT>,
+}
+
+impl SurfaceResources for SurfaceDependentResources {
+    type Shared = TextureRepo<TexturesPool, StagingPool>;
+
+    fn create<P: PassPosition>(
+        context: &mut RenderingContext,
+        repo: &TextureRepo<TexturesPool, StagingPool>,
+    ) -> Result<Self> {
+        let props = context.properties().clone();
+
+        let pipeline_spec = PipelineSpecBuilder::default()
+            .rasterizer(Rasterizer {
+                polygon_mode: PolygonMode::Fill,
+                cull_face: Face::NONE,
+                front_face: FrontFace::CounterClockwise,
+                depth_clamping: false,
+                depth_bias: None,
+                conservative: false,
+                line_width: State::Static(1.0),
+            })
+            .depth_stencil(DepthStencilDesc {
+                depth: None,
+                depth_bounds: false,
+                stencil: None,
+            })
+            .blender(BlendMode::AlphaBlend)
+            .primitive_assembler(VertexPrimitiveAssemblerSpec::with_buffers(
+                InputAssemblerDesc::new(Primitive::TriangleList),
+                vec![TextVertex::buffer_spec(VertexInputRate::Vertex)],
+            ))
+            .shader_vertex(ShaderDesc {
+                source: include_str!("./data/text.vert").to_string(),
+                entry: "main".to_string(),
+                kind: ShaderKind::Vertex,
+            })
+            .shader_fragment(ShaderDesc {
+                source: include_str!("./data/text.frag").to_string(),
+                entry: "main".to_string(),
+                kind: ShaderKind::Fragment,
+            })
+            .dynamic_viewport(true)
+            .dynamic_scissor(true)
+            .renderpass(RenderpassSpec {
+                colors: vec![AttachmentSpec {
+                    attachment: Attachment {
+                        format: Some(props.color_format),
+                        samples: 1,
+                        ops: P::attachment_ops(),
+                        stencil_ops: P::attachment_ops(),
+                        layouts: P::layout_as_range(),
+                    },
+                    used_layout: Layout::ColorAttachmentOptimal,
+                }],
+                depth: None,
+                inputs: vec![],
+                resolves: vec![],
+                preserves: vec![],
+            })
+            .build()
+            .context("Error building text pipeline")?;
+
+        let mut device = context.lock_device()?;
+        let pipeline = pipeline_spec
+            .build(
+                &mut device,
+                props.extent,
+                &context.physical_device_properties().limits,
+                once(&*repo.get_ds_layout()?),
+            )
+            .context("Error building text pipeline")?;
+
+        let fat = props.swapchain_framebuffer_attachment();
+        let framebuffers = TargetSpecificResources::new(
+            || unsafe {
+                Ok(device.create_framebuffer(
+                    &pipeline.renderpass,
+                    IntoIter::new([fat.clone()]),
+                    props.extent,
+                )?)
+            },
+            context.frames_in_flight(),
+        );
+        let framebuffers = match framebuffers {
+            Ok(f) => f,
+            Err(e) => {
+                pipeline.deactivate(&mut device);
+                return Err(e);
+            }
+        };
+
+        Ok(SurfaceDependentResources {
+            pipeline,
+            framebuffers,
+        })
+    }
+
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()> {
+        unsafe {
+            let mut device = context.lock_device()?;
+            for fb in self.framebuffers.dissolve() {
+                device.destroy_framebuffer(fb);
+            }
+
+            self.pipeline.deactivate(&mut device);
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,21 @@
 //! Utility structs & functions
 
+#[cfg(debug_assertions)]
+use std::cell::Cell;
+use std::mem::replace;
+
 use anyhow::Result;
+use hal::{
+    command::{RenderAttachmentInfo, SubpassContents},
+    pso::Rect,
+};
+#[cfg(debug_assertions)]
+use log::trace;
+
+use super::PassPosition;
+use crate::{backend::Backend, context::RenderingContext, types::*};
 
-/// Keeps a given resource for each swapchain image
+/// Keeps a given resource for each frame in flight
 pub struct TargetSpecificResources<T> {
     elements: Vec<T>,
     next_idx: usize,
@@ -10,7 +23,7 @@ pub struct TargetSpecificResources<T> {
 
 impl<T> TargetSpecificResources<T> {
     /// Create a new set of resources, given a function to generate them and the count
-    /// In most cases, count should be swapchain_properties.image_count
+    /// In most cases, count should be `RenderingContext::frames_in_flight`
     pub fn new<F>(mut generator: F, count: usize) -> Result<Self>
     where
         F: FnMut() -> Result<T>,
@@ -26,6 +39,11 @@ impl<T> TargetSpecificResources<T> {
         })
     }
 
+    /// Get the resource at the given index, without affecting which will be returned by `get_next`.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        self.elements.get(idx)
+    }
+
     /// Get the next resource, wrapping around if necessary.
     pub fn get_next(&mut self) -> &T {
         let el = &self.elements[self.next_idx];
@@ -33,9 +51,138 @@ impl<T> TargetSpecificResources<T> {
         el
     }
 
+    /// The number of resources in the set.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Check if the resource set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Iterate over every resource in the set, without affecting which will be returned by `get_next`.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elements.iter()
+    }
+
+    /// Iterate mutably over every resource in the set, without affecting which will be returned by `get_next`.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.elements.iter_mut()
+    }
+
     /// Dissolve the resource set, returning an iterator over each item.
     /// In most cases, each item will need deactivated.
     pub fn dissolve(self) -> impl Iterator<Item = T> {
         self.elements.into_iter()
     }
 }
+
+impl<T> From<Vec<T>> for TargetSpecificResources<T> {
+    fn from(elements: Vec<T>) -> Self {
+        TargetSpecificResources {
+            elements,
+            next_idx: 0,
+        }
+    }
+}
+
+/// Resources that depend on the surface, such as pipelines and framebuffers, and so are rebuilt from scratch when it changes.
+/// Draw passes keep these in one field, apart from state that should survive a surface change, such as texture repos, buffers, or counters.
+/// [`super::DrawPass::handle_surface_change`] can then just call [`Self::recreate`], deactivating the whole pass if it fails.
+pub trait SurfaceResources: Sized {
+    /// State kept across surface changes that's needed to create the resources, for example a texture repo whose descriptor set layout the pipeline uses.
+    type Shared: ?Sized;
+
+    /// Create the resources for the context's current surface, for a pass in position `P`.
+    fn create<P: PassPosition>(
+        context: &mut RenderingContext,
+        shared: &Self::Shared,
+    ) -> Result<Self>;
+
+    /// Deactivate any vulkan parts of the resources.
+    fn deactivate(self, context: &mut RenderingContext) -> Result<()>;
+
+    /// Create new resources for the current surface, then deactivate the old ones.
+    /// If creating them fails, the old resources are kept. Either way, `self` is always left usable or ready to be deactivated.
+    fn recreate<P: PassPosition>(
+        &mut self,
+        context: &mut RenderingContext,
+        shared: &Self::Shared,
+    ) -> Result<()> {
+        let new = Self::create::<P>(context, shared)?;
+
+        replace(self, new).deactivate(context)
+    }
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// The number of renderpasses begun with [`begin_render_pass`] on this thread that haven't been ended yet.
+    static OPEN_RENDER_PASSES: Cell<usize> = Cell::new(0);
+}
+
+/// Begin a renderpass, the same as calling [`hal::command::CommandBuffer::begin_render_pass`] directly.
+/// Draw passes should use this and [`end_render_pass`], so debug builds can check every renderpass they begin is ended before `queue_draw` returns.
+/// Renderpasses begun on the command buffer directly aren't counted, so can't be checked.
+///
+/// # Safety
+/// See [`hal::command::CommandBuffer::begin_render_pass`].
+pub unsafe fn begin_render_pass<'a, T>(
+    cmd_buffer: &mut CommandBufferT,
+    render_pass: &RenderPassT,
+    framebuffer: &FramebufferT,
+    render_area: Rect,
+    attachments: T,
+    first_subpass: SubpassContents,
+) where
+    T: Iterator<Item = RenderAttachmentInfo<'a, Backend>>,
+{
+    #[cfg(debug_assertions)]
+    OPEN_RENDER_PASSES.with(|open| open.set(open.get() + 1));
+
+    cmd_buffer.begin_render_pass(
+        render_pass,
+        framebuffer,
+        render_area,
+        attachments,
+        first_subpass,
+    );
+}
+
+/// End the current renderpass, begun with [`begin_render_pass`].
+///
+/// # Safety
+/// See [`hal::command::CommandBuffer::end_render_pass`].
+pub unsafe fn end_render_pass(cmd_buffer: &mut CommandBufferT) {
+    #[cfg(debug_assertions)]
+    OPEN_RENDER_PASSES.with(|open| open.set(open.get().saturating_sub(1)));
+
+    cmd_buffer.end_render_pass();
+}
+
+/// Queue a pass's draw commands with `queue_draw`, where `name` is the pass's [`super::DrawPass::name`].
+/// In debug builds, this logs which pass is being drawn, and panics if it leaves open a renderpass begun with [`begin_render_pass`].
+/// Passes that draw other passes should use this for each of them, so the panic says which one was at fault.
+pub fn queue_draw_checked(
+    name: &'static str,
+    queue_draw: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    // Backend validation errors don't say which pass recorded the bad commands, so log where each one starts.
+    #[cfg(debug_assertions)]
+    trace!("Queueing draw for {}", name);
+
+    let result = queue_draw();
+
+    #[cfg(debug_assertions)]
+    {
+        let open = OPEN_RENDER_PASSES.with(|open| open.replace(0));
+        if result.is_ok() && open != 0 {
+            panic!("{} began {} renderpass(es) without ending them", name, open);
+        }
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = name;
+
+    result
+}
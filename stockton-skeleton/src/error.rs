@@ -1,5 +1,11 @@
 //! Error types
 
+use hal::{
+    pso::ShaderStageFlags,
+    window::{AcquireError, PresentError},
+    Features,
+};
+use std::ops::Range;
 use thiserror::Error;
 
 /// An error caused by a lock being poisoned.
@@ -31,6 +37,9 @@ pub enum EnvironmentError {
     #[error("No supported depth format")]
     DepthFormat,
 
+    #[error("No supported depth format can be sampled")]
+    SampledDepthFormat,
+
     #[error("No supported present mode")]
     PresentMode,
 
@@ -51,6 +60,9 @@ pub enum EnvironmentError {
 
     #[error("Memory pool missing")]
     MemoryPoolMissing,
+
+    #[error("Device doesn't support the required features {0:?}")]
+    MissingFeatures(Features),
 }
 
 /// Indicates invalid usage of an API.
@@ -61,6 +73,123 @@ pub enum UsageError {
 
     #[error("Called get_queue without properly requesting the queue beforehand.")]
     QueueNegotiatorMisuse,
+
+    #[error("Push constants don't fit in any range declared for the given shader stages.")]
+    PushConstantsOutOfRange,
+
+    #[error("Push constant range {range:?} for stages {stages:?} goes past the device's limit of {max} bytes.")]
+    PushConstantsTooLarge {
+        stages: ShaderStageFlags,
+        range: Range<u32>,
+        max: usize,
+    },
+
+    #[error("Image data is {actual} bytes, but the image needs exactly {expected}.")]
+    ImageDataSize { expected: usize, actual: usize },
+
+    #[error("Resource {0} not found in session. Make sure it's inserted before it's used.")]
+    ResourceNotFound(&'static str),
+
+    #[error("Renderpass has no subpasses.")]
+    NoSubpasses,
+
+    #[error("Subpass {index} doesn't exist, as the renderpass only has {count}.")]
+    SubpassOutOfRange { index: u8, count: usize },
+
+    #[error(
+        "Subpass {subpass} refers to attachment {attachment}, but the renderpass only has {count}."
+    )]
+    AttachmentOutOfRange {
+        subpass: usize,
+        attachment: usize,
+        count: usize,
+    },
+
+    #[error("Renderer was used after an earlier error poisoned it. It should be dropped instead.")]
+    RendererPoisoned,
+
+    #[error("Recording thread stopped, most likely because a draw pass panicked.")]
+    RecordingThreadStopped,
+
+    #[error("Pass {pass} reads {resource} before any pass writes to it.")]
+    GraphReadBeforeWrite {
+        pass: &'static str,
+        resource: &'static str,
+    },
+
+    #[error("Pass {pass} uses {resource} in more than one way.")]
+    GraphConflictingUsage {
+        pass: &'static str,
+        resource: &'static str,
+    },
+
+    #[error("Pass {pass} uses the swapchain image as something other than a colour attachment.")]
+    GraphSwapchainUsage { pass: &'static str },
+
+    #[error("No pass in the render graph owns {0}.")]
+    GraphResourceMissing(&'static str),
+
+    #[error("Memory pool {0} is still referenced elsewhere, so can't be deactivated.")]
+    MemoryPoolInUse(&'static str),
+
+    #[error("Device is still referenced elsewhere, so the context can't be destroyed.")]
+    DeviceInUse,
+}
+
+/// The kind of error that happened while drawing a frame, which decides whether it's worth trying to recover from.
+/// Errors from acquiring or presenting swapchain images have this attached as context, so it can be found with [`DrawErrorKind::of`].
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum DrawErrorKind {
+    #[error("Swapchain is out of date with the surface")]
+    SurfaceOutOfDate,
+
+    #[error("Surface was lost")]
+    SurfaceLost,
+
+    #[error("Device was lost")]
+    DeviceLost,
+
+    #[error("Error drawing frame")]
+    Other,
+}
+
+impl DrawErrorKind {
+    /// Get the kind of the given error, or `Other` if it wasn't given one.
+    pub fn of(err: &anyhow::Error) -> Self {
+        err.downcast_ref::<DrawErrorKind>()
+            .copied()
+            .unwrap_or(DrawErrorKind::Other)
+    }
+
+    /// Check if the error can be recovered from by recreating the surface.
+    pub fn is_surface_error(&self) -> bool {
+        matches!(
+            self,
+            DrawErrorKind::SurfaceOutOfDate | DrawErrorKind::SurfaceLost
+        )
+    }
+}
+
+impl From<&AcquireError> for DrawErrorKind {
+    fn from(err: &AcquireError) -> Self {
+        match err {
+            AcquireError::OutOfDate(_) => DrawErrorKind::SurfaceOutOfDate,
+            AcquireError::SurfaceLost(_) => DrawErrorKind::SurfaceLost,
+            AcquireError::DeviceLost(_) => DrawErrorKind::DeviceLost,
+            _ => DrawErrorKind::Other,
+        }
+    }
+}
+
+impl From<&PresentError> for DrawErrorKind {
+    fn from(err: &PresentError) -> Self {
+        match err {
+            PresentError::OutOfDate(_) => DrawErrorKind::SurfaceOutOfDate,
+            PresentError::SurfaceLost(_) => DrawErrorKind::SurfaceLost,
+            PresentError::DeviceLost(_) => DrawErrorKind::DeviceLost,
+            _ => DrawErrorKind::Other,
+        }
+    }
 }
 
 /// Displays an error with full backtrace
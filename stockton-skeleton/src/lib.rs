@@ -1,11 +1,10 @@
-#[cfg(feature = "vulkan")]
-extern crate gfx_backend_vulkan as back;
 extern crate gfx_hal as hal;
 extern crate nalgebra_glm as na;
 
 #[macro_use]
 extern crate derive_builder;
 
+pub mod backend;
 pub mod buffers;
 pub mod builders;
 pub mod components;
@@ -13,6 +12,8 @@ pub mod context;
 pub mod draw_passes;
 pub mod error;
 pub mod mem;
+pub mod observer;
+pub mod pipelined;
 pub mod queue_negotiator;
 pub mod session;
 mod target;
@@ -21,33 +22,43 @@ pub mod types;
 pub mod utils;
 
 pub use anyhow::Result;
-pub use context::RenderingContext;
-pub use draw_passes::{DrawPass, IntoDrawPass, PassPosition};
+pub use context::{RendererOptions, RenderingContext};
+pub use draw_passes::{DrawPass, FrameContext, IntoDrawPass, PassPosition};
+pub use pipelined::PipelinedRenderer;
 pub use session::Session;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use draw_passes::Singular;
+use error::{DrawErrorKind, UsageError};
+use image::RgbaImage;
 use std::mem::ManuallyDrop;
 use winit::window::Window;
 
 /// Renders a world to a window when you tell it to.
 /// Also takes ownership of the window and channels window events to be processed outside winit's event loop.
+/// To record frames on a worker thread instead of the one rendering is called from, turn this into a [`PipelinedRenderer`].
 pub struct Renderer<DP> {
     /// All the vulkan stuff
     context: ManuallyDrop<RenderingContext>,
 
     /// The draw pass we're using
     draw_pass: ManuallyDrop<DP>,
+
+    /// Set when an operation fails partway through, so `context` or `draw_pass` may have been taken.
+    /// Once this is set, neither should be touched again.
+    poisoned: bool,
 }
 
 impl<DP: DrawPass<Singular>> Renderer<DP> {
-    /// Create a new Renderer.
+    /// Create a new Renderer. Use `RendererOptions::default()` to pick everything based on what the surface supports.
+    /// Check [`RenderingContext::properties`] for what was actually picked.
     pub fn new<IDP: IntoDrawPass<DP, Singular>>(
         window: &Window,
         session: &mut Session,
         idp: IDP,
+        options: RendererOptions,
     ) -> Result<Self> {
-        let mut context = RenderingContext::new::<IDP, DP>(window)?;
+        let mut context = RenderingContext::new::<IDP, DP>(window, options)?;
 
         // Draw pass
         let draw_pass = idp
@@ -57,43 +68,85 @@ impl<DP: DrawPass<Singular>> Renderer<DP> {
         Ok(Renderer {
             context: ManuallyDrop::new(context),
             draw_pass: ManuallyDrop::new(draw_pass),
+            poisoned: false,
         })
     }
 
     /// Render a single frame of the given session.
     /// If this returns an error, the whole renderer is dead, hence it takes ownership to ensure it can't be called in that case.
     pub fn render(mut self, session: &Session) -> Result<Renderer<DP>> {
-        // Safety: If this fails at any point, the ManuallyDrop won't be touched again, as Renderer will be dropped.
+        self.render_in_place(session)?;
+
+        Ok(self)
+    }
+
+    /// Render a single frame of the given session, without taking ownership.
+    /// If this returns an error, the renderer is poisoned: any further rendering will return an error, and any other methods will panic.
+    /// Once poisoned, the renderer should just be dropped.
+    pub fn render_in_place(&mut self, session: &Session) -> Result<()> {
+        self.check_poisoned()?;
+
+        // Safety: We stay poisoned unless we get to the end, so if this fails at any point the ManuallyDrop won't be touched again.
         // Hence, we can always take from the ManuallyDrop
+        self.poisoned = true;
         unsafe {
-            match ManuallyDrop::take(&mut self.context)
+            let c = match ManuallyDrop::take(&mut self.context)
                 .draw_next_frame(session, &mut *self.draw_pass)
             {
-                Ok(c) => {
-                    self.context = ManuallyDrop::new(c);
-                    Ok(self)
-                }
-                Err((_e, c)) => {
-                    // TODO: Try to detect if the error is actually surface related.
+                Ok(c) => c,
+                Err((e, c)) => {
+                    // Only surface errors can be fixed by recreating the surface, anything else is fatal.
+                    if !DrawErrorKind::of(&e).is_surface_error() {
+                        return Err(e);
+                    }
 
                     let c = c.attempt_recovery()?;
-                    match c.draw_next_frame(session, &mut *self.draw_pass) {
-                        Ok(c) => {
-                            self.context = ManuallyDrop::new(c);
-                            Ok(self)
-                        }
-                        Err((e, _c)) => Err(e),
-                    }
+                    c.draw_next_frame(session, &mut *self.draw_pass)
+                        .map_err(|(e, _c)| e)?
                 }
-            }
+            };
+            self.context = ManuallyDrop::new(c);
         }
+        self.poisoned = false;
+
+        Ok(())
+    }
+
+    /// Render a single frame of the given session into an image, instead of to the window.
+    /// This isn't fully headless: the renderer still needs a window and surface to be created, although nothing is presented to it, so it can be kept hidden.
+    /// See [`RenderingContext::draw_to_image`] for how the draw pass is used.
+    /// Like [`Self::render_in_place`], the renderer is poisoned if this returns an error.
+    pub fn render_once_to_image(&mut self, session: &Session) -> Result<RgbaImage> {
+        self.check_poisoned()?;
+
+        // Safety: We stay poisoned unless we get to the end, so if this fails the ManuallyDrop won't be touched again.
+        self.poisoned = true;
+        let (image, dp) = unsafe {
+            self.context
+                .draw_to_image(session, ManuallyDrop::take(&mut self.draw_pass))?
+        };
+        self.draw_pass = ManuallyDrop::new(dp);
+        self.poisoned = false;
+
+        Ok(image)
     }
 
     /// Recreate the surface, and other derived components.
     /// This should be called when the window is resized.
     pub fn recreate_surface(mut self, session: &Session) -> Result<Renderer<DP>> {
-        // Safety: If this fails at any point, the ManuallyDrop won't be touched again, as Renderer will be dropped.
+        self.recreate_surface_in_place(session)?;
+
+        Ok(self)
+    }
+
+    /// Recreate the surface and other derived components, without taking ownership.
+    /// Like [`Self::render_in_place`], the renderer is poisoned if this returns an error.
+    pub fn recreate_surface_in_place(&mut self, session: &Session) -> Result<()> {
+        self.check_poisoned()?;
+
+        // Safety: We stay poisoned unless we get to the end, so if this fails at any point the ManuallyDrop won't be touched again.
         // Hence, we can always take from the ManuallyDrop
+        self.poisoned = true;
         unsafe {
             let ctx = ManuallyDrop::take(&mut self.context);
             log::debug!("ctx");
@@ -104,17 +157,88 @@ impl<DP: DrawPass<Singular>> Renderer<DP> {
                 .handle_surface_change(session, &mut self.context)?;
             self.draw_pass = ManuallyDrop::new(dp);
         }
+        self.poisoned = false;
 
-        Ok(self)
+        Ok(())
+    }
+
+    /// Deactivate the draw pass and destroy the context, consuming the renderer.
+    /// This waits for the device to be idle first, and tears everything down in the right order, so nothing is leaked.
+    /// Anything else created from the context, such as draw passes that aren't part of this renderer, must be deactivated before this is called.
+    /// If the renderer is poisoned, this returns an error and whatever is left is leaked.
+    pub fn deactivate(mut self) -> Result<()> {
+        self.check_poisoned()?;
+
+        // Safety: self is consumed, so neither ManuallyDrop is touched again.
+        let (mut context, draw_pass) = unsafe {
+            (
+                ManuallyDrop::take(&mut self.context),
+                ManuallyDrop::take(&mut self.draw_pass),
+            )
+        };
+
+        context
+            .lock_device()?
+            .wait_idle()
+            .context("Error waiting for device to be idle")?;
+        draw_pass
+            .deactivate(&mut context)
+            .context("Error deactivating draw pass")?;
+
+        context
+            .deactivate_memory_pools()?
+            .deactivate()
+            .context("Error destroying context")
+    }
+
+    /// Check if an earlier error has poisoned the renderer.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Return an error if the renderer is poisoned.
+    fn check_poisoned(&self) -> Result<()> {
+        match self.poisoned {
+            true => Err(anyhow!(UsageError::RendererPoisoned)),
+            false => Ok(()),
+        }
+    }
+
+    /// Panic if the renderer is poisoned, as the context or draw pass may not be valid.
+    fn assert_not_poisoned(&self) {
+        assert!(!self.poisoned, "{}", UsageError::RendererPoisoned);
+    }
+
+    /// Update the pixels per point used when drawing.
+    /// This should be called with the new scale factor whenever winit gives a `ScaleFactorChanged` event.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.assert_not_poisoned();
+        self.context.set_pixels_per_point(pixels_per_point);
     }
 
     pub fn get_aspect_ratio(&self) -> f32 {
+        self.assert_not_poisoned();
         let e = self.context.properties().extent;
         e.width as f32 / e.height as f32
     }
 
     /// Get a reference to the renderer's context.
     pub fn context(&self) -> &RenderingContext {
+        self.assert_not_poisoned();
         &self.context
     }
+
+    /// Get a mutable reference to the renderer's context.
+    /// This can be used to initialise draw passes that will be added at runtime.
+    pub fn context_mut(&mut self) -> &mut RenderingContext {
+        self.assert_not_poisoned();
+        &mut self.context
+    }
+
+    /// Get a mutable reference to the renderer's draw pass.
+    /// This can be used to change the draw pass at runtime, for example toggling passes in a [`draw_passes::DrawPassList`].
+    pub fn draw_pass_mut(&mut self) -> &mut DP {
+        self.assert_not_poisoned();
+        &mut self.draw_pass
+    }
 }
@@ -10,7 +10,7 @@ use crate::{
 };
 
 use std::{
-    ops::Range,
+    ops::{Add, Range},
     sync::{Arc, RwLock},
 };
 
@@ -40,6 +40,51 @@ pub trait MemoryPool: Send + Sync + 'static {
 
     /// Deactivate this memory pool, freeing any allocated memory objects.
     fn deactivate(self, context: &mut StatefulRenderingContext<DeactivatedMemoryPools>);
+
+    /// Get statistics about this pool's memory usage, if it keeps track of them.
+    fn stats(&self) -> Option<PoolStats> {
+        None
+    }
+}
+
+/// Statistics about a [`MemoryPool`]'s memory usage.
+/// These may be approximate, depending on the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Bytes of memory allocated from the device.
+    pub allocated: u64,
+
+    /// Bytes of memory in blocks that are currently in use.
+    pub used: u64,
+
+    /// The number of chunks the allocated memory is split into.
+    pub chunks: usize,
+}
+
+impl PoolStats {
+    /// Record a block of `size` bytes being allocated, which needed `allocated` bytes from the device.
+    fn record_alloc(&mut self, size: u64, allocated: u64) {
+        self.used += size;
+        self.allocated += allocated;
+    }
+
+    /// Record a block of `size` bytes being freed, which returned `freed` bytes to the device.
+    fn record_free(&mut self, size: u64, freed: u64) {
+        self.used = self.used.saturating_sub(size);
+        self.allocated = self.allocated.saturating_sub(freed);
+    }
+}
+
+impl Add for PoolStats {
+    type Output = PoolStats;
+
+    fn add(self, rhs: PoolStats) -> PoolStats {
+        PoolStats {
+            allocated: self.allocated + rhs.allocated,
+            used: self.used + rhs.used,
+            chunks: self.chunks + rhs.chunks,
+        }
+    }
 }
 
 /// Block that owns a `Range` of the `Memory`.
@@ -81,10 +126,13 @@ mod rendy {
     use super::*;
 
     use crate::{
+        backend as back,
         error::{EnvironmentError, UsageError},
         utils::find_memory_type_id,
     };
 
+    use std::marker::PhantomData;
+
     use anyhow::{anyhow, Context, Result};
     use hal::{
         format::Format,
@@ -107,27 +155,47 @@ mod rendy {
         }
     }
 
-    /// Intended to be used for textures.
-    /// The allocated memory is guaranteed to be suitable for any colour image with optimal tiling and no extra sparse flags or view capabilities.
-    pub struct TexturesPool(DynamicAllocator<back::Backend>);
-    impl MemoryPool for TexturesPool {
+    /// Describes the images that an [`ImagePool`] will be used for.
+    pub trait ImagePoolSpec: Send + Sync + 'static {
+        /// The format of images allocated from the pool.
+        fn format(context: &RenderingContext) -> Format;
+
+        /// How images allocated from the pool will be used.
+        fn usage() -> hal::image::Usage {
+            hal::image::Usage::SAMPLED
+        }
+    }
+
+    /// A pool of device-local memory for images.
+    /// The allocated memory is guaranteed to be suitable for any image with the format and usage given by `S`, optimal tiling, and no extra sparse flags or view capabilities.
+    pub struct ImagePool<S: ImagePoolSpec>(
+        DynamicAllocator<back::Backend>,
+        PoolStats,
+        PhantomData<S>,
+    );
+    impl<S: ImagePoolSpec> MemoryPool for ImagePool<S> {
         type Block = DynamicBlock<back::Backend>;
 
         fn alloc(&mut self, device: &DeviceT, size: u64, align: u64) -> Result<(Self::Block, u64)> {
-            Ok(self.0.alloc(device, size, align)?)
+            let (block, allocated) = self.0.alloc(device, size, align)?;
+            self.1.record_alloc(Block::size(&block), allocated);
+            Ok((block, allocated))
         }
 
         fn free(&mut self, device: &DeviceT, block: Self::Block) -> u64 {
-            self.0.free(device, block)
+            let size = Block::size(&block);
+            let freed = self.0.free(device, block);
+            self.1.record_free(size, freed);
+            freed
         }
 
         fn from_context(context: &RenderingContext) -> Result<Arc<RwLock<Self>>> {
             let type_mask = unsafe {
-                use hal::image::{Kind, Tiling, Usage, ViewCapabilities};
+                use hal::image::{Kind, Tiling, ViewCapabilities};
 
-                // We create an empty image with the same format as used for textures
+                // We create an empty image with the same format and usage as the images we'll be used for
                 // this is to get the type_mask required, which will stay the same for
-                // all colour images of the same tiling. (certain memory flags excluded).
+                // all images of the same format and tiling. (certain memory flags excluded).
 
                 // Size and alignment don't necessarily stay the same, so we're forced to
                 // guess at the alignment for our allocator.
@@ -136,9 +204,9 @@ mod rendy {
                     .create_image(
                         Kind::D2(16, 16, 1, 1),
                         1,
-                        Format::Rgba8Srgb,
+                        S::format(context),
                         Tiling::Optimal,
-                        Usage::SAMPLED,
+                        S::usage(),
                         SparseFlags::empty(),
                         ViewCapabilities::empty(),
                     )
@@ -170,91 +238,84 @@ mod rendy {
                 )
             };
 
-            Ok(Arc::new(RwLock::new(Self(allocator))))
+            Ok(Arc::new(RwLock::new(Self(
+                allocator,
+                PoolStats::default(),
+                PhantomData,
+            ))))
         }
 
         fn deactivate(self, _context: &mut StatefulRenderingContext<DeactivatedMemoryPools>) {
             self.0.dispose();
         }
-    }
 
-    /// Used for depth buffers.
-    /// Memory returned is guaranteed to be suitable for any image using `context.target_chain().properties().depth_format` with optimal tiling, and no sparse flags or view capabilities.
-    pub struct DepthBufferPool(DynamicAllocator<back::Backend>);
-    impl MemoryPool for DepthBufferPool {
-        type Block = DynamicBlock<back::Backend>;
-
-        fn alloc(&mut self, device: &DeviceT, size: u64, align: u64) -> Result<(Self::Block, u64)> {
-            Ok(self.0.alloc(device, size, align)?)
+        fn stats(&self) -> Option<PoolStats> {
+            Some(PoolStats {
+                chunks: self.0.chunk_count(),
+                ..self.1
+            })
         }
+    }
 
-        fn free(&mut self, device: &DeviceT, block: Self::Block) -> u64 {
-            self.0.free(device, block)
+    /// Spec for [`TexturesPool`]
+    pub struct TexturesSpec;
+    impl ImagePoolSpec for TexturesSpec {
+        fn format(_context: &RenderingContext) -> Format {
+            Format::Rgba8Srgb
         }
+    }
 
-        fn from_context(context: &RenderingContext) -> Result<Arc<RwLock<Self>>> {
-            let type_mask = unsafe {
-                use hal::image::{Kind, Tiling, Usage, ViewCapabilities};
-
-                let device = context.lock_device()?;
-                let img = device
-                    .create_image(
-                        Kind::D2(16, 16, 1, 1),
-                        1,
-                        context.properties().depth_format,
-                        Tiling::Optimal,
-                        Usage::SAMPLED,
-                        SparseFlags::empty(),
-                        ViewCapabilities::empty(),
-                    )
-                    .context("Error creating test image to get buffer settings")?;
-
-                let type_mask = device.get_image_requirements(&img).type_mask;
-
-                device.destroy_image(img);
-
-                type_mask
-            };
+    /// Intended to be used for textures.
+    /// The allocated memory is guaranteed to be suitable for any colour image with optimal tiling and no extra sparse flags or view capabilities.
+    pub type TexturesPool = ImagePool<TexturesSpec>;
 
-            let allocator = {
-                let props = MemProps::DEVICE_LOCAL;
+    /// Spec for [`DepthBufferPool`]
+    pub struct DepthBufferSpec;
+    impl ImagePoolSpec for DepthBufferSpec {
+        fn format(context: &RenderingContext) -> Format {
+            context.properties().depth_format
+        }
+    }
 
-                DynamicAllocator::new(
-                    find_memory_type_id(context.adapter(), type_mask, props)
-                        .ok_or(EnvironmentError::NoMemoryTypes)?,
-                    props,
-                    DynamicConfig {
-                        block_size_granularity: 4 * 32 * 32, // 32x32 image
-                        max_chunk_size: u64::pow(2, 63),
-                        min_device_allocation: 4 * 32 * 32,
-                    },
-                    context
-                        .physical_device_properties()
-                        .limits
-                        .non_coherent_atom_size as u64,
-                )
-            };
+    /// Used for depth buffers.
+    /// Memory returned is guaranteed to be suitable for any image using `context.target_chain().properties().depth_format` with optimal tiling, and no sparse flags or view capabilities.
+    pub type DepthBufferPool = ImagePool<DepthBufferSpec>;
 
-            Ok(Arc::new(RwLock::new(Self(allocator))))
+    /// Spec for [`RenderTargetPool`]
+    pub struct RenderTargetSpec;
+    impl ImagePoolSpec for RenderTargetSpec {
+        fn format(context: &RenderingContext) -> Format {
+            context.properties().color_format
         }
 
-        fn deactivate(self, _context: &mut StatefulRenderingContext<DeactivatedMemoryPools>) {
-            self.0.dispose()
+        fn usage() -> hal::image::Usage {
+            hal::image::Usage::COLOR_ATTACHMENT
+                | hal::image::Usage::SAMPLED
+                | hal::image::Usage::TRANSFER_DST
+                | hal::image::Usage::TRANSFER_SRC
         }
     }
 
+    /// Used for offscreen colour images, which are rendered to by one pass and sampled by another.
+    /// Memory returned is guaranteed to be suitable for any image using the context's `color_format` with optimal tiling, and the usage given by [`RenderTargetSpec`].
+    pub type RenderTargetPool = ImagePool<RenderTargetSpec>;
+
     /// Used for staging buffers
-    pub struct StagingPool(DynamicAllocator<back::Backend>);
+    pub struct StagingPool(DynamicAllocator<back::Backend>, PoolStats);
     impl MemoryPool for StagingPool {
         type Block = MappableRBlock<DynamicBlock<back::Backend>>;
 
         fn alloc(&mut self, device: &DeviceT, size: u64, align: u64) -> Result<(Self::Block, u64)> {
-            let (b, size) = self.0.alloc(device, size, align)?;
-            Ok((MappableRBlock::new_unchecked(b), size))
+            let (b, allocated) = self.0.alloc(device, size, align)?;
+            self.1.record_alloc(Block::size(&b), allocated);
+            Ok((MappableRBlock::new(b)?, allocated))
         }
 
         fn free(&mut self, device: &DeviceT, block: Self::Block) -> u64 {
-            self.0.free(device, block.0)
+            let size = Block::size(&block);
+            let freed = self.0.free(device, block.0);
+            self.1.record_free(size, freed);
+            freed
         }
 
         fn from_context(context: &RenderingContext) -> Result<Arc<RwLock<Self>>> {
@@ -262,6 +323,18 @@ mod rendy {
                 let props = MemProps::CPU_VISIBLE | MemProps::COHERENT;
                 let t = find_memory_type_id(context.adapter(), u32::MAX, props)
                     .ok_or(EnvironmentError::NoMemoryTypes)?;
+
+                // Make sure the memory type we got can actually be mapped, as every block we give out will be
+                let props = context
+                    .adapter()
+                    .physical_device
+                    .memory_properties()
+                    .memory_types[t.0]
+                    .properties;
+                if !props.contains(MemProps::CPU_VISIBLE) {
+                    return Err(anyhow!(UsageError::NonMappableMemory));
+                }
+
                 DynamicAllocator::new(
                     t,
                     props,
@@ -277,25 +350,40 @@ mod rendy {
                 )
             };
 
-            Ok(Arc::new(RwLock::new(StagingPool(allocator))))
+            Ok(Arc::new(RwLock::new(StagingPool(
+                allocator,
+                PoolStats::default(),
+            ))))
         }
 
         fn deactivate(self, _context: &mut StatefulRenderingContext<DeactivatedMemoryPools>) {
             self.0.dispose()
         }
+
+        fn stats(&self) -> Option<PoolStats> {
+            Some(PoolStats {
+                chunks: self.0.chunk_count(),
+                ..self.1
+            })
+        }
     }
 
     /// Suitable for input data, such as vertices and indices.
-    pub struct DataPool(DynamicAllocator<back::Backend>);
+    pub struct DataPool(DynamicAllocator<back::Backend>, PoolStats);
     impl MemoryPool for DataPool {
         type Block = DynamicBlock<back::Backend>;
 
         fn alloc(&mut self, device: &DeviceT, size: u64, align: u64) -> Result<(Self::Block, u64)> {
-            Ok(self.0.alloc(device, size, align)?)
+            let (block, allocated) = self.0.alloc(device, size, align)?;
+            self.1.record_alloc(Block::size(&block), allocated);
+            Ok((block, allocated))
         }
 
         fn free(&mut self, device: &DeviceT, block: Self::Block) -> u64 {
-            self.0.free(device, block)
+            let size = Block::size(&block);
+            let freed = self.0.free(device, block);
+            self.1.record_free(size, freed);
+            freed
         }
 
         fn from_context(context: &RenderingContext) -> Result<Arc<RwLock<Self>>> {
@@ -318,12 +406,22 @@ mod rendy {
                 )
             };
 
-            Ok(Arc::new(RwLock::new(DataPool(allocator))))
+            Ok(Arc::new(RwLock::new(DataPool(
+                allocator,
+                PoolStats::default(),
+            ))))
         }
 
         fn deactivate(self, _context: &mut StatefulRenderingContext<DeactivatedMemoryPools>) {
             self.0.dispose()
         }
+
+        fn stats(&self) -> Option<PoolStats> {
+            Some(PoolStats {
+                chunks: self.0.chunk_count(),
+                ..self.1
+            })
+        }
     }
 
     /// A rendy memory block that is guaranteed to be CPU visible.
@@ -0,0 +1,78 @@
+//! Hooks for watching what the renderer is doing, for profiling or debugging.
+//!
+//! The renderer still logs through the `log` crate, but that's hard to do anything with besides print.
+//! Setting a [`RenderObserver`] with [`crate::RenderingContext::set_observer`] gives you each [`RenderEvent`] as it happens instead,
+//! which you can time, count, or forward to whatever tracing setup you already use.
+//! Texture loaders take the observer from the context when they're created, so it should be set before creating any draw passes.
+
+use std::{
+    sync::{RwLock, RwLockWriteGuard, TryLockError},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{error::LockPoisoned, types::DeviceT};
+
+/// Something the renderer did, given to a [`RenderObserver`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderEvent {
+    /// A texture loader started loading the block with the given index.
+    BlockLoadStarted(usize),
+
+    /// A texture loader finished loading the block with the given index, and is sending it back.
+    BlockLoadFinished(usize),
+
+    /// The surface and swapchain are about to be recreated.
+    SurfaceRecreateStarted,
+
+    /// The surface and swapchain were recreated successfully.
+    SurfaceRecreateFinished,
+
+    /// The device lock was held by another thread, so we waited the given time for it.
+    DeviceLockContended(Duration),
+}
+
+/// Receives events from the renderer.
+/// This is called from whichever thread the event happened on, including texture loader threads, so should return quickly.
+pub trait RenderObserver: Send + Sync {
+    /// Called once for each event, as it happens.
+    fn on_event(&self, event: RenderEvent);
+}
+
+impl<F: Fn(RenderEvent) + Send + Sync> RenderObserver for F {
+    fn on_event(&self, event: RenderEvent) {
+        self(event)
+    }
+}
+
+/// Tell the observer about an event, if there is one.
+pub(crate) fn notify(observer: Option<&dyn RenderObserver>, event: RenderEvent) {
+    if let Some(observer) = observer {
+        observer.on_event(event);
+    }
+}
+
+/// Lock the device for writing, telling the observer if we had to wait for another thread to release it.
+/// Without an observer, this is the same as locking it normally.
+pub(crate) fn lock_device_observed<'a>(
+    device: &'a RwLock<DeviceT>,
+    observer: Option<&dyn RenderObserver>,
+) -> Result<RwLockWriteGuard<'a, DeviceT>> {
+    let observer = match observer {
+        Some(x) => x,
+        None => return Ok(device.write().map_err(|_| LockPoisoned::Device)?),
+    };
+
+    match device.try_write() {
+        Ok(guard) => Ok(guard),
+        Err(TryLockError::Poisoned(_)) => Err(LockPoisoned::Device.into()),
+        Err(TryLockError::WouldBlock) => {
+            let start = Instant::now();
+            let guard = device.write().map_err(|_| LockPoisoned::Device)?;
+            observer.on_event(RenderEvent::DeviceLockContended(start.elapsed()));
+
+            Ok(guard)
+        }
+    }
+}
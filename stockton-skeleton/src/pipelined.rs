@@ -0,0 +1,266 @@
+//! Recording each frame's commands on a worker thread, while the main thread only begins and submits frames.
+//!
+//! [`PipelinedRenderer`] moves the draw pass onto a thread of its own.
+//! Each call to [`PipelinedRenderer::render`] submits the frame the worker recorded since the last call, then begins the next frame and sends it to the worker with a [`RenderSnapshot`] to draw from.
+//! This means the main thread can handle input and update the session while the next frame is being recorded, at the cost of a frame of latency.
+//!
+//! The worker draws from a session of its own, which only has what's in the snapshots it's given, so everything the draw pass reads from the session needs to be in the [`crate::session::SnapshotSpec`].
+//! Only one frame is pending at a time, so this doesn't need any more swapchain images than [`Renderer`] does.
+//! Each frame has its own command pool, which the worker only touches through the [`PendingFrame`] it's been sent, so recording never races with the main thread beginning or submitting frames.
+
+use std::{
+    mem::ManuallyDrop,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    context::PendingFrame,
+    draw_passes::Singular,
+    error::{DrawErrorKind, UsageError},
+    session::{RenderSnapshot, Session},
+    DrawPass, Renderer, RenderingContext,
+};
+
+/// A frame to be recorded, and the snapshot to record it from.
+type Job = (PendingFrame, RenderSnapshot);
+
+/// A frame that's been recorded, or the error from recording it.
+type Recorded = (PendingFrame, Result<()>);
+
+/// A thread that owns the draw pass, and records the frames sent to it in order.
+struct Worker<DP> {
+    jobs: Sender<Job>,
+    recorded: Receiver<Recorded>,
+    thread: JoinHandle<DP>,
+}
+
+impl<DP: DrawPass<Singular> + Send + 'static> Worker<DP> {
+    fn spawn(mut draw_pass: DP) -> Result<Self> {
+        let (jobs, job_rx) = channel::<Job>();
+        let (recorded_tx, recorded) = channel::<Recorded>();
+
+        let thread = thread::Builder::new()
+            .name("stockton-recording".into())
+            .spawn(move || {
+                // Sessions can't be sent between threads, so this one is only ever filled from snapshots.
+                let mut session = Session::new(|_| {});
+                for (mut frame, snapshot) in job_rx.iter() {
+                    snapshot.apply_to(&mut session);
+                    let result = frame.record(&session, &mut draw_pass);
+                    if result.is_err() {
+                        draw_pass.frame_cancelled(frame.context());
+                    }
+                    if recorded_tx.send((frame, result)).is_err() {
+                        break;
+                    }
+                }
+
+                draw_pass
+            })
+            .context("Error spawning recording thread")?;
+
+        Ok(Worker {
+            jobs,
+            recorded,
+            thread,
+        })
+    }
+
+    /// Stop the thread once it's done with any frames it's been sent, and take back the draw pass.
+    fn stop(self) -> Result<DP> {
+        drop(self.jobs);
+        self.thread
+            .join()
+            .map_err(|_| anyhow!(UsageError::RecordingThreadStopped))
+    }
+}
+
+/// Renders a world like [`Renderer`], but records each frame's commands on a worker thread. See the [module docs](self) for details.
+pub struct PipelinedRenderer<DP> {
+    /// All the vulkan stuff
+    context: ManuallyDrop<RenderingContext>,
+
+    /// The thread the draw pass is on. This is only None while the surface is being recreated, or if that failed.
+    worker: Option<Worker<DP>>,
+
+    /// Set when a frame has been sent to the worker, and not yet taken back.
+    recording: bool,
+
+    /// Set when an operation fails partway through, like [`Renderer`].
+    poisoned: bool,
+}
+
+impl<DP: DrawPass<Singular> + Send + 'static> PipelinedRenderer<DP> {
+    /// Move the renderer's draw pass onto a new worker thread.
+    pub fn new(renderer: Renderer<DP>) -> Result<Self> {
+        renderer.check_poisoned()?;
+
+        let Renderer {
+            context, draw_pass, ..
+        } = renderer;
+        let worker = Worker::spawn(ManuallyDrop::into_inner(draw_pass))?;
+
+        Ok(PipelinedRenderer {
+            context,
+            worker: Some(worker),
+            recording: false,
+            poisoned: false,
+        })
+    }
+
+    /// Submit the frame recorded since the last call, if there is one, then begin the next frame and send it to the worker to be recorded from `snapshot`.
+    /// This only blocks if the worker hasn't finished recording the last frame yet.
+    /// If this returns an error whose [`DrawErrorKind`] is a surface error, the surface should be recreated with [`Self::recreate_surface`].
+    /// Any other error poisons the renderer, after which it should just be dropped.
+    pub fn render(&mut self, snapshot: RenderSnapshot) -> Result<()> {
+        self.check_poisoned()?;
+
+        let result = self.render_inner(snapshot);
+        self.poison_unless_surface_error(&result);
+
+        result
+    }
+
+    fn render_inner(&mut self, snapshot: RenderSnapshot) -> Result<()> {
+        if let Some(frame) = self.take_recorded()? {
+            self.context.submit_frame(frame)?;
+        }
+
+        let frame = self.context.begin_frame()?;
+        let worker = self.worker.as_ref().ok_or(UsageError::RendererPoisoned)?;
+        if let Err(e) = worker.jobs.send((frame, snapshot)) {
+            let (frame, _) = e.0;
+            self.context.cancel_frame(frame);
+            return Err(UsageError::RecordingThreadStopped.into());
+        }
+        self.recording = true;
+
+        Ok(())
+    }
+
+    /// Wait for the worker to finish recording the last frame, if there is one, and submit it.
+    /// Errors are handled the same as [`Self::render`].
+    pub fn flush(&mut self) -> Result<()> {
+        self.check_poisoned()?;
+
+        let result = match self.take_recorded() {
+            Ok(Some(frame)) => self.context.submit_frame(frame),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        self.poison_unless_surface_error(&result);
+
+        result
+    }
+
+    /// Recreate the surface and other derived components. This should be called when the window is resized.
+    /// Any frame still being recorded is thrown away, and the draw pass is moved back to this thread while it handles the change.
+    /// The renderer is poisoned if this returns an error.
+    pub fn recreate_surface(&mut self, session: &Session) -> Result<()> {
+        self.check_poisoned()?;
+
+        // Safety: We stay poisoned unless we get to the end, so if this fails at any point the ManuallyDrop won't be touched again.
+        self.poisoned = true;
+        let cancelled = self.take_recorded()?;
+
+        let mut draw_pass = self
+            .worker
+            .take()
+            .ok_or(UsageError::RendererPoisoned)?
+            .stop()?;
+        if let Some(frame) = cancelled {
+            draw_pass.frame_cancelled(frame.context());
+            self.context.cancel_frame(frame);
+        }
+        unsafe {
+            let ctx = ManuallyDrop::take(&mut self.context).recreate_surface()?;
+            self.context = ManuallyDrop::new(ctx);
+        }
+        let draw_pass = draw_pass.handle_surface_change(session, &mut self.context)?;
+        self.worker = Some(Worker::spawn(draw_pass)?);
+        self.poisoned = false;
+
+        Ok(())
+    }
+
+    /// Submit any frame still being recorded, and move the draw pass back to this thread, giving a [`Renderer`] that records on the thread it's used from.
+    pub fn into_renderer(mut self) -> Result<Renderer<DP>> {
+        self.flush()?;
+
+        let PipelinedRenderer {
+            context, worker, ..
+        } = self;
+        let draw_pass = worker.ok_or(UsageError::RendererPoisoned)?.stop()?;
+
+        Ok(Renderer {
+            context,
+            draw_pass: ManuallyDrop::new(draw_pass),
+            poisoned: false,
+        })
+    }
+
+    /// Deactivate the draw pass and destroy the context, consuming the renderer. See [`Renderer::deactivate`].
+    pub fn deactivate(self) -> Result<()> {
+        self.into_renderer()?.deactivate()
+    }
+
+    /// Wait for the frame the worker is recording, if there is one, and take it back.
+    /// If recording it failed, the frame is cancelled and the error is returned.
+    fn take_recorded(&mut self) -> Result<Option<PendingFrame>> {
+        if !self.recording {
+            return Ok(None);
+        }
+
+        let (frame, result) = self
+            .worker
+            .as_ref()
+            .ok_or(UsageError::RendererPoisoned)?
+            .recorded
+            .recv()
+            .map_err(|_| UsageError::RecordingThreadStopped)?;
+        self.recording = false;
+
+        match result {
+            Ok(()) => Ok(Some(frame)),
+            Err(e) => {
+                self.context.cancel_frame(frame);
+                Err(e)
+            }
+        }
+    }
+
+    /// Poison the renderer if `result` is an error that can't be fixed by recreating the surface.
+    fn poison_unless_surface_error(&mut self, result: &Result<()>) {
+        if let Err(e) = result {
+            self.poisoned = !DrawErrorKind::of(e).is_surface_error();
+        }
+    }
+
+    /// Check if an earlier error has poisoned the renderer.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Return an error if the renderer is poisoned.
+    fn check_poisoned(&self) -> Result<()> {
+        match self.poisoned {
+            true => Err(anyhow!(UsageError::RendererPoisoned)),
+            false => Ok(()),
+        }
+    }
+
+    /// Get a reference to the renderer's context.
+    pub fn context(&self) -> &RenderingContext {
+        assert!(!self.poisoned, "{}", UsageError::RendererPoisoned);
+        &self.context
+    }
+
+    /// Get a mutable reference to the renderer's context.
+    pub fn context_mut(&mut self) -> &mut RenderingContext {
+        assert!(!self.poisoned, "{}", UsageError::RendererPoisoned);
+        &mut self.context
+    }
+}
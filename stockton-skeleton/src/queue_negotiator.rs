@@ -36,7 +36,7 @@ use crate::{
     types::*,
 };
 use anyhow::{bail, Error, Result};
-use hal::queue::family::QueueFamilyId;
+use hal::queue::{family::QueueFamilyId, QueueType};
 use std::{
     any::TypeId,
     collections::hash_map::{Entry, HashMap},
@@ -46,6 +46,43 @@ use std::{
 /// A queue, possibly shared between threads.
 pub type SharedQueue = Arc<RwLock<QueueT>>;
 
+/// What a queue family that was selected by the negotiator can do.
+/// Get this with [`crate::context::RenderingContext::queue_family_properties`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFamilyProperties {
+    /// The ID of the family
+    pub id: QueueFamilyId,
+
+    /// The types of work queues in this family support
+    pub queue_type: QueueType,
+
+    /// The maximum number of queues that can be created in this family
+    pub max_queues: usize,
+
+    /// Whether timestamp queries can be used on queues in this family.
+    /// The backend only tells us if all graphics and compute queues support them, so this is always false for other families.
+    pub supports_timestamps: bool,
+}
+
+impl QueueFamilyProperties {
+    /// Get the properties of the given family, using the device's limits to check timestamp support.
+    pub fn new(family: &QueueFamilyT, timestamp_compute_and_graphics: bool) -> Self {
+        let queue_type = family.queue_type();
+        QueueFamilyProperties {
+            id: family.id(),
+            queue_type,
+            max_queues: family.max_queues(),
+            supports_timestamps: timestamp_compute_and_graphics
+                && (queue_type.supports_graphics() || queue_type.supports_compute()),
+        }
+    }
+
+    /// Check if this family only supports transfer operations, meaning it's probably a dedicated transfer engine.
+    pub fn is_transfer_only(&self) -> bool {
+        self.queue_type == QueueType::Transfer
+    }
+}
+
 /// Used to find appropriate queue families during init phase.
 pub struct QueueFamilyNegotiator {
     /// Family and count being used for each selector
@@ -83,14 +120,15 @@ impl QueueFamilyNegotiator {
             return Err(Error::new(EnvironmentError::NoSuitableFamilies));
         }
 
-        // Prefer using unique families
-        let family = match candidates
+        // Prefer using unique families, then the family the selector prefers most
+        let family = *candidates
             .iter()
-            .find(|x| !self.family_ids.values().any(|y| y.1 == x.id()))
-        {
-            Some(x) => *x,
-            None => candidates[0],
-        };
+            .rev()
+            .max_by_key(|x| {
+                let unique = !self.family_ids.values().any(|y| y.1 == x.id());
+                (unique, filter.priority(x))
+            })
+            .unwrap();
 
         self.family_ids
             .insert(TypeId::of::<T>(), (count, family.id()));
@@ -163,6 +201,12 @@ pub struct QueueNegotiator {
 pub trait QueueFamilySelector: 'static {
     /// Return true if the given family is suitable
     fn is_suitable(&self, family: &QueueFamilyT) -> bool;
+
+    /// How much this family should be preferred over other suitable ones. Higher is better.
+    /// Families not already used by other selectors are always picked first.
+    fn priority(&self, _family: &QueueFamilyT) -> usize {
+        0
+    }
 }
 
 impl QueueNegotiator {
@@ -1,7 +1,24 @@
 //! The thing you play on and all the associated state.
+//!
+//! To run updates and drawing at the same time, keep the session being updated on its own thread, and after each update take a [`RenderSnapshot`] of the components and resources draw passes need with [`Session::snapshot`].
+//! Snapshots can be sent to the thread the renderer is on, and put into a session used only for drawing with [`RenderSnapshot::apply_to`], while the next update runs.
+//! Entities keep their ids in snapshots, so draw passes can keep referring to entities by id.
 
-use legion::systems::Builder;
+use crate::error::UsageError;
+
+use std::{
+    any::type_name,
+    ops::{Deref, DerefMut, Range},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Result;
+use legion::systems::{Builder, Resource};
 use legion::*;
+use legion::{
+    storage::{Archetype, ArchetypeWriter, Component, Components, EntityLayout},
+    world::{Allocate, Duplicate, Merger},
+};
 
 /// A loaded world.
 pub struct Session {
@@ -30,4 +47,204 @@ impl Session {
     pub fn do_update(&mut self) {
         self.schedule.execute(&mut self.world, &mut self.resources);
     }
+
+    /// Get the resource of the given type, or a [`UsageError::ResourceNotFound`] if it's not there.
+    pub fn resource<T: Resource>(&self) -> Result<impl Deref<Target = T> + '_> {
+        Ok(self
+            .resources
+            .get::<T>()
+            .ok_or(UsageError::ResourceNotFound(type_name::<T>()))?)
+    }
+
+    /// Get the resource of the given type mutably, or a [`UsageError::ResourceNotFound`] if it's not there.
+    pub fn resource_mut<T: Resource>(&mut self) -> Result<impl DerefMut<Target = T> + '_> {
+        Ok(self
+            .resources
+            .get_mut::<T>()
+            .ok_or(UsageError::ResourceNotFound(type_name::<T>()))?)
+    }
+
+    /// Get a clone of a shared resource stored as an `Arc<RwLock<T>>`, so it can be kept after the session is borrowed.
+    pub fn get_cloned_arc<T>(&self) -> Result<Arc<RwLock<T>>>
+    where
+        Arc<RwLock<T>>: Resource,
+    {
+        Ok(self.resource::<Arc<RwLock<T>>>()?.clone())
+    }
+
+    /// Copy the components and resources given by `spec` into a snapshot, which can be drawn from on another thread.
+    /// Components and resources not in `spec` are left out, though every entity is still copied.
+    pub fn snapshot(&self, spec: &mut SnapshotSpec) -> RenderSnapshot {
+        let mut world = World::default();
+        world.clone_from(&self.world, &any(), &mut spec.merger);
+
+        let resources = spec
+            .resources
+            .iter()
+            .filter_map(|copy| copy(&self.resources))
+            .collect();
+
+        RenderSnapshot { world, resources }
+    }
+}
+
+/// Puts a copy of a resource into another set of resources.
+type ResourceInserter = Box<dyn FnOnce(&mut Resources) + Send>;
+
+/// Which components and resources are copied into a [`RenderSnapshot`].
+/// This should usually be created once, and reused for every snapshot.
+pub struct SnapshotSpec {
+    merger: KeepIds,
+    resources: Vec<fn(&Resources) -> Option<ResourceInserter>>,
+}
+
+impl SnapshotSpec {
+    /// Create a spec that copies nothing but the entities themselves.
+    pub fn new() -> Self {
+        SnapshotSpec {
+            merger: KeepIds(Duplicate::default()),
+            resources: vec![],
+        }
+    }
+
+    /// Copy components of the given type into snapshots.
+    pub fn with_component<T: Component + Clone>(mut self) -> Self {
+        self.merger.0.register_clone::<T>();
+        self
+    }
+
+    /// Copy the resource of the given type into snapshots, if it's present.
+    pub fn with_resource<T: Resource + Clone + Send>(mut self) -> Self {
+        self.resources.push(copy_resource::<T>);
+        self
+    }
+}
+
+impl Default for SnapshotSpec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copies the registered components like [`Duplicate`], but gives each entity the same id it had in the source world.
+/// Entity ids are allocated globally, so they can't clash with entities already in the snapshot's world.
+struct KeepIds(Duplicate);
+
+impl Merger for KeepIds {
+    fn assign_id(&mut self, existing: Entity, _allocator: &mut Allocate) -> Entity {
+        existing
+    }
+
+    fn convert_layout(&mut self, source_layout: EntityLayout) -> EntityLayout {
+        self.0.convert_layout(source_layout)
+    }
+
+    fn merge_archetype(
+        &mut self,
+        src_entity_range: Range<usize>,
+        src_arch: &Archetype,
+        src_components: &Components,
+        dst: &mut ArchetypeWriter,
+    ) {
+        self.0
+            .merge_archetype(src_entity_range, src_arch, src_components, dst)
+    }
+}
+
+/// Copy the resource of type `T`, if it's present.
+fn copy_resource<T: Resource + Clone + Send>(resources: &Resources) -> Option<ResourceInserter> {
+    let copy = resources.get::<T>()?.clone();
+
+    Some(Box::new(move |resources: &mut Resources| {
+        resources.insert(copy)
+    }))
+}
+
+/// A copy of the state needed for drawing, taken from a session after an update with [`Session::snapshot`].
+/// Unlike a session, this can be sent between threads.
+pub struct RenderSnapshot {
+    world: World,
+    resources: Vec<ResourceInserter>,
+}
+
+impl RenderSnapshot {
+    /// Put the snapshot into the given session, replacing its world and any resources of the same types.
+    /// Any other resources in the session are left alone, so draw passes can keep resources of their own there.
+    pub fn apply_to(self, session: &mut Session) {
+        session.world = self.world;
+        for insert in self.resources {
+            insert(&mut session.resources);
+        }
+    }
+
+    /// Turn the snapshot into a new session with no systems, for drawing from.
+    pub fn into_session(self) -> Session {
+        let mut session = Session::new(|_| {});
+        self.apply_to(&mut session);
+
+        session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Position(f32, f32);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Velocity(f32, f32);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct FrameCount(u32);
+
+    #[test]
+    fn snapshot_keeps_entity_ids() {
+        let mut session = Session::new(|_| {});
+        let a = session.world.push((Position(1.0, 2.0), Velocity(0.0, 1.0)));
+        let b = session.world.push((Position(3.0, 4.0),));
+        session.resources.insert(FrameCount(7));
+
+        let mut spec = SnapshotSpec::new()
+            .with_component::<Position>()
+            .with_resource::<FrameCount>();
+
+        let mut drawing = Session::new(|_| {});
+        session.snapshot(&mut spec).apply_to(&mut drawing);
+
+        let entry = drawing.world.entry_ref(a).unwrap();
+        assert_eq!(entry.get_component::<Position>(), Ok(&Position(1.0, 2.0)));
+        assert!(entry.get_component::<Velocity>().is_err());
+        assert_eq!(
+            drawing
+                .world
+                .entry_ref(b)
+                .unwrap()
+                .get_component::<Position>(),
+            Ok(&Position(3.0, 4.0))
+        );
+        assert_eq!(*drawing.resource::<FrameCount>().unwrap(), FrameCount(7));
+
+        // Applying a later snapshot replaces the world, and keeps the ids the same again
+        session
+            .world
+            .entry(a)
+            .unwrap()
+            .get_component_mut::<Position>()
+            .map(|pos| pos.0 = 5.0)
+            .unwrap();
+        session.world.remove(b);
+        session.snapshot(&mut spec).apply_to(&mut drawing);
+
+        assert_eq!(
+            drawing
+                .world
+                .entry_ref(a)
+                .unwrap()
+                .get_component::<Position>(),
+            Ok(&Position(5.0, 2.0))
+        );
+        assert!(drawing.world.entry_ref(b).is_err());
+    }
 }
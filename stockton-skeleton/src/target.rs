@@ -1,9 +1,26 @@
 //! Resources needed for drawing on the screen, including sync objects
 //! You likely won't need to interact with this directly
+//!
+//! Drawing a frame is split into three steps:
+//!  1. [`TargetChain::begin_frame`] acquires an image, waits for the last use of that frame's resources to finish, and resets its command pool.
+//!  2. [`PendingFrame::record`] records the draw pass's commands into the frame's command buffer.
+//!  3. [`TargetChain::submit_frame`] submits the command buffer and presents the image.
+//!
+//! Each frame has its own command pool, and the [`PendingFrame`] owns the command buffer until it's submitted.
+//! This means recording one frame never touches a pool used by another, so the pool for a frame only needs to be externally synchronised between steps 1 and 3 of that frame,
+//! which is guaranteed by the [`PendingFrame`] being moved through them.
+//! The `present_complete` fence of a frame is only waited on in step 1, so the CPU can begin the next frame while the GPU is still working on the last.
+//!
+//! Step 2 can happen on another thread, as a [`PendingFrame`] can be sent between threads and recording only touches its own command buffer.
+//! Steps 1 and 3 need the device and queue locks and mutable access to the [`TargetChain`], so stay on whichever thread owns the context.
+//! [`crate::pipelined::PipelinedRenderer`] uses this to record on a worker thread while the main thread only begins and submits frames.
+//! Note that having more than one frame pending at once needs at least one more swapchain image than the surface's minimum, or acquiring may block forever.
 
 use crate::{
+    backend as back,
     context::ContextProperties,
-    draw_passes::{DrawPass, Singular},
+    draw_passes::{util::queue_draw_checked, DrawPass, FrameContext, Singular},
+    error::DrawErrorKind,
     session::Session,
     types::*,
 };
@@ -16,29 +33,124 @@ use std::{
 
 use hal::{
     command::CommandBufferFlags,
-    image::Usage as ImgUsage,
+    pool::CommandPoolCreateFlags,
+    pso::Rect,
+    queue::QueueFamilyId,
     window::{Extent2D, SwapchainConfig},
 };
 
 use anyhow::{Context, Result};
 
+/// A swapchain image that has been acquired but not yet presented
+type SwapchainImageT = <SurfaceT as PresentationSurface<back::Backend>>::SwapchainImage;
+
 /// Holds our swapchain and other resources for drawing each frame
 pub struct TargetChain {
     /// Surface we're targeting
     surface: ManuallyDrop<SurfaceT>,
 
-    /// Command buffers and sync objects used when drawing
-    resources: Box<[(CommandBufferT, SyncObjects)]>,
+    /// Command pools, buffers and sync objects used when drawing
+    resources: Box<[FrameResources]>,
 
     /// Last image index of the swapchain drawn to
     last_resources: usize,
+
+    /// The whole area of the swapchain images
+    viewport: Rect,
+}
+
+/// The resources used to draw a single frame
+struct FrameResources {
+    /// The pool `cmd_buffer` is allocated from. Nothing else is allocated from this.
+    cmd_pool: ManuallyDrop<CommandPoolT>,
+
+    /// The command buffer to record into, or None if it's currently owned by a [`PendingFrame`]
+    cmd_buffer: Option<CommandBufferT>,
+
+    /// Sync objects for this frame
+    syncs: SyncObjects,
+}
+
+impl FrameResources {
+    fn new(device: &mut DeviceT, family: QueueFamilyId) -> Result<Self> {
+        let mut cmd_pool =
+            unsafe { device.create_command_pool(family, CommandPoolCreateFlags::TRANSIENT) }
+                .context("Error creating draw command pool")?;
+
+        let syncs = match SyncObjects::new(device).context("Error creating sync objects") {
+            Ok(s) => s,
+            Err(e) => {
+                unsafe { device.destroy_command_pool(cmd_pool) };
+                return Err(e);
+            }
+        };
+
+        let cmd_buffer = unsafe { cmd_pool.allocate_one(hal::command::Level::Primary) };
+
+        Ok(FrameResources {
+            cmd_pool: ManuallyDrop::new(cmd_pool),
+            cmd_buffer: Some(cmd_buffer),
+            syncs,
+        })
+    }
+
+    fn deactivate(mut self, device: &mut DeviceT) {
+        unsafe {
+            // Destroying the pool frees any buffers allocated from it, including ones still owned by a pending frame.
+            device.destroy_command_pool(ManuallyDrop::take(&mut self.cmd_pool));
+        }
+        self.syncs.deactivate(device);
+    }
+}
+
+/// A frame that has had its image acquired, but hasn't been submitted yet.
+/// This owns the frame's command buffer, and must be given back with [`crate::RenderingContext::submit_frame`] or [`crate::RenderingContext::cancel_frame`].
+/// It can be sent to another thread to be recorded, as long as it's sent back to be submitted.
+pub struct PendingFrame {
+    /// The index of the resources this frame is using
+    idx: usize,
+
+    /// The image being drawn to
+    image: SwapchainImageT,
+
+    /// The command buffer being recorded into
+    cmd_buffer: CommandBufferT,
+
+    /// Information about the frame, passed to draw passes
+    frame: FrameContext,
+}
+
+impl PendingFrame {
+    /// Information about the frame, which is given to the draw pass when it's recorded.
+    pub fn context(&self) -> FrameContext {
+        self.frame
+    }
+
+    /// Record the draw pass's commands for this frame. This doesn't need access to the [`TargetChain`] it came from.
+    pub fn record<DP: DrawPass<Singular>>(&mut self, session: &Session, dp: &mut DP) -> Result<()> {
+        unsafe {
+            self.cmd_buffer
+                .begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+
+            let image: &ImageViewT = self.image.borrow();
+            let (cmd_buffer, frame) = (&mut self.cmd_buffer, self.frame);
+            queue_draw_checked(dp.name(), || {
+                dp.queue_draw(session, image, cmd_buffer, frame)
+            })
+            .context("Error in draw pass")?;
+
+            self.cmd_buffer.finish();
+        }
+
+        Ok(())
+    }
 }
 
 impl TargetChain {
     pub fn new(
         device: &mut DeviceT,
         mut surface: SurfaceT,
-        cmd_pool: &mut CommandPoolT,
+        family: QueueFamilyId,
         properties: &ContextProperties,
     ) -> Result<TargetChain> {
         // Create swapchain
@@ -52,105 +164,177 @@ impl TargetChain {
             },
             image_count: properties.image_count,
             image_layers: 1,
-            image_usage: ImgUsage::COLOR_ATTACHMENT,
+            image_usage: properties.color_usage,
         };
 
-        // Create command buffers and sync objects
-        let mut resources = Vec::with_capacity(swap_config.image_count as usize);
-
-        for _ in 0..swap_config.image_count {
-            resources.push((
-                unsafe { cmd_pool.allocate_one(hal::command::Level::Primary) },
-                SyncObjects::new(device).context("Error creating sync objects")?,
-            ));
+        // Create command pools, buffers and sync objects
+        let mut resources: Vec<FrameResources> =
+            Vec::with_capacity(properties.frames_in_flight as usize);
+
+        for _ in 0..properties.frames_in_flight {
+            match FrameResources::new(device, family) {
+                Ok(r) => resources.push(r),
+                Err(e) => {
+                    for r in resources {
+                        r.deactivate(device);
+                    }
+                    return Err(e);
+                }
+            }
         }
 
         // Configure Swapchain
         unsafe {
-            surface
-                .configure_swapchain(device, swap_config)
-                .context("Error configuring swapchain")?;
+            if let Err(e) = surface.configure_swapchain(device, swap_config) {
+                for r in resources {
+                    r.deactivate(device);
+                }
+                return Err(e).with_context(|| {
+                    format!(
+                        "Error configuring swapchain ({}x{}, {} images, {:?}, {:?})",
+                        properties.extent.width,
+                        properties.extent.height,
+                        properties.image_count,
+                        properties.color_format,
+                        properties.present_mode
+                    )
+                });
+            }
         }
 
         Ok(TargetChain {
             surface: ManuallyDrop::new(surface),
             resources: resources.into_boxed_slice(),
-            last_resources: (properties.image_count - 1) as usize, // This means the next one to be used is index 0
+            last_resources: (properties.frames_in_flight - 1) as usize, // This means the next one to be used is index 0
+            viewport: properties.extent.rect(),
         })
     }
 
-    pub fn deactivate(
-        self,
-        instance: &mut InstanceT,
-        device: &mut DeviceT,
-        cmd_pool: &mut CommandPoolT,
-    ) {
-        let surface = self.deactivate_with_recyling(device, cmd_pool);
+    pub fn deactivate(self, instance: &mut InstanceT, device: &mut DeviceT) {
+        let surface = self.deactivate_with_recyling(device);
 
         unsafe {
             instance.destroy_surface(surface);
         }
     }
 
-    pub fn deactivate_with_recyling(
-        mut self,
-        device: &mut DeviceT,
-        cmd_pool: &mut CommandPoolT,
-    ) -> SurfaceT {
-        use core::ptr::read;
-        unsafe {
-            for i in 0..self.resources.len() {
-                let (cmd_buf, syncs) = read(&self.resources[i]);
-                cmd_pool.free(once(cmd_buf));
-                syncs.deactivate(device);
-            }
+    pub fn deactivate_with_recyling(self, device: &mut DeviceT) -> SurfaceT {
+        let TargetChain {
+            mut surface,
+            resources,
+            ..
+        } = self;
 
-            self.surface.unconfigure_swapchain(device);
+        for r in Vec::from(resources) {
+            r.deactivate(device);
         }
 
-        unsafe { ManuallyDrop::into_inner(read(&self.surface)) }
+        unsafe {
+            surface.unconfigure_swapchain(device);
+            ManuallyDrop::into_inner(surface)
+        }
     }
 
-    pub fn do_draw_with<'a, DP: DrawPass<Singular>>(
-        &'a mut self,
+    /// Draw a whole frame, recording and submitting it straight away.
+    /// Returns the frame context the draw pass was given.
+    pub fn do_draw_with<DP: DrawPass<Singular>>(
+        &mut self,
         device: &mut DeviceT,
         command_queue: &mut QueueT,
         dp: &mut DP,
         session: &Session,
-    ) -> Result<()> {
-        self.last_resources = (self.last_resources + 1) % self.resources.len();
+        frame_number: u64,
+    ) -> Result<FrameContext> {
+        let mut frame = self.begin_frame(device, frame_number)?;
+        if let Err(e) = frame.record(session, dp) {
+            dp.frame_cancelled(frame.frame);
+            self.cancel_frame(frame);
+            return Err(e);
+        }
 
-        let (cmd_buffer, syncs) = &mut self.resources[self.last_resources];
+        let context = frame.frame;
+        self.submit_frame(device, command_queue, frame)?;
 
-        // Get the image
-        let (img, _) = unsafe {
-            self.surface
-                .acquire_image(core::u64::MAX)
-                .context("Error getting image from swapchain")?
+        Ok(context)
+    }
+
+    /// Acquire the next image and get the resources for drawing to it ready.
+    /// The returned frame must be passed back to [`Self::submit_frame`] or [`Self::cancel_frame`] before this is called for the same resources again.
+    /// `frame_number` is passed on to draw passes in the frame's [`FrameContext`].
+    pub fn begin_frame(&mut self, device: &mut DeviceT, frame_number: u64) -> Result<PendingFrame> {
+        let idx = (self.last_resources + 1) % self.resources.len();
+        let resources = &mut self.resources[idx];
+        let mut cmd_buffer = resources
+            .cmd_buffer
+            .take()
+            .context("Resources for the next frame are still in use by a pending frame")?;
+
+        let frame = FrameContext {
+            frame_index: idx,
+            frame_count: self.resources.len(),
+            frame_number,
+            viewport: self.viewport,
         };
 
-        // Make sure whatever was last using this has finished
-        unsafe {
-            device
-                .wait_for_fence(&syncs.present_complete, core::u64::MAX)
-                .context("Error waiting for present_complete")?;
-            device
-                .reset_fence(&mut syncs.present_complete)
-                .context("Error resetting present_complete fence")?;
+        // Get the image
+        let image = unsafe { self.surface.acquire_image(core::u64::MAX) };
+        let image = match image {
+            Ok((img, _)) => img,
+            Err(e) => {
+                resources.cmd_buffer = Some(cmd_buffer);
+                let kind = DrawErrorKind::from(&e);
+                return Err(anyhow::Error::new(e)
+                    .context(kind)
+                    .context("Error getting image from swapchain"));
+            }
         };
 
-        // Record commands
+        // Make sure whatever was last using this has finished, then reset the pool.
+        // Once the fence is signalled, the GPU is done with the command buffer, and nothing on the CPU side has it, so we have exclusive access to the pool.
+        // The fence itself is only reset right before submitting, so that a cancelled frame leaves it signalled.
+        if let Err(e) =
+            unsafe { device.wait_for_fence(&resources.syncs.present_complete, core::u64::MAX) }
+        {
+            resources.cmd_buffer = Some(cmd_buffer);
+            return Err(e).context("Error waiting for present_complete");
+        }
         unsafe {
-            cmd_buffer.begin_primary(CommandBufferFlags::empty());
+            resources.cmd_pool.reset(false);
+        }
 
-            dp.queue_draw(session, img.borrow(), cmd_buffer)
-                .context("Error in draw pass")?;
+        self.last_resources = idx;
 
-            cmd_buffer.finish();
-        }
+        Ok(PendingFrame {
+            idx,
+            image,
+            cmd_buffer,
+            frame,
+        })
+    }
+
+    /// Submit a recorded frame and present it.
+    /// Frames should be submitted in the order they were begun.
+    pub fn submit_frame(
+        &mut self,
+        device: &mut DeviceT,
+        command_queue: &mut QueueT,
+        frame: PendingFrame,
+    ) -> Result<()> {
+        let PendingFrame {
+            idx,
+            image,
+            cmd_buffer,
+            ..
+        } = frame;
+        let resources = &mut self.resources[idx];
+        let cmd_buffer = resources.cmd_buffer.insert(cmd_buffer);
+        let syncs = &mut resources.syncs;
 
         // Submit it
         unsafe {
+            device
+                .reset_fence(&mut syncs.present_complete)
+                .context("Error resetting present_complete fence")?;
             command_queue.submit(
                 once(&*cmd_buffer),
                 empty(),
@@ -158,12 +342,24 @@ impl TargetChain {
                 Some(&mut syncs.present_complete),
             );
             command_queue
-                .present(&mut self.surface, img, Some(&mut *syncs.render_complete))
-                .context("Error presenting to surface")?;
+                .present(&mut self.surface, image, Some(&mut *syncs.render_complete))
+                .map_err(|e| {
+                    let kind = DrawErrorKind::from(&e);
+                    anyhow::Error::new(e)
+                        .context(kind)
+                        .context("Error presenting to surface")
+                })?;
         };
 
         Ok(())
     }
+
+    /// Give back a frame's resources without submitting it, for example because recording failed.
+    /// The image won't be presented, so the swapchain will most likely need to be recreated.
+    /// Whoever recorded the frame should also tell its draw pass with [`DrawPass::frame_cancelled`].
+    pub fn cancel_frame(&mut self, frame: PendingFrame) {
+        self.resources[frame.idx].cmd_buffer = Some(frame.cmd_buffer);
+    }
 }
 
 pub struct SyncObjects {
@@ -0,0 +1,338 @@
+//! Packing many small textures into a few larger atlas images, so fewer descriptor sets need to be bound.
+
+use super::{LoadableImage, TextureResolver, PIXEL_SIZE};
+
+use std::{cmp::Reverse, collections::HashMap};
+
+use hal::image::SamplerDesc;
+use image::RgbaImage;
+
+/// Configuration for packing textures into atlases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasConfig {
+    /// The width and height of each atlas image
+    pub size: u32,
+
+    /// Textures wider or taller than this won't be packed, and will be loaded on their own instead.
+    pub max_texture_size: u32,
+
+    /// Empty pixels to leave around each packed texture, to stop filtering from bleeding between them.
+    pub padding: u32,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        AtlasConfig {
+            size: 2048,
+            max_texture_size: 256,
+            padding: 1,
+        }
+    }
+}
+
+/// Where a texture ended up after packing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasLocation {
+    /// The id of the texture to load and bind, which is either an atlas or the texture on its own.
+    pub texture_id: u32,
+
+    /// The top left of the texture in the atlas, in UV coordinates.
+    pub offset: [f32; 2],
+
+    /// The size of the texture in the atlas, in UV coordinates.
+    pub scale: [f32; 2],
+}
+
+impl AtlasLocation {
+    /// The location of a texture that isn't in an atlas, and so covers the whole image.
+    pub fn whole(texture_id: u32) -> Self {
+        AtlasLocation {
+            texture_id,
+            offset: [0.0, 0.0],
+            scale: [1.0, 1.0],
+        }
+    }
+
+    /// Convert UV coordinates for the original texture into coordinates in the atlas.
+    /// Packed textures can't wrap, so coordinates outside of 0..1 should be wrapped before this, or in the shader using `offset` and `scale`.
+    pub fn transform_uv(&self, uv: [f32; 2]) -> [f32; 2] {
+        [
+            self.offset[0] + uv[0] * self.scale[0],
+            self.offset[1] + uv[1] * self.scale[1],
+        ]
+    }
+}
+
+/// Packs rectangles into a fixed size area, keeping track of the highest used point of each horizontal span.
+struct SkylinePacker {
+    width: u32,
+    height: u32,
+
+    /// Spans of the skyline as `(x, y, width)`, from left to right.
+    skyline: Vec<(u32, u32, u32)>,
+}
+
+impl SkylinePacker {
+    fn new(width: u32, height: u32) -> Self {
+        SkylinePacker {
+            width,
+            height,
+            skyline: vec![(0, 0, width)],
+        }
+    }
+
+    /// Find a place for a rectangle of the given size, returning its top left corner if it fits.
+    fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        // Find the span to start at that leaves the rectangle lowest, breaking ties by the least wasted width.
+        let mut best: Option<(usize, u32, u32)> = None;
+        for i in 0..self.skyline.len() {
+            if let Some(y) = self.fits_at(i, w, h) {
+                let span_w = self.skyline[i].2;
+                let better = match best {
+                    Some((_, by, bw)) => y < by || (y == by && span_w < bw),
+                    None => true,
+                };
+                if better {
+                    best = Some((i, y, span_w));
+                }
+            }
+        }
+
+        let (i, y, _) = best?;
+        let x = self.skyline[i].0;
+        self.add_span(i, x, y + h, w);
+
+        Some((x, y))
+    }
+
+    /// Get the y position a rectangle would be placed at if its left edge was at span `i`, or None if it doesn't fit there.
+    fn fits_at(&self, i: usize, w: u32, h: u32) -> Option<u32> {
+        let x = self.skyline[i].0;
+        if x + w > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = w as i64;
+        for &(_, span_y, span_w) in &self.skyline[i..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(span_y);
+            remaining -= span_w as i64;
+        }
+
+        if y + h > self.height {
+            None
+        } else {
+            Some(y)
+        }
+    }
+
+    /// Raise the skyline to `y` between `x` and `x + w`, starting at span `i`.
+    fn add_span(&mut self, i: usize, x: u32, y: u32, w: u32) {
+        self.skyline.insert(i, (x, y, w));
+
+        // Shrink or remove the spans now covered by the new one
+        let right = x + w;
+        let j = i + 1;
+        while j < self.skyline.len() {
+            let (sx, sy, sw) = self.skyline[j];
+            if sx >= right {
+                break;
+            }
+            if sx + sw <= right {
+                self.skyline.remove(j);
+            } else {
+                self.skyline[j] = (right, sy, sx + sw - right);
+                break;
+            }
+        }
+
+        // Merge neighbouring spans at the same height
+        let mut k = 0;
+        while k + 1 < self.skyline.len() {
+            if self.skyline[k].1 == self.skyline[k + 1].1 {
+                self.skyline[k].2 += self.skyline[k + 1].2;
+                self.skyline.remove(k + 1);
+            } else {
+                k += 1;
+            }
+        }
+    }
+}
+
+/// An image given out by an [`AtlasResolver`], either a whole atlas or a texture that wasn't packed.
+pub enum AtlasImage<I> {
+    Atlas(RgbaImage),
+    Single(I),
+}
+
+impl<I: LoadableImage> LoadableImage for AtlasImage<I> {
+    fn width(&self) -> u32 {
+        match self {
+            AtlasImage::Atlas(i) => LoadableImage::width(i),
+            AtlasImage::Single(i) => i.width(),
+        }
+    }
+
+    fn height(&self) -> u32 {
+        match self {
+            AtlasImage::Atlas(i) => LoadableImage::height(i),
+            AtlasImage::Single(i) => i.height(),
+        }
+    }
+
+    unsafe fn copy_row(&self, y: u32, ptr: *mut u8) {
+        match self {
+            AtlasImage::Atlas(i) => i.copy_row(y, ptr),
+            AtlasImage::Single(i) => i.copy_row(y, ptr),
+        }
+    }
+}
+
+/// Wraps another resolver, packing the small textures it gives into atlases.
+/// Texture ids given to this resolver are ids of atlases followed by the textures too big to pack, so use [`AtlasResolver::locations`] to find where each original texture went.
+pub struct AtlasResolver<R: TextureResolver> {
+    inner: R,
+
+    /// The packed atlases, given out as the first ids
+    atlases: Vec<RgbaImage>,
+
+    /// The original ids of textures that weren't packed, given out after the atlases
+    singles: Vec<u32>,
+
+    /// Where each original texture can be found
+    locations: HashMap<u32, AtlasLocation>,
+}
+
+impl<R: TextureResolver> AtlasResolver<R> {
+    /// Resolve all of the given textures from `inner` and pack them into atlases.
+    /// This resolves every texture straight away, blocking until it's done, and ignoring [`TextureResolver::is_ready`].
+    /// Textures that are too big to pack will be resolved again when they're loaded. Textures that can't be resolved are left out.
+    pub fn new(mut inner: R, ids: impl IntoIterator<Item = u32>, config: &AtlasConfig) -> Self {
+        let mut small = Vec::new();
+        let mut large = Vec::new();
+        for id in ids {
+            if let Some(img) = inner.resolve(id) {
+                if img.width() <= config.max_texture_size && img.height() <= config.max_texture_size
+                {
+                    small.push((id, img));
+                } else {
+                    large.push(id);
+                }
+            }
+        }
+
+        // Tallest first packs better with a skyline
+        small.sort_by_key(|(_, img)| Reverse(img.height()));
+
+        let mut atlases: Vec<(SkylinePacker, RgbaImage)> = Vec::new();
+        let mut placements = Vec::with_capacity(small.len());
+        for (id, img) in small.iter() {
+            let w = img.width() + config.padding * 2;
+            let h = img.height() + config.padding * 2;
+
+            let placed = atlases
+                .iter_mut()
+                .enumerate()
+                .find_map(|(i, (packer, _))| packer.pack(w, h).map(|pos| (i, pos)));
+            let (atlas_idx, (x, y)) = match placed {
+                Some(p) => p,
+                None => {
+                    let mut packer = SkylinePacker::new(config.size, config.size);
+                    let pos = match packer.pack(w, h) {
+                        Some(pos) => pos,
+                        None => {
+                            // Too big for an empty atlas, likely because of padding
+                            large.push(*id);
+                            continue;
+                        }
+                    };
+                    atlases.push((packer, RgbaImage::new(config.size, config.size)));
+                    (atlases.len() - 1, pos)
+                }
+            };
+
+            let (x, y) = (x + config.padding, y + config.padding);
+            copy_into_atlas(img, &mut atlases[atlas_idx].1, x, y);
+            placements.push((*id, atlas_idx, x, y, img.width(), img.height()));
+        }
+
+        let size = config.size as f32;
+        let mut locations = HashMap::with_capacity(placements.len() + large.len());
+        for (id, atlas_idx, x, y, w, h) in placements {
+            locations.insert(
+                id,
+                AtlasLocation {
+                    texture_id: atlas_idx as u32,
+                    offset: [x as f32 / size, y as f32 / size],
+                    scale: [w as f32 / size, h as f32 / size],
+                },
+            );
+        }
+        for (i, id) in large.iter().enumerate() {
+            locations.insert(*id, AtlasLocation::whole((atlases.len() + i) as u32));
+        }
+
+        AtlasResolver {
+            inner,
+            atlases: atlases.into_iter().map(|(_, img)| img).collect(),
+            singles: large,
+            locations,
+        }
+    }
+
+    /// Where each of the original textures ended up.
+    pub fn locations(&self) -> &HashMap<u32, AtlasLocation> {
+        &self.locations
+    }
+
+    /// The number of atlases that textures were packed into.
+    pub fn atlas_count(&self) -> usize {
+        self.atlases.len()
+    }
+
+    /// Get the original id of the given unpacked texture, if it is one.
+    fn single_id(&self, texture_id: u32) -> Option<u32> {
+        let idx = (texture_id as usize).checked_sub(self.atlases.len())?;
+        self.singles.get(idx).copied()
+    }
+}
+
+impl<R: TextureResolver> TextureResolver for AtlasResolver<R> {
+    type Image = AtlasImage<R::Image>;
+
+    fn resolve(&mut self, texture_id: u32) -> Option<Self::Image> {
+        if let Some(atlas) = self.atlases.get(texture_id as usize) {
+            return Some(AtlasImage::Atlas(atlas.clone()));
+        }
+
+        let id = self.single_id(texture_id)?;
+        self.inner.resolve(id).map(AtlasImage::Single)
+    }
+
+    fn is_ready(&mut self, texture_id: u32) -> bool {
+        match self.single_id(texture_id) {
+            Some(id) => self.inner.is_ready(id),
+            None => true,
+        }
+    }
+
+    fn sampler(&mut self, texture_id: u32) -> Option<SamplerDesc> {
+        let id = self.single_id(texture_id)?;
+        self.inner.sampler(id)
+    }
+}
+
+/// Copy the given image into the atlas, with its top left at the given position.
+fn copy_into_atlas<I: LoadableImage>(img: &I, atlas: &mut RgbaImage, x: u32, y: u32) {
+    let atlas_row_size = atlas.width() as usize * PIXEL_SIZE;
+    let start = y as usize * atlas_row_size + x as usize * PIXEL_SIZE;
+    let raw: &mut [u8] = &mut *atlas;
+
+    // Safety: The packer only gives positions where the whole image fits in the atlas.
+    unsafe {
+        img.copy_into(raw[start..].as_mut_ptr(), atlas_row_size);
+    }
+}
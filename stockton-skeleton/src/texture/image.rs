@@ -3,6 +3,7 @@ use super::PIXEL_SIZE;
 use core::ptr::copy_nonoverlapping;
 use std::convert::TryInto;
 
+use hal::image::SamplerDesc;
 use image::RgbaImage;
 
 /// An object that can be loaded as an image into GPU memory
@@ -48,4 +49,17 @@ pub trait TextureResolver {
 
     /// Get the given texture, or None if it's corrupt/not there.
     fn resolve(&mut self, texture_id: u32) -> Option<Self::Image>;
+
+    /// Check if the given texture can be resolved without blocking.
+    /// Resolvers that get textures from slow sources (ie the network) should start fetching the texture here, and return false until it's done.
+    /// Blocks with textures that aren't ready will be retried later, rather than blocking the loader thread.
+    fn is_ready(&mut self, _texture_id: u32) -> bool {
+        true
+    }
+
+    /// Get how the given texture should be sampled.
+    /// If this returns None, the filter and wrap mode from [`super::TextureLoadConfig`] are used.
+    fn sampler(&mut self, _texture_id: u32) -> Option<SamplerDesc> {
+        None
+    }
 }
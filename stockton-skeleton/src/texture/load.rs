@@ -1,11 +1,16 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    ops::Range,
+    sync::{Arc, RwLock},
+};
 
 use super::{block::TexturesBlock, repo::BLOCK_SIZE, LoadableImage, TextureResolver};
 use crate::{
+    backend as back,
     buffers::{
         image::{ImageSpec, SampledImage, COLOR_RESOURCES},
         staging::StagingBuffer,
     },
+    context::RenderingContext,
     error::LockPoisoned,
     mem::{Block, MappableBlock, MemoryPool},
     types::*,
@@ -16,8 +21,12 @@ use arrayvec::ArrayVec;
 use hal::{
     format::{Aspects, Format},
     image::{
-        Filter, SamplerDesc, SubresourceLayers, SubresourceRange, Usage as ImgUsage, WrapMode,
+        Access, Filter, Layout, Lod, SamplerDesc, SubresourceLayers, SubresourceRange,
+        Usage as ImgUsage, WrapMode,
     },
+    memory::Barrier,
+    queue::QueueFamilyId,
+    Features,
 };
 use thiserror::Error;
 
@@ -41,16 +50,149 @@ pub const LAYERS: SubresourceLayers = SubresourceLayers {
     layers: 0..1,
 };
 
+/// The barrier that finishes loading an image, moving it to the layout for sampling.
+/// If `families` is given, this also releases ownership of it to the second family, and [`acquire_barrier`] must be used on that family before it is used.
+/// This should be used between the `TRANSFER` and `BOTTOM_OF_PIPE` stages.
+pub fn release_barrier(
+    img: &ImageT,
+    families: Option<Range<QueueFamilyId>>,
+) -> Barrier<'_, back::Backend> {
+    Barrier::Image {
+        states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+            ..(Access::empty(), Layout::ShaderReadOnlyOptimal),
+        target: img,
+        families,
+        range: RESOURCES,
+    }
+}
+
+/// The barrier that acquires ownership of a loaded image on the family it will be used on.
+/// This must be recorded on a queue of the second family, and only executed after the matching [`release_barrier`] has been.
+/// This should be used between the `TOP_OF_PIPE` and `FRAGMENT_SHADER` stages.
+pub fn acquire_barrier(img: &ImageT, families: Range<QueueFamilyId>) -> Barrier<'_, back::Backend> {
+    Barrier::Image {
+        states: (Access::empty(), Layout::TransferDstOptimal)
+            ..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+        target: img,
+        families: Some(families),
+        range: RESOURCES,
+    }
+}
+
+/// How textures should be filtered, from lowest to highest quality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureQuality {
+    /// No filtering, which gives a blocky look.
+    Nearest,
+
+    /// Linear filtering within each mip level, but not between them.
+    Bilinear,
+
+    /// Linear filtering within and between mip levels.
+    Trilinear,
+
+    /// Trilinear filtering, plus anisotropic filtering with up to the given number of samples, which keeps surfaces at steep angles sharp.
+    /// This falls back to [`TextureQuality::Trilinear`] if the device doesn't support it, and is clamped to the device's maximum.
+    Anisotropic(u8),
+}
+
+impl Default for TextureQuality {
+    /// Bilinear filtering is used by default.
+    fn default() -> Self {
+        TextureQuality::Bilinear
+    }
+}
+
+impl TextureQuality {
+    /// Get a sampler with this quality, and the given wrap mode and level of detail bias.
+    pub fn sampler(&self, wrap_mode: WrapMode, lod_bias: f32) -> SamplerDesc {
+        let (filter, mip_filter) = match self {
+            TextureQuality::Nearest => (Filter::Nearest, Filter::Nearest),
+            TextureQuality::Bilinear => (Filter::Linear, Filter::Nearest),
+            TextureQuality::Trilinear | TextureQuality::Anisotropic(_) => {
+                (Filter::Linear, Filter::Linear)
+            }
+        };
+
+        let mut desc = SamplerDesc::new(filter, wrap_mode);
+        desc.mip_filter = mip_filter;
+        desc.lod_bias = Lod(lod_bias);
+        if let TextureQuality::Anisotropic(n) = self {
+            desc.anisotropy_clamp = Some(*n);
+        }
+
+        desc
+    }
+
+    /// Get the closest quality to this one that the given context supports.
+    pub fn supported_by(self, context: &RenderingContext) -> Self {
+        match self {
+            TextureQuality::Anisotropic(_)
+                if !context
+                    .enabled_features()
+                    .contains(Features::SAMPLER_ANISOTROPY) =>
+            {
+                TextureQuality::Trilinear
+            }
+            TextureQuality::Anisotropic(n) => {
+                let max = context
+                    .physical_device_properties()
+                    .limits
+                    .max_sampler_anisotropy;
+                TextureQuality::Anisotropic((n as f32).min(max) as u8)
+            }
+            x => x,
+        }
+    }
+}
+
 /// Configuration required to load a texture
 pub struct TextureLoadConfig<R: TextureResolver> {
     /// The resolver to use
     pub resolver: R,
 
-    /// How to sample the image
-    pub filter: Filter,
+    /// How to filter the image
+    pub quality: TextureQuality,
+
+    /// Added to the mip level that would normally be used. Negative values make distant textures sharper, but can cause shimmering.
+    pub lod_bias: f32,
 
     /// How to deal with texture coordinates outside the image.
     pub wrap_mode: WrapMode,
+
+    /// Limits on how much the loader uploads at once
+    pub upload_budget: UploadBudget,
+}
+
+/// Limits on how much a texture loader starts uploading at once.
+/// Spreading uploads out means textures take longer to appear, but stops a lot of them appearing at once from causing a long frame.
+/// The default has no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UploadBudget {
+    /// The most blocks to start loading each time the loader checks for requests.
+    pub blocks_per_iteration: Option<usize>,
+
+    /// The most staging memory to have in use by loads that haven't finished, in bytes.
+    /// A block is always started if nothing else is loading, even if it needs more than this.
+    pub max_staging_bytes: Option<u64>,
+}
+
+impl UploadBudget {
+    /// Check if another block can be started, given how many have been started this iteration and how much staging memory is in use.
+    pub fn allows(&self, started: usize, staging_bytes: u64) -> bool {
+        self.blocks_per_iteration.map_or(true, |max| started < max)
+            && (staging_bytes == 0
+                || self
+                    .max_staging_bytes
+                    .map_or(true, |max| staging_bytes < max))
+    }
+}
+
+impl<R: TextureResolver> TextureLoadConfig<R> {
+    /// The sampler to use for textures that the resolver doesn't give a specific sampler for.
+    pub fn default_sampler(&self) -> SamplerDesc {
+        self.quality.sampler(self.wrap_mode, self.lod_bias)
+    }
 }
 
 /// A texture load that has been queued, and is finished when the fence triggers.
@@ -63,17 +205,16 @@ pub struct QueuedLoad<TP: MemoryPool, SP: MemoryPool> {
 
 /// Create a SampledImage for the given LoadableImage, and load the image data into a StagingBuffer
 /// Note that this doesn't queue up transferring from the buffer to the image.
-pub unsafe fn load_image<I, R, SP, TP>(
+pub unsafe fn load_image<I, SP, TP>(
     device: &mut DeviceT,
     staging_allocator: &Arc<RwLock<SP>>,
     tex_allocator: &Arc<RwLock<TP>>,
     obcpa: u32,
     img_data: I,
-    config: &TextureLoadConfig<R>,
+    sampler: &SamplerDesc,
 ) -> Result<(StagingBuffer<SP>, SampledImage<TP>)>
 where
     I: LoadableImage,
-    R: TextureResolver,
     SP: MemoryPool,
     TP: MemoryPool,
     SP::Block: MappableBlock,
@@ -95,7 +236,7 @@ where
                 usage: ImgUsage::TRANSFER_DST | ImgUsage::SAMPLED,
                 resources: COLOR_RESOURCES,
             },
-            &SamplerDesc::new(config.filter, config.wrap_mode),
+            sampler,
         )?
     };
 
@@ -128,4 +269,7 @@ where
 pub enum TextureLoadError {
     #[error("No available resources")]
     NoResources,
+
+    #[error("Textures aren't ready to be resolved yet")]
+    NotReady,
 }
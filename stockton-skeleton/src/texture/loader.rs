@@ -3,17 +3,19 @@
 use super::{
     block::TexturesBlock,
     load::{
-        load_image, QueuedLoad, TextureLoadConfig, TextureLoadError, FORMAT, LAYERS, RESOURCES,
+        acquire_barrier, load_image, release_barrier, QueuedLoad, TextureLoadConfig,
+        TextureLoadError, FORMAT, LAYERS,
     },
     repo::BLOCK_SIZE,
     TextureResolver, PIXEL_SIZE,
 };
 use crate::{
-    buffers::image::SampledImage,
+    buffers::{image::SampledImage, staging::StagingBuffer},
     context::RenderingContext,
     error::LockPoisoned,
-    mem::{MappableBlock, MemoryPool},
-    queue_negotiator::QueueFamilySelector,
+    mem::{Block, MappableBlock, MemoryPool},
+    observer::{lock_device_observed, notify, RenderEvent, RenderObserver},
+    queue_negotiator::{DrawQueue, QueueFamilySelector},
     types::*,
     utils::get_pixel_size,
 };
@@ -23,6 +25,7 @@ use std::{
     collections::VecDeque,
     iter::{empty, once},
     mem::{drop, ManuallyDrop},
+    ops::Range,
     sync::{
         mpsc::{Receiver, Sender},
         Arc, RwLock,
@@ -39,6 +42,7 @@ use hal::{
     image::{Access, Extent, Layout, Offset, SubresourceLayers, SubresourceRange},
     memory::{Barrier, Dependencies},
     pso::{Descriptor, DescriptorSetWrite, ImageDescriptorType, PipelineStage, ShaderStageFlags},
+    queue::QueueFamilyId,
 };
 use image::{Rgba, RgbaImage};
 use log::*;
@@ -98,8 +102,20 @@ where
     /// The channel blocks are returned to.
     return_channel: Sender<TexturesBlock<TP>>,
 
+    /// Blocks that couldn't be loaded yet, either because there were no free resources, their textures weren't ready, or the upload budget was used up.
+    retry_queue: VecDeque<BlockRef>,
+
+    /// The staging memory used by loads that haven't finished, in bytes
+    staging_in_flight: u64,
+
     /// A filler image for descriptors that aren't needed but still need to be written to
     blank_image: ManuallyDrop<SampledImage<TP>>,
+
+    /// The families loaded images need to be moved between, and the draw queue to acquire them on, if we're loading on a different family to the one used for drawing.
+    ownership_transfer: Option<(Range<QueueFamilyId>, Arc<RwLock<QueueT>>)>,
+
+    /// Receives events about blocks being loaded, taken from the context when we were created
+    observer: Option<Arc<dyn RenderObserver>>,
 }
 
 impl<R, TP, SP> TextureLoader<R, TP, SP>
@@ -135,10 +151,7 @@ where
 
     fn main(&mut self) -> Result<bool> {
         // Get a device lock so we can check fence status
-        let mut device = self
-            .device
-            .write()
-            .map_err(|_| LockPoisoned::Device)
+        let mut device = lock_device_observed(&self.device, self.observer.as_deref())
             .context("Error getting device lock")?;
 
         // Check for blocks that are finished, then send them back
@@ -152,6 +165,7 @@ where
                 let assets = (queued_load.fence, queued_load.buf);
                 let block = queued_load.block;
                 let mut staging_bufs = queued_load.staging_bufs;
+                self.staging_in_flight -= staging_size(&staging_bufs);
 
                 debug!("Load finished for texture block {:?}", block.id);
 
@@ -169,7 +183,24 @@ where
                 // Return assets used for loading
                 self.buffers.push_back(assets);
 
+                // Move it to the draw family, now that the release has finished, so it can be used straight away.
+                if let Some((families, draw_queue)) = &self.ownership_transfer {
+                    unsafe {
+                        Self::acquire_now(
+                            &mut device,
+                            draw_queue,
+                            families.clone(),
+                            block.imgs.iter().map(|img| img.img()),
+                        )
+                    }
+                    .context("Error moving texture block to draw queue family")?;
+                }
+
                 // Send back our loaded block
+                notify(
+                    self.observer.as_deref(),
+                    RenderEvent::BlockLoadFinished(block.id),
+                );
                 self.return_channel
                     .send(block)
                     .context("Error returning texture block")?;
@@ -181,20 +212,48 @@ where
         // Release device lock
         drop(device);
 
-        // Check for messages to start loading blocks
-        let req_iter: Vec<_> = self.request_channel.try_iter().collect();
+        // Retry any blocks we couldn't load before, then check for messages to start loading blocks
+        let mut req_iter: Vec<_> = self
+            .retry_queue
+            .drain(..)
+            .map(LoaderRequest::Load)
+            .collect();
+        req_iter.extend(self.request_channel.try_iter());
+        let mut started = 0;
         for to_load in req_iter {
             match to_load {
                 LoaderRequest::Load(to_load) => {
+                    // Leave the rest for later iterations once the budget is used up
+                    if !self
+                        .config
+                        .upload_budget
+                        .allows(started, self.staging_in_flight)
+                    {
+                        self.retry_queue.push_back(to_load);
+                        continue;
+                    }
+
                     // Attempt to load given block
                     debug!("Attempting to queue load for texture block {:?}", to_load);
 
                     let result = unsafe { self.attempt_queue_load(to_load) };
                     match result {
-                        Ok(queued_load) => self.commands_queued.push(queued_load),
+                        Ok(queued_load) => {
+                            notify(
+                                self.observer.as_deref(),
+                                RenderEvent::BlockLoadStarted(to_load),
+                            );
+                            started += 1;
+                            self.staging_in_flight += staging_size(&queued_load.staging_bufs);
+                            self.commands_queued.push(queued_load);
+                        }
                         Err(x) => match x.downcast_ref::<TextureLoadError>() {
                             Some(TextureLoadError::NoResources) => {
                                 debug!("No resources, trying again later");
+                                self.retry_queue.push_back(to_load);
+                            }
+                            Some(TextureLoadError::NotReady) => {
+                                self.retry_queue.push_back(to_load);
                             }
                             _ => return Err(x).context("Error queuing texture load"),
                         },
@@ -218,6 +277,18 @@ where
         let family = context.get_queue_family::<Q>()?;
         let queue_lock = context.get_queue::<Q>()?;
 
+        // If we're not loading on the draw family, loaded images need their ownership moved to it
+        let draw_family = context.get_queue_family::<DrawQueue>()?;
+        let ownership_transfer = if family != draw_family {
+            debug!(
+                "Loading textures on family {:?}, drawing on {:?}",
+                family, draw_family
+            );
+            Some((family..draw_family, context.get_queue::<DrawQueue>()?))
+        } else {
+            None
+        };
+
         // Memory pools
         let tex_mempool = context.memory_pool()?.clone();
         let staging_mempool = context.memory_pool()?.clone();
@@ -264,6 +335,7 @@ where
                 (&staging_mempool, &tex_mempool),
                 optimal_buffer_copy_pitch_alignment,
                 &config,
+                ownership_transfer.as_ref(),
             )
         }
         .context("Error creating blank image")?;
@@ -286,16 +358,33 @@ where
 
             request_channel,
             return_channel,
+            retry_queue: VecDeque::new(),
+            staging_in_flight: 0,
             config,
             blank_image: ManuallyDrop::new(blank_image),
+            ownership_transfer,
+            observer: context.observer(),
         })
     }
 
+    /// The families loaded images are moved between, if we're loading on a different family to the one used for drawing.
+    fn transfer_families(&self) -> Option<Range<QueueFamilyId>> {
+        self.ownership_transfer
+            .as_ref()
+            .map(|(families, _)| families.clone())
+    }
+
     unsafe fn attempt_queue_load(&mut self, block_ref: usize) -> Result<QueuedLoad<TP, SP>> {
-        let mut device = self
-            .device
-            .write()
-            .map_err(|_| LockPoisoned::Device)
+        // Make sure every texture is ready before we take any resources.
+        // We check all of them so that asynchronous resolvers can fetch them at the same time.
+        let not_ready = ((block_ref * BLOCK_SIZE)..(block_ref + 1) * BLOCK_SIZE)
+            .filter(|tex_idx| !self.config.resolver.is_ready(*tex_idx as u32))
+            .count();
+        if not_ready > 0 {
+            return Err(TextureLoadError::NotReady.into());
+        }
+
+        let mut device = lock_device_observed(&self.device, self.observer.as_deref())
             .context("Error getting device lock")?;
 
         // Get assets to use
@@ -376,6 +465,11 @@ where
             }
 
             let img_data = img_data.unwrap();
+            let sampler = self
+                .config
+                .resolver
+                .sampler(tex_idx as u32)
+                .unwrap_or_else(|| self.config.default_sampler());
 
             let array_offset = tex_idx % BLOCK_SIZE;
 
@@ -385,7 +479,7 @@ where
                 &self.tex_mempool,
                 self.optimal_buffer_copy_pitch_alignment,
                 img_data,
-                &self.config,
+                &sampler,
             )?;
 
             // Write to descriptor set
@@ -458,13 +552,8 @@ where
         buf.pipeline_barrier(
             PipelineStage::TRANSFER..PipelineStage::BOTTOM_OF_PIPE,
             Dependencies::empty(),
-            imgs.iter().map(|li| Barrier::Image {
-                states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
-                    ..(Access::empty(), Layout::ShaderReadOnlyOptimal),
-                target: &*li.img(),
-                families: None,
-                range: RESOURCES,
-            }),
+            imgs.iter()
+                .map(|li| release_barrier(li.img(), self.transfer_families())),
         );
 
         buf.finish();
@@ -495,6 +584,7 @@ where
         (staging_mempool, tex_mempool): (&Arc<RwLock<SP>>, &Arc<RwLock<TP>>),
         obcpa: u32,
         config: &TextureLoadConfig<R>,
+        ownership_transfer: Option<&(Range<QueueFamilyId>, Arc<RwLock<QueueT>>)>,
     ) -> Result<SampledImage<TP>> {
         let img_data = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 255, 255]));
 
@@ -511,7 +601,7 @@ where
             tex_mempool,
             obcpa,
             img_data,
-            config,
+            &config.default_sampler(),
         )?;
 
         buf.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
@@ -554,13 +644,10 @@ where
         buf.pipeline_barrier(
             PipelineStage::TRANSFER..PipelineStage::BOTTOM_OF_PIPE,
             Dependencies::empty(),
-            once(Barrier::Image {
-                states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
-                    ..(Access::empty(), Layout::ShaderReadOnlyOptimal),
-                target: &*img.img(),
-                families: None,
-                range: RESOURCES,
-            }),
+            once(release_barrier(
+                img.img(),
+                ownership_transfer.map(|(families, _)| families.clone()),
+            )),
         );
         buf.finish();
 
@@ -588,9 +675,55 @@ where
             staging_buffer.deactivate_device_pool(device, &mut *staging_mempool);
         }
 
+        // Move it to the draw family, now that the release has finished.
+        if let Some((families, draw_queue)) = ownership_transfer {
+            Self::acquire_now(device, draw_queue, families.clone(), once(img.img()))
+                .context("Error moving blank image to draw queue family")?;
+        }
+
         Ok(img)
     }
 
+    /// Acquire ownership of the given images on the given queue, blocking until it's done.
+    /// This is only done once per block, so we don't bother keeping the pool around.
+    unsafe fn acquire_now<'a>(
+        device: &mut DeviceT,
+        queue_lock: &Arc<RwLock<QueueT>>,
+        families: Range<QueueFamilyId>,
+        imgs: impl Iterator<Item = &'a ImageT>,
+    ) -> Result<()> {
+        use hal::pool::CommandPoolCreateFlags;
+
+        let mut pool = device
+            .create_command_pool(families.end, CommandPoolCreateFlags::TRANSIENT)
+            .context("Error creating command pool")?;
+        let mut buf = pool.allocate_one(hal::command::Level::Primary);
+
+        buf.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+        buf.pipeline_barrier(
+            PipelineStage::TOP_OF_PIPE..PipelineStage::FRAGMENT_SHADER,
+            Dependencies::empty(),
+            imgs.map(|img| acquire_barrier(img, families.clone())),
+        );
+        buf.finish();
+
+        let mut fence = device.create_fence(false).context("Error creating fence")?;
+        {
+            let mut queue = queue_lock.write().map_err(|_| LockPoisoned::Queue)?;
+
+            queue.submit(once(&buf), empty(), empty(), Some(&mut fence));
+        }
+
+        device
+            .wait_for_fence(&fence, std::u64::MAX)
+            .context("Error waiting for acquire")?;
+
+        device.destroy_fence(fence);
+        device.destroy_command_pool(pool);
+
+        Ok(())
+    }
+
     /// Safely destroy all the vulkan stuff in this instance
     /// Note that this returns the memory allocators, from which should be freed any TextureBlocks
     /// All in-progress things are sent to return_channel.
@@ -661,6 +794,14 @@ where
     }
 }
 
+/// The total size of the memory used by the given staging buffers, in bytes.
+fn staging_size<P: MemoryPool>(bufs: &[StagingBuffer<P>]) -> u64
+where
+    P::Block: MappableBlock,
+{
+    bufs.iter().map(|b| b.mem().size()).sum()
+}
+
 pub struct TextureLoaderRemains {
     pub descriptor_allocator: ManuallyDrop<DescriptorAllocator>,
 }
@@ -1,16 +1,22 @@
 //! Everything related to loading textures into GPU memory
 
+mod atlas;
 mod block;
 mod image;
 mod load;
 mod loader;
 mod repo;
+#[cfg(feature = "zip")]
+mod resolver;
 
+pub use self::atlas::{AtlasConfig, AtlasImage, AtlasLocation, AtlasResolver};
 pub use self::block::TexturesBlock;
 pub use self::image::{LoadableImage, TextureResolver};
-pub use self::load::TextureLoadConfig;
+pub use self::load::{TextureLoadConfig, TextureQuality, UploadBudget};
 pub use self::loader::BlockRef;
 pub use self::repo::{TexLoadQueue, TextureRepo};
+#[cfg(feature = "zip")]
+pub use self::resolver::ZipResolver;
 
 /// The size of each pixel in an image
 pub const PIXEL_SIZE: usize = std::mem::size_of::<u8>() * 4;
@@ -1,4 +1,5 @@
 use super::{
+    atlas::{AtlasConfig, AtlasLocation, AtlasResolver},
     block::TexturesBlock,
     load::TextureLoadConfig,
     loader::{BlockRef, LoaderRequest, TextureLoader, TextureLoaderRemains, NUM_SIMULTANEOUS_CMDS},
@@ -22,7 +23,10 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use hal::pso::{DescriptorSetLayoutBinding, DescriptorType, ImageDescriptorType, ShaderStageFlags};
+use hal::{
+    pso::{DescriptorSetLayoutBinding, DescriptorType, ImageDescriptorType, ShaderStageFlags},
+    queue::QueueType,
+};
 use log::debug;
 
 /// The number of textures in one 'block'
@@ -38,6 +42,9 @@ pub const BLOCK_SIZE: usize = 8;
 /// Make sure to call [`self::TextureRepo::process_responses`] every frame, or at least often.
 /// Then, whenever you draw, use [`self::TextureRepo::attempt_get_descriptor_set`] to see if that texture has finished loading,
 /// or `queue_load` to start loading it ASAP.
+///
+/// If textures are loaded on a different queue family to the one used for drawing (for example a dedicated transfer family),
+/// the loader moves their ownership over to the draw family before returning them, so they can be used the same way.
 
 pub struct TextureRepo<TP, SP>
 where
@@ -50,6 +57,9 @@ where
     req_send: Sender<LoaderRequest>,
     resp_recv: Receiver<TexturesBlock<TP>>,
     blocks: HashMap<BlockRef, Option<TexturesBlock<TP>>>,
+
+    /// Where each texture was packed, if this repo is using atlases
+    atlas_locations: Option<HashMap<u32, AtlasLocation>>,
     _d: PhantomData<(TP, SP)>,
 }
 
@@ -61,10 +71,13 @@ where
 {
     /// Create a new TextureRepo from the given context.
     /// Q should most likely be [`TexLoadQueue`]
+    /// The texture quality will be lowered to what the device supports, see [`super::TextureQuality::supported_by`].
     pub fn new<R: 'static + TextureResolver + Send + Sync, Q: QueueFamilySelector>(
         context: &mut RenderingContext,
-        config: TextureLoadConfig<R>,
+        mut config: TextureLoadConfig<R>,
     ) -> Result<Self> {
+        config.quality = config.quality.supported_by(context);
+
         // Create Channels
         let (req_send, req_recv) = channel();
         let (resp_send, resp_recv) = channel();
@@ -121,10 +134,53 @@ where
             blocks: HashMap::new(),
             req_send,
             resp_recv,
+            atlas_locations: None,
             _d: PhantomData,
         })
     }
 
+    /// Create a new TextureRepo that packs the given textures into atlases, so fewer descriptor sets need to be bound.
+    /// This resolves all of the textures before returning, see [`AtlasResolver::new`].
+    /// Texture ids used with this repo refer to atlases, so use [`Self::location`] to find which to load and how to change texture coordinates.
+    pub fn new_atlased<R: 'static + TextureResolver + Send + Sync, Q: QueueFamilySelector>(
+        context: &mut RenderingContext,
+        config: TextureLoadConfig<R>,
+        texture_ids: impl IntoIterator<Item = u32>,
+        atlas_config: &AtlasConfig,
+    ) -> Result<Self> {
+        let resolver = AtlasResolver::new(config.resolver, texture_ids, atlas_config);
+        let atlas_locations = resolver.locations().clone();
+        debug!(
+            "Packed {} textures into {} atlases",
+            atlas_locations.len(),
+            resolver.atlas_count()
+        );
+
+        let mut repo = Self::new::<_, Q>(
+            context,
+            TextureLoadConfig {
+                resolver,
+                quality: config.quality,
+                lod_bias: config.lod_bias,
+                wrap_mode: config.wrap_mode,
+                upload_budget: config.upload_budget,
+            },
+        )?;
+        repo.atlas_locations = Some(atlas_locations);
+
+        Ok(repo)
+    }
+
+    /// Find where the given texture is.
+    /// If this repo isn't using atlases, this is always the whole of the texture with the same id.
+    /// Otherwise, this returns None for textures that weren't given when creating the repo, or couldn't be resolved.
+    pub fn location(&self, texture_id: u32) -> Option<AtlasLocation> {
+        match &self.atlas_locations {
+            Some(locations) => locations.get(&texture_id).copied(),
+            None => Some(AtlasLocation::whole(texture_id)),
+        }
+    }
+
     /// Get the descriptor layout used for each texture descriptor
     /// This can be used when creating graphics pipelines.
     pub fn get_ds_layout(&self) -> Result<RwLockReadGuard<DescriptorSetLayoutT>> {
@@ -161,6 +217,22 @@ where
             .and_then(|opt| opt.as_ref().map(|z| z.descriptor_set.raw()))
     }
 
+    /// The number of blocks that have been queued but haven't finished loading yet.
+    pub fn pending_count(&self) -> usize {
+        self.blocks.values().filter(|b| b.is_none()).count()
+    }
+
+    /// The number of blocks that have finished loading.
+    pub fn loaded_count(&self) -> usize {
+        self.blocks.values().filter(|b| b.is_some()).count()
+    }
+
+    /// The number of distinct blocks that have been queued, whether or not they've finished loading.
+    /// Progress can be shown with `loaded_count() / total_requested()`.
+    pub fn total_requested(&self) -> usize {
+        self.blocks.len()
+    }
+
     /// Process any textures that just finished loading. This should be called every frame.
     pub fn process_responses(&mut self) {
         let resp_iter: Vec<_> = self.resp_recv.try_iter().collect();
@@ -222,4 +294,13 @@ impl QueueFamilySelector for TexLoadQueue {
     fn is_suitable(&self, family: &QueueFamilyT) -> bool {
         family.queue_type().supports_transfer() && family.max_queues() >= NUM_SIMULTANEOUS_CMDS
     }
+
+    /// Prefer dedicated transfer families, so uploads don't compete with drawing.
+    fn priority(&self, family: &QueueFamilyT) -> usize {
+        match family.queue_type() {
+            QueueType::Transfer => 2,
+            QueueType::Compute => 1,
+            _ => 0,
+        }
+    }
 }
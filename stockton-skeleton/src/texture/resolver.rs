@@ -0,0 +1,75 @@
+//! Resolvers that load textures from archives.
+
+use super::TextureResolver;
+
+use std::{
+    io::{Cursor, Read, Seek},
+    path::Path,
+};
+
+use image::RgbaImage;
+use zip::{result::ZipResult, ZipArchive};
+
+/// The extensions to try, in order, when a texture name doesn't have one.
+const EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "tga"];
+
+/// Resolves textures from a zip archive, such as one embedded with `include_bytes!`.
+/// Texture ids are indexes into the list of names given on creation.
+/// Names without an extension (as Q3 stores them) will be tried with each of the common image extensions.
+pub struct ZipResolver<R: Read + Seek> {
+    archive: ZipArchive<R>,
+    names: Vec<String>,
+}
+
+impl<R: Read + Seek> ZipResolver<R> {
+    /// Create a new resolver reading from the given archive.
+    pub fn new(reader: R, names: Vec<String>) -> ZipResult<Self> {
+        Ok(ZipResolver {
+            archive: ZipArchive::new(reader)?,
+            names,
+        })
+    }
+
+    /// Read the file with the given name out of the archive, if it exists.
+    fn read_file(&mut self, name: &str) -> Option<Vec<u8>> {
+        let mut file = self.archive.by_name(name).ok()?;
+        let mut bytes = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut bytes).ok()?;
+
+        Some(bytes)
+    }
+
+    /// Find the file for the given texture name, trying common extensions if it doesn't have one.
+    fn find_file(&mut self, name: &str) -> Option<Vec<u8>> {
+        let name = name.replace('\\', "/");
+        let name = name.trim_start_matches('/');
+
+        if Path::new(name).extension().is_some() {
+            return self.read_file(name);
+        }
+
+        EXTENSIONS
+            .iter()
+            .find_map(|ext| self.read_file(&format!("{}.{}", name, ext)))
+    }
+}
+
+impl ZipResolver<Cursor<&'static [u8]>> {
+    /// Create a new resolver reading from an archive stored in memory, usually with `include_bytes!`.
+    pub fn from_bytes(bytes: &'static [u8], names: Vec<String>) -> ZipResult<Self> {
+        Self::new(Cursor::new(bytes), names)
+    }
+}
+
+impl<R: Read + Seek> TextureResolver for ZipResolver<R> {
+    type Image = RgbaImage;
+
+    fn resolve(&mut self, texture_id: u32) -> Option<Self::Image> {
+        let name = self.names.get(texture_id as usize)?.clone();
+        let bytes = self.find_file(&name)?;
+
+        image::load_from_memory(&bytes)
+            .ok()
+            .map(|img| img.into_rgba8())
+    }
+}
@@ -2,6 +2,8 @@
 
 pub use hal::prelude::*;
 
+use crate::backend as back;
+
 pub type InstanceT = <back::Backend as hal::Backend>::Instance;
 pub type DeviceT = <back::Backend as hal::Backend>::Device;
 pub type BufferT = <back::Backend as hal::Backend>::Buffer;
@@ -36,8 +38,21 @@ pub type RDescriptorSet = rendy_descriptor::DescriptorSet<back::Backend>;
 
 pub type Vector2 = na::Vec2;
 pub type Vector3 = na::Vec3;
+pub type Vector4 = na::Vec4;
 
 pub type Vector2i = na::IVec2;
 pub type Vector3i = na::IVec3;
 
 pub type Matrix4 = na::Mat4x4;
+pub type Quaternion = na::Quat;
+
+/// Extend a position to homogeneous coordinates (`w = 1`), so it's affected by a matrix's translation.
+/// For directions, which shouldn't be translated, use nalgebra's `to_homogeneous` instead (`w = 0`).
+pub fn point_to_homogeneous(v: &Vector3) -> Vector4 {
+    Vector4::new(v.x, v.y, v.z, 1.0)
+}
+
+/// Get the rotation matrix for the given quaternion.
+pub fn quaternion_to_matrix4(q: &Quaternion) -> Matrix4 {
+    na::quat_to_mat4(q)
+}
@@ -0,0 +1,64 @@
+//! Helpers for tests that need a GPU and a display.
+//! These tests are ignored by default, so run them with `cargo test -- --ignored`.
+
+use stockton_skeleton::{
+    draw_passes::{ClearPass, ClearPassConfig, Singular},
+    RendererOptions, RenderingContext, Result,
+};
+use winit::{
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder},
+};
+
+/// Create a hidden window to render to. The event loop needs to be kept alive for as long as the window is.
+pub fn hidden_window() -> (EventLoop<()>, Window) {
+    let event_loop = new_event_loop();
+    let window = WindowBuilder::new()
+        .with_title("stockton test")
+        .with_visible(false)
+        .build(&event_loop)
+        .expect("Error creating window");
+
+    (event_loop, window)
+}
+
+/// Create a context for the given window, relaxing the surface properties so that software and CI GPUs can be used.
+pub fn context(window: &Window) -> Result<RenderingContext> {
+    RenderingContext::new::<ClearPassConfig, ClearPass<Singular>>(
+        window,
+        RendererOptions {
+            relaxed_properties: true,
+            ..RendererOptions::default()
+        },
+    )
+}
+
+/// Destroy a context made with [`context`], once everything made from it has been deactivated.
+pub fn deactivate(context: RenderingContext) -> Result<()> {
+    context.deactivate_memory_pools()?.deactivate()
+}
+
+/// Tests run on their own threads, so the event loop can't insist on being on the main one.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn new_event_loop() -> EventLoop<()> {
+    use winit::platform::unix::EventLoopExtUnix;
+    EventLoop::new_any_thread()
+}
+
+#[cfg(target_os = "windows")]
+fn new_event_loop() -> EventLoop<()> {
+    use winit::platform::windows::EventLoopExtWindows;
+    EventLoop::new_any_thread()
+}
+
+/// macOS only allows event loops on the main thread, so these tests need `--test-threads=1` there.
+#[cfg(target_os = "macos")]
+fn new_event_loop() -> EventLoop<()> {
+    EventLoop::new()
+}
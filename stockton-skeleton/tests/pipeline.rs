@@ -0,0 +1,253 @@
+//! Building pipelines on a real device.
+
+mod common;
+
+use std::iter::empty;
+
+use gfx_hal::{
+    device::Device,
+    format::Format,
+    image::{Access, Extent, Layout},
+    memory::Dependencies,
+    pass::{Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp, SubpassDependency},
+    pso::{
+        BlendDesc, ColorBlendDesc, ColorMask, Comparison, DepthBias, DepthStencilDesc, DepthTest,
+        Face, FrontFace, InputAssemblerDesc, PipelineStage, PolygonMode, Primitive, Rasterizer,
+        State, VertexInputRate,
+    },
+};
+use stockton_skeleton::{
+    builders::{
+        AttachmentSpec, BlendMode, MultiRenderpassSpec, PipelineSpecBuilder, RenderpassSpec,
+        ShaderDesc, ShaderKind, SubpassSpec, VertexBufferSpec, VertexPrimitiveAssemblerSpec,
+    },
+    Result,
+};
+
+const POSITION_VERT: &str = "
+#version 450
+layout(location = 0) in vec3 position;
+
+void main() {
+    gl_Position = vec4(position, 1.0);
+}
+";
+
+const EXTENT: Extent = Extent {
+    width: 256,
+    height: 256,
+    depth: 1,
+};
+
+/// A rasterizer that doesn't do anything special
+fn rasterizer() -> Rasterizer {
+    Rasterizer {
+        polygon_mode: PolygonMode::Fill,
+        cull_face: Face::BACK,
+        front_face: FrontFace::CounterClockwise,
+        depth_clamping: false,
+        depth_bias: None,
+        conservative: false,
+        line_width: State::Static(1.0),
+    }
+}
+
+fn position_assembler() -> VertexPrimitiveAssemblerSpec {
+    VertexPrimitiveAssemblerSpec::with_buffers(
+        InputAssemblerDesc::new(Primitive::TriangleList),
+        vec![VertexBufferSpec {
+            attributes: vec![Format::Rgb32Sfloat],
+            rate: VertexInputRate::Vertex,
+        }],
+    )
+}
+
+fn position_shader() -> ShaderDesc {
+    ShaderDesc {
+        source: POSITION_VERT.to_string(),
+        entry: "main".to_string(),
+        kind: ShaderKind::Vertex,
+    }
+}
+
+/// An attachment cleared at the start of the renderpass and left in `final_layout`
+fn cleared(format: Format, store: AttachmentStoreOp, final_layout: Layout) -> Attachment {
+    Attachment {
+        format: Some(format),
+        samples: 1,
+        ops: AttachmentOps::new(AttachmentLoadOp::Clear, store),
+        stencil_ops: AttachmentOps::DONT_CARE,
+        layouts: Layout::Undefined..final_layout,
+    }
+}
+
+#[test]
+#[ignore]
+fn depth_bias() -> Result<()> {
+    let (_event_loop, window) = common::hidden_window();
+    let mut context = common::context(&window)?;
+    let depth_format = context.properties().depth_format;
+
+    let spec = PipelineSpecBuilder::default()
+        .rasterizer(Rasterizer {
+            depth_bias: Some(State::Static(DepthBias {
+                const_factor: 1.25,
+                clamp: 0.0,
+                slope_factor: 1.75,
+            })),
+            ..rasterizer()
+        })
+        .depth_stencil(DepthStencilDesc {
+            depth: Some(DepthTest {
+                fun: Comparison::Less,
+                write: true,
+            }),
+            depth_bounds: false,
+            stencil: None,
+        })
+        .blender(BlendDesc {
+            logic_op: None,
+            targets: vec![],
+        })
+        .primitive_assembler(position_assembler())
+        .shader_vertex(position_shader())
+        .renderpass(RenderpassSpec {
+            colors: vec![],
+            depth: Some(AttachmentSpec {
+                attachment: cleared(
+                    depth_format,
+                    AttachmentStoreOp::Store,
+                    Layout::ShaderReadOnlyOptimal,
+                ),
+                used_layout: Layout::DepthStencilAttachmentOptimal,
+            }),
+            inputs: vec![],
+            resolves: vec![],
+            preserves: vec![],
+        })
+        .build()?;
+
+    let mut device = context.lock_device()?;
+    let pipeline = spec.build(
+        &mut device,
+        EXTENT,
+        &context.physical_device_properties().limits,
+        empty(),
+    )?;
+    pipeline.deactivate(&mut device);
+    drop(device);
+
+    common::deactivate(context)
+}
+
+#[test]
+#[ignore]
+fn two_subpasses() -> Result<()> {
+    let (_event_loop, window) = common::hidden_window();
+    let mut context = common::context(&window)?;
+    let color_format = context.properties().color_format;
+    let depth_format = context.properties().depth_format;
+
+    // A G-buffer written in the first subpass, and read as input attachments in the second
+    let gbuffer = |format| {
+        cleared(
+            format,
+            AttachmentStoreOp::DontCare,
+            Layout::ShaderReadOnlyOptimal,
+        )
+    };
+    let renderpass = MultiRenderpassSpec {
+        attachments: vec![
+            cleared(
+                color_format,
+                AttachmentStoreOp::Store,
+                Layout::ColorAttachmentOptimal,
+            ),
+            gbuffer(Format::Rgba8Unorm),
+            gbuffer(Format::Rgba16Sfloat),
+            cleared(
+                depth_format,
+                AttachmentStoreOp::DontCare,
+                Layout::DepthStencilAttachmentOptimal,
+            ),
+        ],
+        subpasses: vec![
+            SubpassSpec {
+                colors: vec![
+                    (1, Layout::ColorAttachmentOptimal),
+                    (2, Layout::ColorAttachmentOptimal),
+                ],
+                depth: Some((3, Layout::DepthStencilAttachmentOptimal)),
+                ..SubpassSpec::default()
+            },
+            SubpassSpec {
+                colors: vec![(0, Layout::ColorAttachmentOptimal)],
+                inputs: vec![
+                    (1, Layout::ShaderReadOnlyOptimal),
+                    (2, Layout::ShaderReadOnlyOptimal),
+                ],
+                ..SubpassSpec::default()
+            },
+        ],
+        dependencies: vec![SubpassDependency {
+            passes: Some(0)..Some(1),
+            stages: PipelineStage::COLOR_ATTACHMENT_OUTPUT..PipelineStage::FRAGMENT_SHADER,
+            accesses: Access::COLOR_ATTACHMENT_WRITE..Access::INPUT_ATTACHMENT_READ,
+            flags: Dependencies::BY_REGION,
+        }],
+    };
+
+    let gbuffer_spec = PipelineSpecBuilder::default()
+        .rasterizer(rasterizer())
+        .depth_stencil(DepthStencilDesc {
+            depth: Some(DepthTest {
+                fun: Comparison::Less,
+                write: true,
+            }),
+            depth_bounds: false,
+            stencil: None,
+        })
+        .blender(BlendDesc {
+            logic_op: None,
+            targets: vec![
+                ColorBlendDesc {
+                    mask: ColorMask::ALL,
+                    blend: None,
+                };
+                2
+            ],
+        })
+        .primitive_assembler(position_assembler())
+        .shader_vertex(position_shader())
+        .renderpass(renderpass.clone())
+        .subpass(0)
+        .build()?;
+    let lighting_spec = PipelineSpecBuilder::default()
+        .rasterizer(rasterizer())
+        .depth_stencil(DepthStencilDesc {
+            depth: None,
+            depth_bounds: false,
+            stencil: None,
+        })
+        .blender(BlendMode::Opaque)
+        .primitive_assembler(position_assembler())
+        .shader_vertex(position_shader())
+        .renderpass(renderpass.clone())
+        .subpass(1)
+        .build()?;
+
+    let mut device = context.lock_device()?;
+    let limits = &context.physical_device_properties().limits;
+    let gbuffer_pipeline = gbuffer_spec.build(&mut device, EXTENT, limits, empty())?;
+    let lighting_pipeline = lighting_spec.build(&mut device, EXTENT, limits, empty())?;
+    let raw = renderpass.build_renderpass(&mut device)?;
+
+    gbuffer_pipeline.deactivate(&mut device);
+    lighting_pipeline.deactivate(&mut device);
+    unsafe {
+        device.destroy_render_pass(raw);
+    }
+    drop(device);
+
+    common::deactivate(context)
+}
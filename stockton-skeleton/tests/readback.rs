@@ -0,0 +1,72 @@
+//! Drawing a frame into an image and reading it back.
+
+mod common;
+
+use stockton_skeleton::{
+    draw_passes::{ClearPass, ClearPassConfig, Singular},
+    Renderer, RendererOptions, Result, Session,
+};
+
+/// The colour to clear to, as linear RGBA
+const COLOR: [f32; 4] = [1.0, 0.5, 0.2, 1.0];
+
+/// How far each channel can be from what's expected, to allow for rounding
+const TOLERANCE: u8 = 2;
+
+/// Encode a linear value the way an sRGB image stores it.
+fn to_srgb(linear: f32) -> u8 {
+    let encoded = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+
+    (encoded * 255.0).round() as u8
+}
+
+#[test]
+#[ignore]
+fn clear_pass_color() -> Result<()> {
+    let (_event_loop, window) = common::hidden_window();
+    let mut session = Session::new(|_| {});
+    let mut renderer: Renderer<ClearPass<Singular>> = Renderer::new(
+        &window,
+        &mut session,
+        ClearPassConfig { color: COLOR },
+        RendererOptions {
+            relaxed_properties: true,
+            ..RendererOptions::default()
+        },
+    )?;
+
+    // Alpha isn't gamma encoded
+    let expected = [
+        to_srgb(COLOR[0]),
+        to_srgb(COLOR[1]),
+        to_srgb(COLOR[2]),
+        (COLOR[3] * 255.0).round() as u8,
+    ];
+
+    // The second time checks the draw pass was put back properly
+    for _ in 0..2 {
+        let image = renderer.render_once_to_image(&session)?;
+        let extent = renderer.context().properties().extent;
+        assert_eq!(image.dimensions(), (extent.width, extent.height));
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            for (channel, (got, want)) in pixel.0.iter().zip(expected.iter()).enumerate() {
+                assert!(
+                    (*got as i16 - *want as i16).abs() <= TOLERANCE as i16,
+                    "Pixel ({}, {}) channel {} was {}, expected {}",
+                    x,
+                    y,
+                    channel,
+                    got,
+                    want
+                );
+            }
+        }
+    }
+
+    renderer.deactivate()
+}
@@ -0,0 +1,58 @@
+//! Creating a renderer and shutting it down cleanly.
+//! In debug builds, gfx-backend-vulkan enables the validation layers if they're installed, and logs anything they report as an error.
+
+mod common;
+
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use stockton_skeleton::{
+    draw_passes::{ClearPass, ClearPassConfig, Singular},
+    Renderer, RendererOptions, Result, Session,
+};
+
+/// Errors logged so far, including any from the validation layers
+static ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct ErrorLog;
+
+impl Log for ErrorLog {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Error
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            ERRORS
+                .lock()
+                .unwrap()
+                .push(format!("{}: {}", record.target(), record.args()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+#[ignore]
+fn create_and_deactivate() -> Result<()> {
+    log::set_logger(&ErrorLog).expect("Error setting logger");
+    log::set_max_level(LevelFilter::Error);
+
+    let (_event_loop, window) = common::hidden_window();
+    let mut session = Session::new(|_| {});
+    let renderer: Renderer<ClearPass<Singular>> = Renderer::new(
+        &window,
+        &mut session,
+        ClearPassConfig::default(),
+        RendererOptions {
+            relaxed_properties: true,
+            ..RendererOptions::default()
+        },
+    )?;
+    renderer.deactivate()?;
+
+    assert_eq!(*ERRORS.lock().unwrap(), Vec::<String>::new());
+
+    Ok(())
+}
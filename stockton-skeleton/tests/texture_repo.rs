@@ -0,0 +1,140 @@
+//! Loading texture blocks with a [`TextureRepo`].
+
+mod common;
+
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use gfx_hal::image::WrapMode;
+use image::{Rgba, RgbaImage};
+use stockton_skeleton::{
+    draw_passes::{ClearPass, ClearPassConfig, Singular},
+    mem::{StagingPool, TexturesPool},
+    queue_negotiator::{DrawQueue, QueueFamilyNegotiator},
+    texture::{TexLoadQueue, TextureLoadConfig, TextureRepo, TextureResolver},
+    types::Adapter,
+    IntoDrawPass, RendererOptions, RenderingContext, Result, Session,
+};
+
+/// Gives a small solid image for every texture
+struct SolidResolver;
+
+impl TextureResolver for SolidResolver {
+    type Image = RgbaImage;
+
+    fn resolve(&mut self, _texture_id: u32) -> Option<Self::Image> {
+        Some(RgbaImage::from_pixel(4, 4, Rgba([255, 0, 255, 255])))
+    }
+}
+
+/// A clear pass that also asks for a [`TexLoadQueue`], which is on a dedicated transfer family if the device has one.
+struct WithTexLoadQueue(ClearPassConfig);
+
+impl IntoDrawPass<ClearPass<Singular>, Singular> for WithTexLoadQueue {
+    fn init(
+        self,
+        session: &mut Session,
+        context: &mut RenderingContext,
+    ) -> Result<ClearPass<Singular>> {
+        self.0.init(session, context)
+    }
+
+    fn find_aux_queues(
+        adapter: &Adapter,
+        queue_negotiator: &mut QueueFamilyNegotiator,
+    ) -> Result<()> {
+        queue_negotiator.find(adapter, &TexLoadQueue, 1)
+    }
+}
+
+fn load_config() -> TextureLoadConfig<SolidResolver> {
+    TextureLoadConfig {
+        resolver: SolidResolver,
+        quality: Default::default(),
+        lod_bias: 0.0,
+        wrap_mode: WrapMode::Tile,
+        upload_budget: Default::default(),
+    }
+}
+
+/// Keep processing responses until `count` blocks have loaded.
+fn wait_for_loads(repo: &mut TextureRepo<TexturesPool, StagingPool>, count: usize) {
+    let start = Instant::now();
+    while repo.loaded_count() < count {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "Blocks took too long to load"
+        );
+        sleep(Duration::from_millis(10));
+
+        repo.process_responses();
+        assert_eq!(repo.pending_count() + repo.loaded_count(), count);
+    }
+}
+
+#[test]
+#[ignore]
+fn counts_follow_queued_blocks() -> Result<()> {
+    let (_event_loop, window) = common::hidden_window();
+    let mut context = common::context(&window)?;
+
+    // Loading on the draw queue means blocks don't need their ownership transferred before they're returned
+    let mut repo: TextureRepo<TexturesPool, StagingPool> =
+        TextureRepo::new::<_, DrawQueue>(&mut context, load_config())?;
+    assert_eq!(repo.pending_count(), 0);
+    assert_eq!(repo.loaded_count(), 0);
+    assert_eq!(repo.total_requested(), 0);
+
+    for block in 0..3 {
+        repo.queue_load(block)?;
+    }
+    // Already queued, so not counted again
+    repo.queue_load(0)?;
+    assert_eq!(repo.total_requested(), 3);
+    assert_eq!(repo.pending_count() + repo.loaded_count(), 3);
+
+    wait_for_loads(&mut repo, 3);
+    assert_eq!(repo.pending_count(), 0);
+    assert_eq!(repo.total_requested(), 3);
+    assert!(repo.attempt_get_descriptor_set(2).is_some());
+
+    // Forcing a reload marks it as pending again
+    repo.force_queue_load(1)?;
+    assert_eq!(repo.pending_count(), 1);
+    assert_eq!(repo.loaded_count(), 2);
+    assert_eq!(repo.total_requested(), 3);
+
+    repo.deactivate(&mut context);
+    common::deactivate(context)
+}
+
+#[test]
+#[ignore]
+fn loads_on_tex_load_queue() -> Result<()> {
+    let (_event_loop, window) = common::hidden_window();
+    let mut context = RenderingContext::new::<WithTexLoadQueue, ClearPass<Singular>>(
+        &window,
+        RendererOptions {
+            relaxed_properties: true,
+            ..RendererOptions::default()
+        },
+    )?;
+    if context.get_queue_family::<TexLoadQueue>()? == context.get_queue_family::<DrawQueue>()? {
+        log::warn!("No separate transfer family, so ownership transfers won't be tested");
+    }
+
+    // Blocks should be usable as soon as they're returned, without the caller doing anything for the ownership transfer
+    let mut repo: TextureRepo<TexturesPool, StagingPool> =
+        TextureRepo::new::<_, TexLoadQueue>(&mut context, load_config())?;
+    for block in 0..2 {
+        repo.queue_load(block)?;
+    }
+    wait_for_loads(&mut repo, 2);
+    assert!(repo.attempt_get_descriptor_set(0).is_some());
+    assert!(repo.attempt_get_descriptor_set(1).is_some());
+
+    repo.deactivate(&mut context);
+    common::deactivate(context)
+}